@@ -0,0 +1,407 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use goblin::pe::{
+    section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE},
+    symbol::{IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_CLASS_STATIC},
+    Coff, PE,
+};
+
+use crate::{reloc, util};
+
+const CALL: u8 = 0xE8;
+const JMP: u8 = 0xE9;
+
+/// bytes MSVC pads between functions with, or ends them with
+const PADDING: [u8; 2] = [0xCC, 0x90];
+const RET: u8 = 0xC3;
+
+/// shortest run of printable bytes treated as a string
+const MIN_STRING_LEN: usize = 4;
+
+/// a section of the executable holding code, with its raw data
+pub struct CodeSection<'a> {
+    pub rva: u32,
+    pub data: &'a [u8],
+}
+
+impl CodeSection<'_> {
+    pub fn contains(&self, rva: u32) -> bool {
+        rva >= self.rva && rva < self.rva + self.data.len() as u32
+    }
+}
+
+/// a function found in the executable's code
+#[derive(Debug)]
+pub struct Function {
+    pub rva: u32,
+    pub size: u32,
+    /// hash of the function's bytes with everything that changes when code moves around masked out
+    pub hash: blake3::Hash,
+    /// functions it calls, by RVA, in the order the calls appear
+    pub calls: Vec<u32>,
+    /// what its relocated pointers point to, by RVA, in the order they appear
+    pub pointers: Vec<u32>,
+    /// strings it references, in the order the references appear
+    pub strings: Vec<String>,
+}
+
+/// the bytes of a function in an object file, with the ones its relocations fill in left out
+#[derive(Debug)]
+pub struct Signature {
+    pub name: String,
+    pub bytes: Vec<u8>,
+    /// whether each byte is compared
+    pub mask: Vec<bool>,
+}
+
+impl Signature {
+    /// how many bytes are compared
+    pub fn significant(&self) -> usize {
+        self.mask.iter().filter(|&&significant| significant).count()
+    }
+
+    /// whether `data` starts with the function
+    pub fn matches(&self, data: &[u8]) -> bool {
+        data.len() >= self.bytes.len()
+            && self
+                .bytes
+                .iter()
+                .zip(self.mask.iter())
+                .zip(data.iter())
+                .all(|((byte, &significant), data_byte)| !significant || byte == data_byte)
+    }
+}
+
+/// the sections of `pe` which hold code, trimmed to the raw data that is in the file
+pub fn code_sections<'a>(file: &'a [u8], pe: &PE) -> Vec<CodeSection<'a>> {
+    pe.sections
+        .iter()
+        .filter(|sec| sec.characteristics & (IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE) != 0)
+        .filter_map(|sec| {
+            let size = match sec.virtual_size {
+                0 => sec.size_of_raw_data,
+                virtual_size => virtual_size.min(sec.size_of_raw_data),
+            };
+            let start = sec.pointer_to_raw_data as usize;
+            let data = file.get(start..(start + size as usize).min(file.len()))?;
+            Some(CodeSection {
+                rva: sec.virtual_address,
+                data,
+            })
+        })
+        .collect()
+}
+
+/// the bytes of `data`, which starts at `rva`, with base relocated addresses and the targets of
+/// relative calls and jumps into code zeroed, so the same code hashes the same wherever it is linked
+pub fn mask_code(data: &[u8], rva: u32, relocs: &reloc::BaseRelocations, code: &[CodeSection]) -> Vec<u8> {
+    let mut masked = data.to_vec();
+
+    for (&reloc_rva, _) in relocs.range(rva.saturating_sub(3)..rva + data.len() as u32) {
+        for byte_rva in reloc_rva..reloc_rva + 4 {
+            if byte_rva >= rva && byte_rva < rva + data.len() as u32 {
+                masked[(byte_rva - rva) as usize] = 0;
+            }
+        }
+    }
+
+    for (i, target) in relative_branches(data, rva) {
+        if code.iter().any(|sec| sec.contains(target)) {
+            masked[i + 1..i + 5].fill(0);
+        }
+    }
+
+    masked
+}
+
+/// every `call rel32` and `jmp rel32` looking byte in `data`, which starts at `rva`, as the index of
+/// the opcode and the RVA it branches to
+///
+/// without disassembling there is no telling opcodes from operands, so some of these are bogus,
+/// which is fine as long as the same bytes are treated the same way everywhere
+pub fn relative_branches(data: &[u8], rva: u32) -> impl Iterator<Item = (usize, u32)> + '_ {
+    data.windows(5).enumerate().filter_map(move |(i, window)| {
+        if window[0] != CALL && window[0] != JMP {
+            return None;
+        }
+
+        let rel = i32::from_le_bytes(window[1..5].try_into().unwrap());
+        let target = (rva as i64 + i as i64 + 5 + rel as i64).try_into().ok()?;
+        Some((i, target))
+    })
+}
+
+/// finds the functions in the executable's code
+///
+/// a function starts at the entry point, an export or any call or relocated pointer target that
+/// follows padding or a `ret`, or is called from more than one place, and runs until the next one
+/// starts, without the padding after it
+pub fn find_functions(file: &[u8], pe: &PE) -> Result<Vec<Function>, String> {
+    let code = code_sections(file, pe);
+    let relocs = reloc::parse_base_relocations(file, pe)?;
+
+    let byte_at = |rva: u32| {
+        code.iter()
+            .find(|sec| sec.contains(rva))
+            .map(|sec| sec.data[(rva - sec.rva) as usize])
+    };
+    let follows_padding = |rva: u32| {
+        code.iter().any(|sec| sec.rva == rva)
+            || byte_at(rva.wrapping_sub(1)).is_some_and(|byte| PADDING.contains(&byte) || byte == RET)
+    };
+
+    // data and forwarded exports point outside the code, where no function can start
+    let mut starts: BTreeSet<u32> = std::iter::once(pe.entry as u32)
+        .chain(pe.exports.iter().map(|export| export.rva as u32))
+        .filter(|&rva| byte_at(rva).is_some())
+        .collect();
+
+    let mut call_counts: BTreeMap<u32, usize> = BTreeMap::new();
+    for sec in code.iter() {
+        for (i, target) in relative_branches(sec.data, sec.rva) {
+            if sec.data[i] == CALL && byte_at(target).is_some() {
+                *call_counts.entry(target).or_default() += 1;
+            }
+        }
+    }
+    starts.extend(
+        call_counts
+            .into_iter()
+            .filter(|&(target, count)| count > 1 || follows_padding(target))
+            .map(|(target, _)| target),
+    );
+
+    starts.extend(
+        relocs
+            .keys()
+            .filter_map(|&reloc_rva| pointer_at(file, pe, reloc_rva))
+            .filter(|&target| byte_at(target).is_some() && follows_padding(target)),
+    );
+
+    let mut functions = Vec::new();
+    for sec in code.iter() {
+        let sec_end = sec.rva + sec.data.len() as u32;
+        let sec_starts: Vec<u32> = starts.range(sec.rva..sec_end).copied().collect();
+
+        for (start_i, &start) in sec_starts.iter().enumerate() {
+            let end = sec_starts.get(start_i + 1).copied().unwrap_or(sec_end);
+            let data = &sec.data[(start - sec.rva) as usize..(end - sec.rva) as usize];
+            let size = data.len() - data.iter().rev().take_while(|byte| PADDING.contains(byte)).count();
+            let data = &data[..size.max(1)];
+
+            let calls = relative_branches(data, start)
+                .filter(|&(i, target)| data[i] == CALL && starts.contains(&target))
+                .map(|(_, target)| target)
+                .collect();
+            let pointers: Vec<u32> = relocs
+                .range(start..start + data.len() as u32)
+                .filter_map(|(&reloc_rva, _)| pointer_at(file, pe, reloc_rva))
+                .collect();
+            let strings = pointers.iter().filter_map(|&pointer| string_at(file, pe, pointer)).collect();
+
+            functions.push(Function {
+                rva: start,
+                size: data.len() as u32,
+                hash: blake3::hash(&mask_code(data, start, &relocs, &code)),
+                calls,
+                pointers,
+                strings,
+            });
+        }
+    }
+
+    Ok(functions)
+}
+
+/// the RVA an absolute pointer at `rva` points to
+fn pointer_at(file: &[u8], pe: &PE, rva: u32) -> Option<u32> {
+    let offset = util::rva_to_file_offset(pe, rva as usize)?;
+    let pointer = u32::from_le_bytes(file.get(offset..offset + 4)?.try_into().unwrap());
+    (pointer as usize).checked_sub(pe.image_base)?.try_into().ok()
+}
+
+/// the printable NUL terminated string at `rva`, if there is one
+fn string_at(file: &[u8], pe: &PE, rva: u32) -> Option<String> {
+    let offset = util::rva_to_file_offset(pe, rva as usize)?;
+    let len = file.get(offset..)?.iter().position(|&byte| byte == 0)?;
+    let bytes = &file[offset..offset + len];
+
+    (len >= MIN_STRING_LEN && bytes.iter().all(|&byte| byte.is_ascii_graphic() || byte.is_ascii_whitespace()))
+        .then(|| String::from_utf8_lossy(bytes).to_string())
+}
+
+/// builds a signature for every function defined in the code sections of the COFF object in
+/// `obj_file`, a function running until the next symbol in its section or the section's end
+pub fn object_signatures(obj_file: &[u8]) -> Result<Vec<Signature>, String> {
+    let coff = Coff::parse(obj_file).map_err(|err| format!("failed to parse object file ({})", err))?;
+    let Some(symbols) = &coff.symbols else {
+        return Ok(Vec::new());
+    };
+
+    let mut signatures = Vec::new();
+    for (sec_i, sec) in coff.sections.iter().enumerate() {
+        if sec.characteristics & IMAGE_SCN_CNT_CODE == 0 || sec.pointer_to_raw_data == 0 {
+            continue;
+        }
+
+        let start = sec.pointer_to_raw_data as usize;
+        let data = obj_file
+            .get(start..start + sec.size_of_raw_data as usize)
+            .ok_or("object section extends past the end of the file")?;
+
+        let mut mask = vec![true; data.len()];
+        for reloc in sec
+            .relocations(obj_file)
+            .map_err(|err| format!("failed to parse object relocations ({})", err))?
+        {
+            let offset = (reloc.virtual_address - sec.virtual_address) as usize;
+            for significant in mask.iter_mut().skip(offset).take(4) {
+                *significant = false;
+            }
+        }
+
+        // section definitions share the section's name, which starts with a `.`
+        let mut functions: Vec<(u32, String)> = symbols
+            .iter()
+            .filter(|(_, _, symbol)| {
+                symbol.section_number == sec_i as i16 + 1
+                    && (symbol.storage_class == IMAGE_SYM_CLASS_EXTERNAL
+                        || (symbol.storage_class == IMAGE_SYM_CLASS_STATIC && !symbol.is_section_definition()))
+            })
+            .filter_map(|(_, name, symbol)| {
+                let name = match name {
+                    Some(name) => name.to_string(),
+                    None => symbol.name(coff.strings.as_ref()?).ok()?.to_string(),
+                };
+                (!name.starts_with('.') && !name.starts_with('$')).then_some((symbol.value, name))
+            })
+            .collect();
+        functions.sort();
+        functions.dedup_by_key(|(value, _)| *value);
+
+        for (function_i, (value, name)) in functions.iter().enumerate() {
+            let end = functions.get(function_i + 1).map_or(data.len(), |(next, _)| *next as usize);
+            let range = (*value as usize).min(end)..end;
+            if range.is_empty() {
+                continue;
+            }
+
+            signatures.push(Signature {
+                name: name.clone(),
+                bytes: data[range.clone()].to_vec(),
+                mask: mask[range].to_vec(),
+            });
+        }
+    }
+
+    Ok(signatures)
+}
+
+#[cfg(test)]
+mod tests {
+    use goblin::pe::{
+        header::COFF_MACHINE_X86,
+        relocation::IMAGE_REL_I386_DIR32,
+        section_table::{IMAGE_SCN_CNT_CODE, IMAGE_SCN_MEM_EXECUTE},
+        symbol::{IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_CLASS_STATIC},
+    };
+
+    use super::object_signatures;
+
+    /// `_f` loads a global, the static `_helper` returns its address and after a label `$LN3`
+    /// inside it comes `_long_function_name`, which returns zero
+    const CODE: [u8; 19] = [
+        0x55, 0x8B, 0xEC, 0xA1, 0x00, 0x00, 0x00, 0x00, 0x5D, 0xC3, // _f
+        0xB8, 0x00, 0x00, 0x00, 0x00, 0xC3, // _helper
+        0x33, 0xC0, 0xC3, // _long_function_name
+    ];
+
+    /// an object holding `CODE` in its `.text` section, as MSVC writes one
+    fn object() -> Vec<u8> {
+        let relocs: [u32; 2] = [4, 11];
+        let symbols: [(&str, u32, i16, u8, u8); 6] = [
+            (".text", 0, 1, IMAGE_SYM_CLASS_STATIC, 1),
+            ("_f", 0, 1, IMAGE_SYM_CLASS_EXTERNAL, 0),
+            ("_helper", 10, 1, IMAGE_SYM_CLASS_STATIC, 0),
+            ("$LN3", 12, 1, IMAGE_SYM_CLASS_STATIC, 0),
+            ("_long_function_name", 16, 1, IMAGE_SYM_CLASS_EXTERNAL, 0),
+            ("_global", 0, 0, IMAGE_SYM_CLASS_EXTERNAL, 0),
+        ];
+        let data_off = 20 + 40;
+        let relocs_off = data_off + CODE.len();
+        let symbols_off = relocs_off + relocs.len() * 10;
+
+        let mut file = Vec::new();
+        file.extend_from_slice(&COFF_MACHINE_X86.to_le_bytes());
+        file.extend_from_slice(&1u16.to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(&(symbols_off as u32).to_le_bytes());
+        file.extend_from_slice(&(symbols.len() as u32 + 1).to_le_bytes());
+        file.extend_from_slice(&[0; 4]);
+
+        let mut header = [0; 40];
+        header[..5].copy_from_slice(b".text");
+        header[16..20].copy_from_slice(&(CODE.len() as u32).to_le_bytes());
+        header[20..24].copy_from_slice(&(data_off as u32).to_le_bytes());
+        header[24..28].copy_from_slice(&(relocs_off as u32).to_le_bytes());
+        header[32..34].copy_from_slice(&(relocs.len() as u16).to_le_bytes());
+        header[36..40].copy_from_slice(&(IMAGE_SCN_CNT_CODE | IMAGE_SCN_MEM_EXECUTE).to_le_bytes());
+        file.extend_from_slice(&header);
+        file.extend_from_slice(&CODE);
+        for offset in relocs {
+            file.extend_from_slice(&offset.to_le_bytes());
+            file.extend_from_slice(&6u32.to_le_bytes());
+            file.extend_from_slice(&IMAGE_REL_I386_DIR32.to_le_bytes());
+        }
+
+        // names that don't fit in a symbol are in the string table, which follows it
+        let mut strings = Vec::new();
+        for (name, value, section_number, storage_class, aux_count) in symbols {
+            let mut symbol = [0; 18];
+            if name.len() <= 8 {
+                symbol[..name.len()].copy_from_slice(name.as_bytes());
+            } else {
+                symbol[4..8].copy_from_slice(&(4 + strings.len() as u32).to_le_bytes());
+                strings.extend_from_slice(name.as_bytes());
+                strings.push(0);
+            }
+            symbol[8..12].copy_from_slice(&value.to_le_bytes());
+            symbol[12..14].copy_from_slice(&section_number.to_le_bytes());
+            symbol[16] = storage_class;
+            symbol[17] = aux_count;
+            file.extend_from_slice(&symbol);
+            // the section definition's auxiliary record
+            if aux_count > 0 {
+                file.extend_from_slice(&[0; 18]);
+            }
+        }
+        file.extend_from_slice(&(4 + strings.len() as u32).to_le_bytes());
+        file.extend_from_slice(&strings);
+
+        file
+    }
+
+    #[test]
+    fn functions_of_an_object_run_to_the_next_symbol() {
+        let signatures = object_signatures(&object()).unwrap();
+
+        assert_eq!(
+            signatures.iter().map(|signature| (signature.name.as_str(), signature.bytes.as_slice())).collect::<Vec<_>>(),
+            [("_f", &CODE[..10]), ("_helper", &CODE[10..16]), ("_long_function_name", &CODE[16..])]
+        );
+        assert_eq!(signatures.iter().map(|signature| signature.significant()).collect::<Vec<_>>(), [6, 2, 3]);
+    }
+
+    #[test]
+    fn relocated_bytes_match_anything() {
+        let signatures = object_signatures(&object()).unwrap();
+        let f = &signatures[0];
+
+        // where the global was linked doesn't matter
+        assert!(f.matches(&[0x55, 0x8B, 0xEC, 0xA1, 0x78, 0x56, 0x34, 0x12, 0x5D, 0xC3, 0xCC]));
+        // every other byte does
+        assert!(!f.matches(&[0x55, 0x8B, 0xEC, 0xA1, 0x78, 0x56, 0x34, 0x12, 0x5D, 0xC2]));
+        // and so does running out of data
+        assert!(!f.matches(&[0x55, 0x8B, 0xEC, 0xA1, 0x78, 0x56, 0x34, 0x12, 0x5D]));
+    }
+}
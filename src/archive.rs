@@ -0,0 +1,106 @@
+use goblin::archive::Archive;
+
+/// a file stored in a `.lib` archive
+pub struct Member<'a> {
+    pub name: String,
+    pub data: &'a [u8],
+}
+
+/// reads every member of the archive in `file`, in the order they are stored, MSVC libraries can
+/// hold several members with the same name so they are not looked up by name
+pub fn members(file: &[u8]) -> Result<Vec<Member<'_>>, String> {
+    let archive = Archive::parse(file).map_err(|err| format!("failed to parse archive ({})", err))?;
+
+    let mut members = Vec::new();
+    for member_i in 0..archive.len() {
+        let Some(member) = archive.get_at(member_i) else {
+            continue;
+        };
+
+        let start = member.offset as usize;
+        let data = file
+            .get(start..start + member.size())
+            .ok_or_else(|| format!("archive member `{}` extends past the end of the archive", member.extended_name()))?;
+        members.push(Member {
+            name: member.extended_name().to_string(),
+            data,
+        });
+    }
+
+    Ok(members)
+}
+
+/// finds the member called `name`, which may also be just the file name of a member stored with
+/// its full path, as MSVC does
+pub fn find_member<'a, 'b>(members: &'a [Member<'b>], name: &str) -> Result<&'a Member<'b>, String> {
+    let file_name = |member: &Member| member.name.rsplit(['\\', '/']).next().unwrap_or_default().to_string();
+
+    match members.iter().find(|member| member.name == name) {
+        Some(member) => Ok(member),
+        None => {
+            let mut matching = members.iter().filter(|member| file_name(member) == name);
+            match (matching.next(), matching.next()) {
+                (Some(member), None) => Ok(member),
+                (Some(_), Some(_)) => Err(format!("more than one member is called `{}`, give its full name", name)),
+                (None, _) => Err(format!("there is no member `{}`", name)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_member, members};
+
+    /// an archive as MSVC's librarian writes one, members being stored with the path they were added
+    /// with, which is kept in the long names member
+    fn archive(members: &[(&str, &[u8])]) -> Vec<u8> {
+        let header = |name: &str, size: usize| format!("{:<16}{:<12}{:<6}{:<6}{:<8}{:<10}`\n", name, 0, "", "", 0, size);
+
+        let mut long_names = String::new();
+        let mut body = Vec::new();
+        for (name, data) in members {
+            body.extend_from_slice(header(&format!("/{}", long_names.len()), data.len()).as_bytes());
+            body.extend_from_slice(data);
+            // members start at even offsets
+            if !data.len().is_multiple_of(2) {
+                body.push(b'\n');
+            }
+            long_names += &format!("{}/\n", name);
+        }
+
+        let mut file = b"!<arch>\n".to_vec();
+        file.extend_from_slice(header("//", long_names.len()).as_bytes());
+        file.extend_from_slice(long_names.as_bytes());
+        if !long_names.len().is_multiple_of(2) {
+            file.push(b'\n');
+        }
+        file.extend_from_slice(&body);
+        file
+    }
+
+    #[test]
+    fn members_keep_their_order_full_names_and_data() {
+        let file = archive(&[("x86\\f.obj", b"odd"), ("x86\\g.obj", b"even"), ("x64\\f.obj", b"64")]);
+        let members = members(&file).unwrap();
+
+        assert_eq!(
+            members.iter().map(|member| (member.name.as_str(), member.data)).collect::<Vec<_>>(),
+            [("x86\\f.obj", &b"odd"[..]), ("x86\\g.obj", &b"even"[..]), ("x64\\f.obj", &b"64"[..])]
+        );
+    }
+
+    #[test]
+    fn members_are_found_by_full_or_unique_file_name() {
+        let file = archive(&[("x86\\f.obj", b"odd"), ("x86\\g.obj", b"even"), ("x64\\f.obj", b"64")]);
+        let members = members(&file).unwrap();
+
+        assert_eq!(find_member(&members, "x64\\f.obj").unwrap().data, b"64");
+        assert_eq!(find_member(&members, "g.obj").unwrap().data, b"even");
+        assert_eq!(
+            find_member(&members, "f.obj").err().unwrap(),
+            "more than one member is called `f.obj`, give its full name"
+        );
+        assert_eq!(find_member(&members, "h.obj").err().unwrap(), "there is no member `h.obj`");
+    }
+}
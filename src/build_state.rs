@@ -0,0 +1,217 @@
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tool::Tool;
+
+const STATE_FILE_NAME: &str = "gen_state.json";
+const TOOLCHAIN_FILE_NAME: &str = "toolchain.json";
+
+/// prefix cl puts before every file it includes when given `/showIncludes`
+const SHOW_INCLUDES_PREFIX: &str = "Note: including file:";
+
+/// what every compiled object in the build directory was built from, so `gen` can leave alone the
+/// ones none of it changed for
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct BuildState {
+    /// keyed by object path
+    objects: BTreeMap<String, ObjectState>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ObjectState {
+    args: Vec<String>,
+    /// hash of the tool's executable, or `None` if it couldn't be found
+    tool: Option<String>,
+    /// hashes of the source and every header it included, `None` for those that couldn't be read
+    inputs: BTreeMap<String, Option<String>>,
+}
+
+impl BuildState {
+    /// reads the state of `build_dir`, starting over if there is none or it can't be read
+    pub fn load(build_dir: &Path) -> BuildState {
+        fs::read_to_string(build_dir.join(STATE_FILE_NAME))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, build_dir: &Path) -> Result<(), String> {
+        let path = build_dir.join(STATE_FILE_NAME);
+        let json =
+            serde_json::to_string_pretty(self).map_err(|err| format!("failed to serialize build state ({})", err))?;
+        fs::write(&path, json).map_err(|err| format!("failed to write build state to `{}` ({})", path.display(), err))
+    }
+
+    /// whether `obj_path` exists and was built with `args` by the tool hashing to `tool` from inputs
+    /// that are still the same
+    pub fn is_up_to_date(&self, obj_path: &Path, args: &[String], tool: &Option<String>) -> bool {
+        let Some(state) = self.objects.get(&obj_path.display().to_string()) else {
+            return false;
+        };
+
+        obj_path.is_file()
+            && state.args == args
+            && tool.is_some()
+            && state.tool == *tool
+            && state
+                .inputs
+                .iter()
+                .all(|(path, hash)| hash.is_some() && hash_file(Path::new(path)) == *hash)
+    }
+
+    /// the source and headers `obj_path` was last built from
+    pub fn inputs(&self, obj_path: &Path) -> Vec<PathBuf> {
+        self.objects
+            .get(&obj_path.display().to_string())
+            .map(|state| state.inputs.keys().map(PathBuf::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// remembers that `obj_path` was just built with `args` by the tool hashing to `tool` from `inputs`
+    pub fn record(&mut self, obj_path: &Path, args: Vec<String>, tool: Option<String>, inputs: &[PathBuf]) {
+        let inputs = inputs
+            .iter()
+            .map(|path| (path.display().to_string(), hash_file(path)))
+            .collect();
+        self.objects.insert(obj_path.display().to_string(), ObjectState { args, tool, inputs });
+    }
+}
+
+/// the tools `doctor` last found, so the next run can tell when one of them changed
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Toolchain {
+    /// keyed by what the tool is used as, `compiler` and so on
+    tools: BTreeMap<String, ToolState>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ToolState {
+    pub path: String,
+    /// the version line the tool printed
+    pub version: String,
+    /// hash of the tool's executable, or `None` if it couldn't be found
+    pub hash: Option<String>,
+}
+
+impl Toolchain {
+    /// reads the toolchain recorded in `build_dir`, starting over if there is none or it can't be read
+    pub fn load(build_dir: &Path) -> Toolchain {
+        fs::read_to_string(build_dir.join(TOOLCHAIN_FILE_NAME))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, build_dir: &Path) -> Result<(), String> {
+        let path = build_dir.join(TOOLCHAIN_FILE_NAME);
+        let json =
+            serde_json::to_string_pretty(self).map_err(|err| format!("failed to serialize toolchain ({})", err))?;
+        fs::write(&path, json).map_err(|err| format!("failed to write toolchain to `{}` ({})", path.display(), err))
+    }
+
+    /// records `state` as the tool used as `label`, returning what was recorded before if it differs
+    pub fn update(&mut self, label: &str, state: ToolState) -> Option<ToolState> {
+        self.tools.insert(label.to_string(), state.clone()).filter(|last| *last != state)
+    }
+}
+
+fn hash_file(path: &Path) -> Option<String> {
+    fs::read(path).ok().map(|data| blake3::hash(&data).to_string())
+}
+
+/// hash of the executable `tool` runs, found the way the OS would, through `PATH` if it has no
+/// directory, and through the wine prefix if it is a wrapped tool with a Windows path
+pub fn hash_tool(tool: &Tool) -> Option<String> {
+    let path = tool.host_path(&tool.path);
+    if path.components().count() > 1 {
+        return hash_file(&path);
+    }
+
+    env::split_paths(&env::var_os("PATH")?)
+        .flat_map(|dir| [dir.join(&path), dir.join(format!("{}.exe", path.display()))])
+        .find(|path| path.is_file())
+        .and_then(|path| hash_file(&path))
+}
+
+/// the files cl given `/showIncludes` says it included
+pub fn parse_show_includes(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| Some(line.strip_prefix(SHOW_INCLUDES_PREFIX)?.trim().to_string()))
+        .collect()
+}
+
+/// a make rule with `inputs` as the prerequisites of `target`, as gcc writes with `-MD`
+pub fn depfile(target: &Path, inputs: &[PathBuf]) -> String {
+    let escape = |path: &Path| path.display().to_string().replace(' ', "\\ ");
+    let inputs: Vec<String> = inputs.iter().map(|path| escape(path)).collect();
+
+    format!("{}: {}\n", escape(target), inputs.join(" "))
+}
+
+/// the prerequisites of a make rule written by gcc or clang with `-MD`
+pub fn parse_depfile(text: &str) -> Vec<String> {
+    let text = text.replace("\\\r\n", " ").replace("\\\n", " ");
+
+    // the target can hold a drive letter, but its colon is never followed by whitespace
+    let Some(colon) = text
+        .char_indices()
+        .zip(text.chars().skip(1))
+        .find(|&((_, c), next)| c == ':' && next.is_whitespace())
+        .map(|((i, _), _)| i)
+    else {
+        return Vec::new();
+    };
+
+    let mut paths = Vec::new();
+    let mut path = String::new();
+    let mut chars = text[colon + 1..].chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&' ') => path.push(chars.next().unwrap()),
+            c if c.is_whitespace() => {
+                if !path.is_empty() {
+                    paths.push(std::mem::take(&mut path));
+                }
+            }
+            c => path.push(c),
+        }
+    }
+    if !path.is_empty() {
+        paths.push(path);
+    }
+
+    paths
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::{depfile, parse_depfile, parse_show_includes};
+
+    #[test]
+    fn parses_depfile_with_drive_letters_and_escaped_spaces() {
+        let text = "C:\\project\\build\\f.obj: C:\\project\\src\\my\\ file.c \\\n  C:\\VC98\\INCLUDE\\stdio.h\n";
+        assert_eq!(parse_depfile(text), ["C:\\project\\src\\my file.c", "C:\\VC98\\INCLUDE\\stdio.h"]);
+    }
+
+    #[test]
+    fn written_depfile_parses_back() {
+        let inputs = [PathBuf::from("src/my file.c"), PathBuf::from("include/a.h")];
+        let text = depfile(Path::new("build/f.obj"), &inputs);
+        assert_eq!(parse_depfile(&text), ["src/my file.c", "include/a.h"]);
+    }
+
+    #[test]
+    fn parses_show_includes() {
+        let output = "f.c\nNote: including file: C:\\VC98\\INCLUDE\\stdio.h\n\
+                      Note: including file:  C:\\project\\include\\a.h\nf.c(3) : warning C4101: 'y' : unreferenced local variable\n";
+        assert_eq!(parse_show_includes(output), ["C:\\VC98\\INCLUDE\\stdio.h", "C:\\project\\include\\a.h"]);
+    }
+}
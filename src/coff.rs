@@ -0,0 +1,99 @@
+use goblin::pe::{
+    section_table::IMAGE_SCN_ALIGN_1BYTES,
+    symbol::{IMAGE_SYM_CLASS_EXTERNAL, IMAGE_SYM_DTYPE_FUNCTION},
+};
+
+const HEADER_SIZE: usize = 20;
+const SECTION_HEADER_SIZE: usize = 40;
+const RELOCATION_SIZE: usize = 10;
+
+/// a COFF object holding one section, for handing bytes from the executable to tools that read
+/// objects
+pub struct Object {
+    pub machine: u16,
+    pub section_name: String,
+    pub characteristics: u32,
+    pub data: Vec<u8>,
+    pub symbols: Vec<ObjectSymbol>,
+    pub relocations: Vec<ObjectRelocation>,
+}
+
+pub struct ObjectSymbol {
+    pub name: String,
+    /// offset into the section, or `None` for a symbol defined elsewhere
+    pub value: Option<u32>,
+    pub function: bool,
+}
+
+pub struct ObjectRelocation {
+    pub offset: u32,
+    /// index into `Object::symbols`
+    pub symbol: usize,
+    pub typ: u16,
+}
+
+impl Object {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let data_start = HEADER_SIZE + SECTION_HEADER_SIZE;
+        let relocations_start = data_start + self.data.len();
+        let symbols_start = relocations_start + self.relocations.len() * RELOCATION_SIZE;
+
+        let mut file = Vec::new();
+        file.extend_from_slice(&self.machine.to_le_bytes());
+        file.extend_from_slice(&1u16.to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(&(symbols_start as u32).to_le_bytes());
+        file.extend_from_slice(&(self.symbols.len() as u32).to_le_bytes());
+        file.extend_from_slice(&0u16.to_le_bytes());
+        file.extend_from_slice(&0u16.to_le_bytes());
+
+        let mut section_name = [0u8; 8];
+        let name_len = self.section_name.len().min(8);
+        section_name[..name_len].copy_from_slice(&self.section_name.as_bytes()[..name_len]);
+        file.extend_from_slice(&section_name);
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        file.extend_from_slice(&(data_start as u32).to_le_bytes());
+        file.extend_from_slice(&(relocations_start as u32).to_le_bytes());
+        file.extend_from_slice(&0u32.to_le_bytes());
+        file.extend_from_slice(&(self.relocations.len() as u16).to_le_bytes());
+        file.extend_from_slice(&0u16.to_le_bytes());
+        file.extend_from_slice(&(self.characteristics | IMAGE_SCN_ALIGN_1BYTES).to_le_bytes());
+
+        file.extend_from_slice(&self.data);
+
+        for relocation in self.relocations.iter() {
+            file.extend_from_slice(&relocation.offset.to_le_bytes());
+            file.extend_from_slice(&(relocation.symbol as u32).to_le_bytes());
+            file.extend_from_slice(&relocation.typ.to_le_bytes());
+        }
+
+        // names longer than eight bytes go in the string table, which starts with its own size
+        let mut strings = Vec::new();
+        for symbol in self.symbols.iter() {
+            if symbol.name.len() <= 8 {
+                let mut name = [0u8; 8];
+                name[..symbol.name.len()].copy_from_slice(symbol.name.as_bytes());
+                file.extend_from_slice(&name);
+            } else {
+                file.extend_from_slice(&0u32.to_le_bytes());
+                file.extend_from_slice(&(4 + strings.len() as u32).to_le_bytes());
+                strings.extend_from_slice(symbol.name.as_bytes());
+                strings.push(0);
+            }
+
+            file.extend_from_slice(&symbol.value.unwrap_or(0).to_le_bytes());
+            file.extend_from_slice(&(symbol.value.is_some() as i16).to_le_bytes());
+            let typ = if symbol.function { IMAGE_SYM_DTYPE_FUNCTION << 4 } else { 0 };
+            file.extend_from_slice(&typ.to_le_bytes());
+            file.push(IMAGE_SYM_CLASS_EXTERNAL);
+            file.push(0);
+        }
+
+        file.extend_from_slice(&(4 + strings.len() as u32).to_le_bytes());
+        file.extend_from_slice(&strings);
+
+        file
+    }
+}
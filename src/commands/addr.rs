@@ -0,0 +1,112 @@
+use std::fs;
+
+use clap::{Args, ValueEnum};
+use goblin::pe::PE;
+
+use crate::util;
+
+use super::CommandExecute;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum AddrKind {
+    Va,
+    Rva,
+    Offset,
+}
+
+#[derive(Debug, Args)]
+pub struct AddrArgs {
+    /// address to look up, in decimal or `0x` prefixed hex
+    pub value: String,
+    /// how to interpret the value, by default a VA if it is at or above the image base and an RVA otherwise
+    #[arg(long, value_enum)]
+    pub from: Option<AddrKind>,
+}
+
+impl CommandExecute for AddrArgs {
+    fn execute(&self) -> Result<(), String> {
+        let config = util::get_config()?;
+
+        let file = fs::read(&config.executable)
+            .map_err(|err| format!("failed to open executable ({})", err))?;
+
+        let pe = PE::parse(&file).map_err(|err| format!("failed to parse executable ({})", err))?;
+
+        let value = util::parse_int(&self.value)
+            .map_err(|err| format!("invalid address `{}` ({})", self.value, err))? as usize;
+
+        let kind = self.from.unwrap_or(if value >= pe.image_base {
+            AddrKind::Va
+        } else {
+            AddrKind::Rva
+        });
+
+        let rva = match kind {
+            AddrKind::Va => value
+                .checked_sub(pe.image_base)
+                .ok_or_else(|| format!("VA `0x{:X}` is below the image base `0x{:X}`", value, pe.image_base))?,
+            AddrKind::Rva => value,
+            AddrKind::Offset => util::file_offset_to_rva(&pe, value)
+                .ok_or_else(|| format!("file offset `0x{:X}` is not inside any section", value))?,
+        };
+        let addr_virtual = pe.image_base + rva;
+
+        println!("va:      0x{:X}", addr_virtual);
+        println!("rva:     0x{:X}", rva);
+        match util::rva_to_file_offset(&pe, rva) {
+            Some(offset) => println!("offset:  0x{:X}", offset),
+            None => println!("offset:  none, not backed by file data"),
+        }
+
+        let sec = pe.sections.iter().find(|sec| {
+            rva >= sec.virtual_address as usize
+                && rva < (sec.virtual_address + sec.virtual_size.max(sec.size_of_raw_data)) as usize
+        });
+        match sec {
+            Some(sec) => println!(
+                "section: {} + 0x{:X}",
+                sec.name().map_err(|err| format!("failed to get section name ({})", err))?,
+                rva - sec.virtual_address as usize
+            ),
+            None => println!("section: none"),
+        }
+
+        match config.find_unit(addr_virtual) {
+            Some((_, unit_i, unit)) => println!(
+                "unit:    {} + 0x{:X}, {} {}{}",
+                unit_i,
+                addr_virtual - unit.addr_virtual,
+                unit.kind,
+                unit.status()?,
+                unit.file
+                    .as_ref()
+                    .map(|file| format!(" `{}`", file))
+                    .unwrap_or_default()
+            ),
+            None => println!("unit:    none"),
+        }
+
+        // configured symbols, exports and import address table slots are all fair game
+        let symbols = config
+            .symbols
+            .iter()
+            .map(|symbol| (symbol.name.clone(), symbol.addr_virtual))
+            .chain(pe.exports.iter().filter_map(|export| {
+                export.name.map(|name| (name.to_string(), pe.image_base + export.rva))
+            }))
+            .chain(pe.imports.iter().map(|import| {
+                (format!("__imp_{}", import.name), pe.image_base + import.rva)
+            }));
+
+        match symbols
+            .filter(|(_, symbol_addr)| *symbol_addr <= addr_virtual)
+            .max_by_key(|(_, symbol_addr)| *symbol_addr)
+        {
+            Some((name, symbol_addr)) if symbol_addr == addr_virtual => println!("symbol:  {}", name),
+            Some((name, symbol_addr)) => println!("symbol:  {} + 0x{:X}", name, addr_virtual - symbol_addr),
+            None => println!("symbol:  none"),
+        }
+
+        Ok(())
+    }
+}
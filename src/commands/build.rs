@@ -0,0 +1,156 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use clap::Args;
+
+use crate::{
+    config::{Config, Workspace},
+    tool_diagnostic::{self, MessageFormat},
+    util,
+};
+
+use super::CommandExecute;
+
+#[derive(Debug, Args)]
+pub struct BuildArgs {
+    /// link the compiled code of nonmatching units instead of their original bytes
+    #[arg(long)]
+    pub use_nonmatching: bool,
+}
+
+enum TargetStatus {
+    Matching,
+    Differs,
+    Failed(&'static str),
+    /// built, but the output couldn't be compared with the original
+    Unchecked(String),
+}
+
+impl CommandExecute for BuildArgs {
+    /// runs split, gen, link and patch-exe for this project, or for every member of a workspace, and
+    /// for every version unless `--version` picks one
+    fn execute(&self) -> Result<(), String> {
+        let dirs = match Workspace::load(util::config_path())? {
+            Some(workspace) => workspace.member_dirs(),
+            None => vec![PathBuf::from(".")],
+        };
+
+        let mut targets = Vec::new();
+        for dir in dirs {
+            let versions = match util::version() {
+                Some(version) => vec![Some(version.to_string())],
+                None => {
+                    // a member whose config can't be read is tried as a single target, so its own
+                    // split reports the error and fails just that target
+                    let names = Config::version_names(&dir.join(util::config_path())).unwrap_or_default();
+                    if names.is_empty() {
+                        vec![None]
+                    } else {
+                        names.into_iter().map(Some).collect()
+                    }
+                }
+            };
+            targets.extend(versions.into_iter().map(|version| (dir.clone(), version)));
+        }
+
+        let pod_path = env::current_exe().map_err(|err| format!("failed to find the pod executable ({})", err))?;
+
+        let mut statuses = Vec::new();
+        for (target, version) in targets.iter() {
+            let dir_name = target_name(target);
+            let target_name = match version {
+                Some(version) => format!("{} ({})", dir_name, version),
+                None => dir_name.clone(),
+            };
+            tool_diagnostic::status(format_args!("building target `{}`", target_name));
+
+            // an explicit build directory is shared, so each target gets its own corner of it
+            let build_dir = util::build_dir_override().map(|build_dir| build_dir.join(&dir_name));
+
+            let mut status = None;
+            for step in ["split", "gen", "link", "patch-exe"] {
+                let mut command = Command::new(&pod_path);
+                // members' configs are named like the workspace's
+                command.arg("-C").arg(target).arg("--config").arg(util::config_path()).arg(step);
+                if let Some(build_dir) = &build_dir {
+                    command.arg("--build-dir").arg(build_dir);
+                }
+                if let Some(version) = version {
+                    command.arg("--version").arg(version);
+                }
+                if tool_diagnostic::message_format() == MessageFormat::Json {
+                    command.arg("--message-format").arg("json");
+                }
+                if self.use_nonmatching && matches!(step, "gen" | "link" | "patch-exe") {
+                    command.arg("--use-nonmatching");
+                }
+
+                let step_status = command
+                    .status()
+                    .map_err(|err| format!("failed to run `{}` for target `{}` ({})", step, target_name, err))?;
+                if !step_status.success() {
+                    status = Some(TargetStatus::Failed(step));
+                    break;
+                }
+            }
+
+            let status = match status {
+                Some(status) => status,
+                None => {
+                    let check = || -> Result<TargetStatus, String> {
+                        let config = Config::load(&target.join(util::config_path()), version.as_deref())?;
+                        let output_path = build_dir
+                            .unwrap_or_else(|| target.join("build"))
+                            .join(version.as_deref().unwrap_or(""))
+                            .join(config.output_name()?);
+                        let output = fs::read(&output_path).map_err(|err| {
+                            format!("failed to open built executable `{}` ({})", output_path.display(), err)
+                        })?;
+
+                        Ok(if blake3::hash(&output).to_string() == config.hash {
+                            TargetStatus::Matching
+                        } else {
+                            TargetStatus::Differs
+                        })
+                    };
+                    check().unwrap_or_else(TargetStatus::Unchecked)
+                }
+            };
+            statuses.push((target_name, status));
+        }
+
+        tool_diagnostic::status("");
+        let name_width = statuses.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        for (name, status) in statuses.iter() {
+            let status = match status {
+                TargetStatus::Matching => "matching".to_string(),
+                TargetStatus::Differs => "built, but does not match the original".to_string(),
+                TargetStatus::Failed(step) => format!("failed at `{}`", step),
+                TargetStatus::Unchecked(err) => format!("built, but could not be checked: {}", err),
+            };
+            tool_diagnostic::status(format_args!("{:<width$}  {}", name, status, width = name_width));
+        }
+
+        let failed = statuses
+            .iter()
+            .filter(|(_, status)| matches!(status, TargetStatus::Failed(_) | TargetStatus::Unchecked(_)))
+            .count();
+        if failed > 0 {
+            return Err(format!("{} of {} targets failed to build", failed, statuses.len()));
+        }
+
+        Ok(())
+    }
+}
+
+/// the name a target is reported under, its directory name
+fn target_name(target: &Path) -> String {
+    target
+        .canonicalize()
+        .ok()
+        .and_then(|target| Some(target.file_name()?.to_string_lossy().to_string()))
+        .unwrap_or_else(|| target.display().to_string())
+}
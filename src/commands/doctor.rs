@@ -0,0 +1,299 @@
+use std::{fs, path::Path, process::Stdio};
+
+use clap::Args;
+use goblin::pe::PE;
+
+use crate::{
+    archive,
+    build_state::{self, Toolchain, ToolState},
+    config::Config,
+    rich::{self, RichEntry, ToolKind},
+    tool::Tool,
+    tool_diagnostic, util,
+};
+
+use super::{gen, CommandExecute};
+
+#[derive(Debug, Args)]
+pub struct DoctorArgs {}
+
+/// problems found so far, each printed with how to fix it
+#[derive(Default)]
+struct Report {
+    problems: usize,
+}
+
+impl Report {
+    fn ok(&mut self, message: String) {
+        tool_diagnostic::status(format_args!("  ok       {}", message));
+    }
+
+    fn problem(&mut self, message: String, fix: String) {
+        tool_diagnostic::status(format_args!("  problem  {}", message));
+        tool_diagnostic::status(format_args!("           fix: {}", fix));
+        self.problems += 1;
+    }
+}
+
+impl CommandExecute for DoctorArgs {
+    /// checks that everything the build needs is in place, before `gen` finds out halfway through
+    fn execute(&self) -> Result<(), String> {
+        let configs = match util::version() {
+            Some(_) => vec![util::get_config()?],
+            None => {
+                let names = Config::version_names(util::config_path())?;
+                if names.is_empty() {
+                    vec![util::get_config()?]
+                } else {
+                    names
+                        .iter()
+                        .map(|name| Config::load(util::config_path(), Some(name)))
+                        .collect::<Result<Vec<_>, String>>()?
+                }
+            }
+        };
+
+        let mut report = Report::default();
+        let mut entries = None;
+        for config in configs.iter() {
+            match &config.version {
+                Some(version) => tool_diagnostic::status(format_args!("version `{}`", version)),
+                None => tool_diagnostic::status("executable"),
+            }
+            entries = check_executable(config, &mut report).or(entries);
+            check_files(config, &mut report);
+        }
+
+        // every version is built by the same toolchain
+        let config = &configs[0];
+        let expected = |kind| entries.as_deref().and_then(|entries| rich::primary_tool(entries, kind));
+        let mut toolchain = Toolchain::load(util::build_dir());
+        tool_diagnostic::status("toolchain");
+        check_tool(&config.assembler(), "assembler", "assembler_path", false, expected(ToolKind::Assembler), &mut toolchain, &mut report);
+        let gnu_compiler = gen::is_gnu_compiler(config.compiler_path());
+        check_tool(&config.compiler(), "compiler", "compiler_path", gnu_compiler, expected(ToolKind::Compiler), &mut toolchain, &mut report);
+        check_tool(&config.linker(), "linker", "linker_path", true, None, &mut toolchain, &mut report);
+
+        tool_diagnostic::status("build directory");
+        if check_build_dir(&mut report) {
+            toolchain.save(util::build_dir())?;
+        }
+
+        match report.problems {
+            0 => {
+                tool_diagnostic::status("\nno problems found");
+                Ok(())
+            }
+            1 => Err("found 1 problem".to_string()),
+            problems => Err(format!("found {} problems", problems)),
+        }
+    }
+}
+
+/// checks that the executable is there and is the one the config describes, returning its rich header
+fn check_executable(config: &Config, report: &mut Report) -> Option<Vec<RichEntry>> {
+    let file = match fs::read(&config.executable) {
+        Ok(file) => file,
+        Err(err) => {
+            report.problem(
+                format!("executable `{}` can't be read ({})", config.executable, err),
+                format!("put the original executable at `{}`, or set `executable` to where it is", config.executable),
+            );
+            return None;
+        }
+    };
+
+    let hash = blake3::hash(&file).to_string();
+    if hash == config.hash {
+        report.ok(format!("executable `{}` matches `hash`", config.executable));
+    } else {
+        report.problem(
+            format!("executable `{}` has hash `{}`, not `{}`", config.executable, hash, config.hash),
+            "this is a different build of the executable than the config describes, find the one with the hash in the config"
+                .to_string(),
+        );
+    }
+
+    let pe = match PE::parse(&file) {
+        Ok(pe) => pe,
+        Err(err) => {
+            report.problem(
+                format!("executable `{}` can't be parsed ({})", config.executable, err),
+                "point `executable` at the original PE executable".to_string(),
+            );
+            return None;
+        }
+    };
+
+    match config.check(&pe) {
+        Ok(()) => report.ok("units cover the executable's sections".to_string()),
+        Err(err) => report.problem(
+            format!("units don't fit the executable:\n{}", err),
+            "fix the units pointed out, `pod unit` splits, merges and moves them, and a unit whose `file` is \
+             missing can be made a copy unit with `pod unit set-kind <addr> copy`"
+                .to_string(),
+        ),
+    }
+
+    rich::rich_entries(&pe).ok().flatten()
+}
+
+/// checks that every file the units and include directories name is there
+fn check_files(config: &Config, report: &mut Report) {
+    let mut found = 0;
+    let mut missing = 0;
+    for sec in config.sections.iter() {
+        for (unit_i, unit) in sec.units.iter().enumerate() {
+            // split extracts resources into their directory when it doesn't exist yet
+            let Some(file) = unit.file.as_ref().filter(|_| unit.kind != "rsrc") else {
+                continue;
+            };
+
+            // `check` has already reported it with where it is in the config
+            if !Path::new(file).exists() {
+                missing += 1;
+                continue;
+            }
+
+            if let (Some(member), "lib-member") = (&unit.member, unit.kind.as_str()) {
+                let member_found = fs::read(file)
+                    .map_err(|err| format!("can't be read ({})", err))
+                    .and_then(|lib_file| {
+                        let members = archive::members(&lib_file)?;
+                        archive::find_member(&members, member).map(|_| ())
+                    });
+                if let Err(err) = member_found {
+                    report.problem(
+                        format!("section `{}`, unit `{}` library `{}`: {}", sec.name, unit_i, file, err),
+                        "set the unit's `member` to one of the library's members, `pod identify` suggests them".to_string(),
+                    );
+                    missing += 1;
+                    continue;
+                }
+            }
+
+            found += 1;
+        }
+    }
+    if missing == 0 {
+        report.ok(format!("all {} unit files exist", found));
+    }
+
+    for include_dir in config.include_dirs() {
+        if !include_dir.is_dir() {
+            report.problem(
+                format!("include directory `{}` doesn't exist", include_dir.display()),
+                "create it, or remove it from `include_dirs`".to_string(),
+            );
+        }
+    }
+}
+
+/// checks that `tool` runs and reports a version, which for MSVC tools should be the build in the
+/// rich header, `expected`, and records it in `toolchain` to point out when it changed since the last run
+fn check_tool(
+    tool: &Tool,
+    label: &str,
+    key: &str,
+    gnu: bool,
+    expected: Option<&RichEntry>,
+    toolchain: &mut Toolchain,
+    report: &mut Report,
+) {
+    let mut command = tool.command();
+    if gnu {
+        command.arg("--version");
+    }
+
+    // MSVC tools print their version before complaining about having nothing to do
+    let output = match command.stdin(Stdio::null()).output() {
+        Ok(output) => output,
+        Err(err) => {
+            let fix = match &tool.wrapper {
+                Some(wrapper) => format!("check that `{}` is installed and that `{}` points at the {}", wrapper, key, label),
+                None => format!("install it and put it on `PATH`, or set `{}` to where it is", key),
+            };
+            report.problem(format!("{} `{}` can't be run ({})", label, tool.path, err), fix);
+            return;
+        }
+    };
+
+    let text = format!("{}\n{}", String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    let version = match gnu {
+        true => text.lines().map(str::trim).find(|line| !line.is_empty()),
+        false => text.lines().map(str::trim).find(|line| line.contains("Version ")),
+    };
+    let Some(version) = version else {
+        report.problem(
+            format!("{} `{}` runs, but reports no version", label, tool.path),
+            format!("make sure `{}` points at the {} itself", key, label),
+        );
+        return;
+    };
+
+    let state = ToolState {
+        path: tool.path.clone(),
+        version: version.to_string(),
+        hash: build_state::hash_tool(tool),
+    };
+    let hash = match &state.hash {
+        Some(hash) => format!("binary `{}`", hash),
+        None => "binary not found to hash".to_string(),
+    };
+    let changed = match toolchain.update(label, state) {
+        Some(last) => format!(
+            ", changed since the last check from `{}` `{}` with {}",
+            last.path,
+            last.version,
+            match last.hash {
+                Some(hash) => format!("binary `{}`", hash),
+                None => "no binary".to_string(),
+            }
+        ),
+        None => String::new(),
+    };
+    match expected.filter(|_| !gnu) {
+        Some(entry) if msvc_build(version) != Some(entry.build) => report.problem(
+            format!(
+                "{} `{}` is `{}`, but the executable was built by build {} ({}){}",
+                label,
+                tool.path,
+                version,
+                entry.build,
+                entry.release().unwrap_or("unknown release"),
+                changed
+            ),
+            format!("code built by another build is unlikely to match, set `{}` to the {} of that build", key, label),
+        ),
+        _ => report.ok(format!("{} `{}` is `{}`, {}{}", label, tool.path, version, hash, changed)),
+    }
+}
+
+/// the build number in an MSVC banner, `Version 12.00.8804` being build 8804
+fn msvc_build(banner: &str) -> Option<u16> {
+    let (_, version) = banner.split_once("Version ")?;
+    version.split_whitespace().next()?.split('.').nth(2)?.parse().ok()
+}
+
+/// checks that the build directory can be written to, creating it like `split` would
+fn check_build_dir(report: &mut Report) -> bool {
+    let build_dir = util::build_dir();
+    let probe_path = build_dir.join(".pod_doctor");
+    let writable = fs::create_dir_all(build_dir)
+        .and_then(|_| fs::write(&probe_path, b""))
+        .and_then(|_| fs::remove_file(&probe_path));
+
+    match writable {
+        Ok(()) => {
+            report.ok(format!("build directory `{}` is writable", build_dir.display()));
+            true
+        }
+        Err(err) => {
+            report.problem(
+                format!("build directory `{}` isn't writable ({})", build_dir.display(), err),
+                "make it writable, or build somewhere else with `--build-dir`".to_string(),
+            );
+            false
+        }
+    }
+}
@@ -1,14 +1,50 @@
-use std::{fs, path::Path, process::Command};
+use std::{
+    fs::{self, File},
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use clap::Args;
 use goblin::pe::PE;
+use serde::Serialize;
 
-use crate::util;
+use crate::{
+    archive,
+    build_state::{self, BuildState},
+    config::Config,
+    rsrc,
+    tool::Tool,
+    tool_diagnostic, util,
+};
 
-use super::CommandExecute;
+use super::{unit, CommandExecute};
+
+/// where editors and clangd look for how each source file is compiled, in the project root
+const COMPILE_COMMANDS_PATH: &str = "compile_commands.json";
+
+/// clang flags standing in for the MSVC environment `cl` compiles in
+const CLANG_MSVC_ARGS: [&str; 3] = ["--target=i686-pc-windows-msvc", "-fms-extensions", "-fms-compatibility"];
 
 #[derive(Debug, Args)]
-pub struct GenArgs {}
+pub struct GenArgs {
+    /// build only the object of the unit containing this virtual address, without the copy of its
+    /// original bytes
+    #[arg(long, value_name = "ADDR")]
+    pub unit: Option<String>,
+    /// fail when nonmatching units don't build, as their compiled code is linked instead of their
+    /// original bytes
+    #[arg(long)]
+    pub use_nonmatching: bool,
+}
+
+/// an entry of a clang compilation database
+#[derive(Debug, Serialize)]
+struct CompileCommand {
+    directory: String,
+    file: String,
+    arguments: Vec<String>,
+    output: String,
+}
 
 impl CommandExecute for GenArgs {
     fn execute(&self) -> Result<(), String> {
@@ -18,8 +54,24 @@ impl CommandExecute for GenArgs {
             .map_err(|err| format!("failed to open executable ({})", err))?;
 
         let pe = PE::parse(&file).map_err(|err| format!("failed to parse executable ({})", err))?;
+        config.check(&pe)?;
 
-        let build_dir = Path::new("build");
+        let build_dir = util::build_dir();
+        let include_dirs = config.include_dirs();
+
+        let only = match &self.unit {
+            Some(addr) => Some(unit::find_unit(&config, unit::parse_addr(addr)?)?),
+            None => {
+                write_compile_commands(&config, build_dir, &include_dirs)?;
+                None
+            }
+        };
+
+        let assembler = config.assembler();
+        let compiler = config.compiler();
+
+        let mut state = BuildState::load(build_dir);
+        let compiler_hash = build_state::hash_tool(&compiler);
 
         for sec in pe.sections.iter() {
             let sec_name = sec
@@ -27,62 +79,143 @@ impl CommandExecute for GenArgs {
                 .map_err(|err| format!("failed to get section name ({})", err))?;
 
             if let Some(cfg_sec) = config.sections.iter().find(|i_sec| i_sec.name == sec_name) {
-                let mut unit_i = 0;
-                for unit in cfg_sec.units.iter() {
-                    match unit.kind.as_str() {
-                        "copy" => {
-                            let asm_path =
-                                build_dir.join(format!("{}_copy_{}.asm", sec_name, unit_i));
-
-                            let asm_command = Command::new(&config.assembler_path)
-                                .arg(format!(
-                                    "/Fo{}",
-                                    build_dir.join(format!("{}_copy_{}.obj", sec_name, unit_i)).display()
-                                ))
-                                .arg("/c")
-                                .arg(&asm_path)
-                                .output()
-                                .map_err(|err| {
-                                    format!("failed to execute copy asm command for section `{}`, unit `{}` ({})", sec_name, unit_i, err)
-                                })?;
+                for (unit_i, unit) in cfg_sec.units.iter().enumerate() {
+                    if only.is_some_and(|(only_sec_i, only_unit_i)| {
+                        config.sections[only_sec_i].name != sec_name || only_unit_i != unit_i
+                    }) {
+                        continue;
+                    }
 
-                            if asm_command.status.success() {
-                                println!("assembled copy unit for section `{}`, unit `{}`", sec_name, unit_i);
-                            } else {
-                                return Err(format!(
-                                    "copy assembly of section `{}`, unit `{}` failed ({})",
-                                    sec_name,
-                                    unit_i,
-                                    String::from_utf8_lossy(&asm_command.stdout)
-                                ));
-                            }
-                        },
+                    let status = unit.status().map_err(|err| {
+                        format!("section `{}`, unit `{}` has {}", sec_name, unit_i, err)
+                    })?;
+
+                    if unit.kind == "copy" || (status != "matching" && only.is_none()) {
+                        assemble(
+                            &assembler,
+                            &build_dir.join(format!("{}_copy_{}.obj", sec_name, unit_i)),
+                            &build_dir.join(format!("{}_copy_{}.asm", sec_name, unit_i)),
+                        )
+                        .map_err(|err| {
+                            format!(
+                                "copy assembly of section `{}`, unit `{}` failed ({})",
+                                sec_name, unit_i, err
+                            )
+                        })?;
+
+                        tool_diagnostic::status(format_args!("assembled copy unit for section `{}`, unit `{}`", sec_name, unit_i));
+                    }
+
+                    let result = match unit.kind.as_str() {
+                        "copy" => continue,
                         "asm" => {
                             if let Some(asm_path) = &unit.file {
-                                let asm_command = Command::new(&config.assembler_path)
-                                .arg(format!(
-                                    "/Fo{}",
-                                    build_dir.join(format!("{}_asm_{}.obj", sec_name, unit_i)).display()
-                                ))
-                                .arg("/c")
-                                .arg(&asm_path)
-                                .output()
+                                assemble(
+                                    &assembler,
+                                    &build_dir.join(format!("{}_asm_{}.obj", sec_name, unit_i)),
+                                    Path::new(asm_path),
+                                )
                                 .map_err(|err| {
-                                    format!("failed to execute asm command for section `{}`, unit `{}` ({})", sec_name, unit_i, err)
-                                })?;
+                                    format!(
+                                        "assembly of section `{}`, unit `{}` failed ({})",
+                                        sec_name, unit_i, err
+                                    )
+                                })
+                                .map(|_| {
+                                    tool_diagnostic::status(format_args!(
+                                        "assembled asm unit for section `{}`, unit `{}`",
+                                        sec_name, unit_i
+                                    ))
+                                })
+                            } else {
+                                return Err(format!("asm unit for section `{}`, unit `{}` is missing file path", sec_name, unit_i))
+                            }
+                        }
+                        "c" => {
+                            if let Some(c_path) = &unit.file {
+                                let obj_path = build_dir.join(format!("{}_c_{}.obj", sec_name, unit_i));
+                                let args = compile_args(&compiler, &include_dirs, &obj_path, Path::new(c_path));
 
-                                if asm_command.status.success() {
-                                    println!("assembled asm unit for section `{}`, unit `{}`", sec_name, unit_i);
+                                if state.is_up_to_date(&obj_path, &args, &compiler_hash) {
+                                    write_depfile(&obj_path, &state.inputs(&obj_path))?;
+                                    tool_diagnostic::status(format_args!("c unit for section `{}`, unit `{}` is up to date", sec_name, unit_i));
+                                    Ok(())
                                 } else {
-                                    return Err(format!(
-                                        "assembly of section `{}`, unit `{}` failed ({})",
-                                        sec_name,
-                                        unit_i,
-                                        String::from_utf8_lossy(&asm_command.stdout)
-                                    ));
+                                    compile(&compiler, &args, &obj_path)
+                                        .map_err(|err| {
+                                            format!(
+                                                "compilation of section `{}`, unit `{}` failed ({})",
+                                                sec_name, unit_i, err
+                                            )
+                                        })
+                                        .and_then(|includes| {
+                                            let mut inputs = vec![PathBuf::from(c_path)];
+                                            inputs.extend(includes);
+                                            state.record(&obj_path, args, compiler_hash.clone(), &inputs);
+                                            state.save(build_dir)?;
+                                            write_depfile(&obj_path, &inputs)?;
+
+                                            tool_diagnostic::status(format_args!(
+                                                "compiled c unit for section `{}`, unit `{}`",
+                                                sec_name, unit_i
+                                            ));
+                                            Ok(())
+                                        })
                                 }
                             } else {
-                                return Err(format!("asm unit for section `{}`, unit `{}` is missing file path", sec_name, unit_i))
+                                return Err(format!("c unit for section `{}`, unit `{}` is missing file path", sec_name, unit_i))
+                            }
+                        }
+                        "rsrc" => {
+                            if let Some(rsrc_path) = &unit.file {
+                                let rva = (unit.addr_virtual - pe.image_base) as u32;
+                                build_resources(build_dir, sec_name, unit_i, Path::new(rsrc_path), rva, unit.raw_size)
+                                    .and_then(|asm_path| {
+                                        assemble(
+                                            &assembler,
+                                            &build_dir.join(format!("{}_rsrc_{}.obj", sec_name, unit_i)),
+                                            &asm_path,
+                                        )
+                                    })
+                                    .map_err(|err| {
+                                        format!(
+                                            "resource compilation of section `{}`, unit `{}` failed ({})",
+                                            sec_name, unit_i, err
+                                        )
+                                    })
+                                    .map(|_| {
+                                        tool_diagnostic::status(format_args!(
+                                            "compiled rsrc unit for section `{}`, unit `{}`",
+                                            sec_name, unit_i
+                                        ))
+                                    })
+                            } else {
+                                return Err(format!("rsrc unit for section `{}`, unit `{}` is missing directory path", sec_name, unit_i))
+                            }
+                        }
+                        // prebuilt, so it is linked from where it is
+                        "obj" => continue,
+                        "lib-member" => {
+                            if let (Some(lib_path), Some(member)) = (&unit.file, &unit.member) {
+                                extract_member(
+                                    Path::new(lib_path),
+                                    member,
+                                    &build_dir.join(format!("{}_lib_{}.obj", sec_name, unit_i)),
+                                )
+                                .map_err(|err| {
+                                    format!(
+                                        "extraction of section `{}`, unit `{}` failed ({})",
+                                        sec_name, unit_i, err
+                                    )
+                                })
+                                .map(|_| {
+                                    tool_diagnostic::status(format_args!(
+                                        "extracted lib-member unit for section `{}`, unit `{}`",
+                                        sec_name, unit_i
+                                    ))
+                                })
+                            } else {
+                                return Err(format!("lib-member unit for section `{}`, unit `{}` is missing library path or member", sec_name, unit_i))
                             }
                         }
                         _ => {
@@ -91,9 +224,16 @@ impl CommandExecute for GenArgs {
                                 sec_name, unit_i, unit.kind
                             ))
                         }
-                    }
+                    };
 
-                    unit_i += 1;
+                    // a wip unit is not expected to build yet, and a nonmatching one only builds for
+                    // diagnostics while its original bytes are linked, so neither holds up everyone
+                    // else unless its code was asked for
+                    let code_needed = only.is_some() || (status == "nonmatching" && self.use_nonmatching);
+                    match result {
+                        Err(err) if status != "matching" && !code_needed => tool_diagnostic::status(format_args!("warning: {}", err)),
+                        result => result?,
+                    }
                 }
             }
         }
@@ -101,3 +241,174 @@ impl CommandExecute for GenArgs {
         Ok(())
     }
 }
+
+fn assemble(assembler: &Tool, obj_path: &Path, asm_path: &Path) -> Result<(), String> {
+    let mut asm_command = assembler.command();
+    asm_command
+        .arg(format!("/Fo{}", assembler.arg_path(obj_path)))
+        .arg("/c")
+        .arg(assembler.arg_path(asm_path));
+
+    assembler.run(asm_command, &obj_log_path(obj_path)).map(|_| ())
+}
+
+/// compiles with the `cl` arguments `cl_args`, rewritten for compilers that don't take them, and
+/// returns the headers that were included
+fn compile(compiler: &Tool, cl_args: &[String], obj_path: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut compile_command = compiler.command();
+    if is_gnu_compiler(&compiler.path) {
+        let dep_path = obj_path.with_extension("d");
+        compile_command
+            .args(gnu_args(cl_args))
+            .arg("-MD")
+            .arg("-MF")
+            .arg(compiler.arg_path(&dep_path));
+        compiler.run(compile_command, &obj_log_path(obj_path))?;
+
+        let depfile = fs::read_to_string(&dep_path)
+            .map_err(|err| format!("failed to read dependency file `{}` ({})", dep_path.display(), err))?;
+        Ok(build_state::parse_depfile(&depfile).iter().map(|path| compiler.host_path(path)).collect())
+    } else {
+        compile_command.arg("/showIncludes").args(cl_args);
+        let stdout = compiler.run(compile_command, &obj_log_path(obj_path))?;
+
+        Ok(build_state::parse_show_includes(&stdout)
+            .iter().map(|path| compiler.host_path(path)).collect())
+    }
+}
+
+/// writes the files `obj_path` was built from next to it, as pod sees them, for `ninja`, which can't
+/// make sense of the paths a wrapped compiler prints
+fn write_depfile(obj_path: &Path, inputs: &[PathBuf]) -> Result<(), String> {
+    let dep_path = obj_path.with_extension("d");
+    fs::write(&dep_path, build_state::depfile(obj_path, inputs))
+        .map_err(|err| format!("failed to write dependency file `{}` ({})", dep_path.display(), err))
+}
+
+/// the log of the tool building `obj_path`
+fn obj_log_path(obj_path: &Path) -> PathBuf {
+    util::log_path(&obj_path.file_stem().unwrap_or_default().to_string_lossy())
+}
+
+/// whether the compiler at `compiler_path` is gcc or clang, which take unix style arguments, rather
+/// than `cl` or `clang-cl`
+pub(super) fn is_gnu_compiler(compiler_path: &str) -> bool {
+    let name = Path::new(compiler_path)
+        .file_stem()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    !name.contains("clang-cl") && (name.contains("gcc") || name.contains("clang") || name == "cc")
+}
+
+/// the arguments `cl` is given to compile `c_path` into `obj_path`, with paths the way `compiler`
+/// sees them
+pub(super) fn compile_args(compiler: &Tool, include_dirs: &[PathBuf], obj_path: &Path, c_path: &Path) -> Vec<String> {
+    let mut args = vec!["/nologo".to_string(), "/c".to_string(), format!("/Fo{}", compiler.arg_path(obj_path))];
+    args.extend(include_dirs.iter().map(|include_dir| format!("/I{}", compiler.arg_path(include_dir))));
+    args.push(compiler.arg_path(c_path));
+
+    args
+}
+
+/// `cl` arguments rewritten into the clang ones that do the same in the MSVC environment
+fn clang_args(cl_args: &[String]) -> Vec<String> {
+    let mut args = vec!["clang".to_string()];
+    args.extend(CLANG_MSVC_ARGS.iter().map(|arg| arg.to_string()));
+    args.extend(gnu_args(cl_args));
+
+    args
+}
+
+/// `cl` arguments rewritten into the ones gcc and clang take, leaving out those only affecting the
+/// compiler's output to the console
+pub(super) fn gnu_args(cl_args: &[String]) -> Vec<String> {
+    let mut args = Vec::new();
+    for arg in cl_args {
+        if arg == "/nologo" {
+            continue;
+        } else if arg == "/c" {
+            args.push("-c".to_string());
+        } else if let Some(obj_path) = arg.strip_prefix("/Fo") {
+            args.push("-o".to_string());
+            args.push(obj_path.to_string());
+        } else if let Some(include_dir) = arg.strip_prefix("/I") {
+            args.push(format!("-I{}", include_dir));
+        } else {
+            args.push(arg.clone());
+        }
+    }
+
+    args
+}
+
+/// writes a compilation database with every c unit, compiled the way `compile` does it, so editors
+/// can make sense of the sources
+fn write_compile_commands(config: &Config, build_dir: &Path, include_dirs: &[PathBuf]) -> Result<(), String> {
+    let directory = std::env::current_dir()
+        .map_err(|err| format!("failed to get current directory ({})", err))?
+        .display()
+        .to_string();
+
+    let mut commands = Vec::new();
+    for sec in config.sections.iter() {
+        for (unit_i, unit) in sec.units.iter().enumerate() {
+            let (Some(c_path), "c") = (&unit.file, unit.kind.as_str()) else {
+                continue;
+            };
+
+            let obj_path = build_dir.join(format!("{}_c_{}.obj", sec.name, unit_i));
+            commands.push(CompileCommand {
+                directory: directory.clone(),
+                file: c_path.clone(),
+                arguments: clang_args(&compile_args(
+                    &Tool::direct(config.compiler_path()),
+                    include_dirs,
+                    &obj_path,
+                    Path::new(c_path),
+                )),
+                output: obj_path.display().to_string(),
+            });
+        }
+    }
+
+    let json = serde_json::to_string_pretty(&commands)
+        .map_err(|err| format!("failed to serialize compilation database ({})", err))?;
+    fs::write(COMPILE_COMMANDS_PATH, json + "\n")
+        .map_err(|err| format!("failed to write `{}` ({})", COMPILE_COMMANDS_PATH, err))?;
+
+    tool_diagnostic::status(format_args!("wrote {} compile commands to `{}`", commands.len(), COMPILE_COMMANDS_PATH));
+
+    Ok(())
+}
+
+/// rebuilds a resource directory from its extracted files into an asm file, returning its path
+fn build_resources(
+    build_dir: &Path,
+    sec_name: &str,
+    unit_i: usize,
+    rsrc_dir: &Path,
+    rva: u32,
+    raw_size: usize,
+) -> Result<PathBuf, String> {
+    let table = rsrc::load_resources(rsrc_dir)?;
+    let data = rsrc::build_resources(&table, rva, raw_size)?;
+
+    let asm_path = build_dir.join(format!("{}_rsrc_{}.asm", sec_name, unit_i));
+    let mut asm_file = File::create(&asm_path)
+        .map_err(|err| format!("failed to create resource asm file ({})", err))?;
+    asm_file
+        .write_all(util::data_asm(&data).as_bytes())
+        .map_err(|err| format!("failed to write resource asm file ({})", err))?;
+
+    Ok(asm_path)
+}
+
+/// copies the member of the library at `lib_path` called `member` out to `obj_path`
+fn extract_member(lib_path: &Path, member: &str, obj_path: &Path) -> Result<(), String> {
+    let lib_file = fs::read(lib_path).map_err(|err| format!("failed to open library `{}` ({})", lib_path.display(), err))?;
+    let members = archive::members(&lib_file)?;
+    let member = archive::find_member(&members, member)?;
+
+    fs::write(obj_path, member.data).map_err(|err| format!("failed to write `{}` ({})", obj_path.display(), err))
+}
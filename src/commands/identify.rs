@@ -0,0 +1,264 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use goblin::pe::PE;
+
+use crate::{
+    analysis::{self, Signature},
+    archive,
+    config::{Include, Section, Symbol, Unit, UnitLayout},
+    tool_diagnostic, util,
+};
+
+use super::CommandExecute;
+
+/// functions MSVC links are aligned to this, so library code can start at any multiple of it
+const FUNCTION_ALIGNMENT: u32 = 16;
+
+#[derive(Debug, Args)]
+pub struct IdentifyArgs {
+    /// COFF archives to take function signatures from
+    #[arg(long = "lib", value_name = "LIB", required = true, num_args = 1..)]
+    pub libs: Vec<String>,
+    /// fewest compared bytes a function needs to be looked for, shorter ones match by accident
+    #[arg(long, value_name = "BYTES", default_value_t = 16)]
+    pub min_size: usize,
+    /// where to write the suggested lib-member units, relative to the project root
+    #[arg(long, value_name = "PATH", default_value = "pod.identified.toml")]
+    pub output: PathBuf,
+}
+
+/// a function from a library member
+struct LibFunction {
+    lib: usize,
+    member: String,
+    signature: Signature,
+}
+
+impl CommandExecute for IdentifyArgs {
+    /// finds library functions in the executable's code and adds them as symbols
+    fn execute(&self) -> Result<(), String> {
+        let mut config = util::get_config()?;
+
+        if config.sources.iter().any(|source| Path::new(&source.path) == self.output) {
+            return Err(format!("`{}` is part of the config, pick another `--output`", self.output.display()));
+        }
+
+        let file = fs::read(&config.executable)
+            .map_err(|err| format!("failed to open executable ({})", err))?;
+
+        let pe = PE::parse(&file).map_err(|err| format!("failed to parse executable ({})", err))?;
+
+        let mut functions = Vec::new();
+        let mut too_short = 0;
+        for (lib_i, lib) in self.libs.iter().enumerate() {
+            let lib_file = fs::read(lib).map_err(|err| format!("failed to open library `{}` ({})", lib, err))?;
+            let members = archive::members(&lib_file).map_err(|err| format!("failed to read library `{}` ({})", lib, err))?;
+
+            for member in members {
+                // linker members and short import members are not objects and have no code anyway
+                let Ok(signatures) = analysis::object_signatures(member.data) else {
+                    continue;
+                };
+
+                for signature in signatures {
+                    if signature.significant() < self.min_size {
+                        too_short += 1;
+                        continue;
+                    }
+                    functions.push(LibFunction {
+                        lib: lib_i,
+                        member: member.name.clone(),
+                        signature,
+                    });
+                }
+            }
+        }
+
+        // most functions start with a byte that is compared, which narrows down what to try
+        let mut by_first_byte: BTreeMap<u8, Vec<usize>> = BTreeMap::new();
+        let mut wildcard = Vec::new();
+        for (function_i, function) in functions.iter().enumerate() {
+            match function.signature.mask.first() {
+                Some(true) => by_first_byte.entry(function.signature.bytes[0]).or_default().push(function_i),
+                _ => wildcard.push(function_i),
+            }
+        }
+
+        let code = analysis::code_sections(&file, &pe);
+        let mut candidates: BTreeSet<u32> = analysis::find_functions(&file, &pe)?
+            .iter()
+            .map(|function| function.rva)
+            .collect();
+        for sec in code.iter() {
+            let first = sec.rva.next_multiple_of(FUNCTION_ALIGNMENT);
+            candidates.extend((first..sec.rva + sec.data.len() as u32).step_by(FUNCTION_ALIGNMENT as usize));
+        }
+
+        let mut found: Vec<(u32, usize)> = Vec::new();
+        let mut ambiguous: Vec<(u32, Vec<&str>)> = Vec::new();
+        let mut next_free = 0;
+        for rva in candidates {
+            let Some(sec) = code.iter().find(|sec| sec.contains(rva)) else {
+                continue;
+            };
+            if rva < next_free {
+                continue;
+            }
+
+            let data = &sec.data[(rva - sec.rva) as usize..];
+            let matching: Vec<usize> = by_first_byte
+                .get(&data[0])
+                .into_iter()
+                .flatten()
+                .chain(wildcard.iter())
+                .copied()
+                .filter(|&function_i| functions[function_i].signature.matches(data))
+                .collect();
+
+            // the longest match wins, shorter ones are just its prefix
+            let Some(longest) = matching.iter().map(|&function_i| functions[function_i].signature.bytes.len()).max() else {
+                continue;
+            };
+            let names: BTreeSet<&str> = matching
+                .iter()
+                .filter(|&&function_i| functions[function_i].signature.bytes.len() == longest)
+                .map(|&function_i| functions[function_i].signature.name.as_str())
+                .collect();
+
+            if names.len() > 1 {
+                ambiguous.push((rva, names.into_iter().collect()));
+                continue;
+            }
+
+            let function_i = *matching
+                .iter()
+                .find(|&&function_i| functions[function_i].signature.bytes.len() == longest)
+                .unwrap();
+            found.push((rva, function_i));
+            next_free = rva + longest as u32;
+        }
+
+        let mut added = 0;
+        for &(rva, function_i) in found.iter() {
+            let addr_virtual = pe.image_base + rva as usize;
+            let name = &functions[function_i].signature.name;
+            if config
+                .symbols
+                .iter()
+                .any(|symbol| symbol.addr_virtual == addr_virtual || symbol.name == *name)
+            {
+                continue;
+            }
+
+            config.symbols.push(Symbol {
+                name: name.clone(),
+                addr_virtual,
+                origin: 0,
+            });
+            added += 1;
+        }
+        if added > 0 {
+            config.forget_sources();
+            util::write_config(&config)?;
+        }
+
+        let lib_member = |function: &LibFunction| {
+            format!("{}({})", util::relative_to_root(&self.libs[function.lib]), function.member)
+        };
+
+        tool_diagnostic::status(format_args!(
+            "identified {} functions with {} signatures, skipping {} too short to look for",
+            found.len(),
+            functions.len(),
+            too_short
+        ));
+        for &(rva, function_i) in found.iter() {
+            let function = &functions[function_i];
+            tool_diagnostic::status(format_args!("  0x{:X}  {}  {}", pe.image_base + rva as usize, function.signature.name, lib_member(function)));
+        }
+
+        if !ambiguous.is_empty() {
+            tool_diagnostic::status("\nmatched by more than one function, so left alone:");
+            for (rva, names) in ambiguous {
+                tool_diagnostic::status(format_args!("  0x{:X}  {}", pe.image_base + rva as usize, names.join(", ")));
+            }
+        }
+
+        // functions from one member that follow each other, give or take padding, are one unit
+        let mut units: Vec<(u32, u32, usize)> = Vec::new();
+        for &(rva, function_i) in found.iter() {
+            let end = rva + functions[function_i].signature.bytes.len() as u32;
+            match units.last_mut() {
+                Some((_, last_end, last_i))
+                    if rva - *last_end < FUNCTION_ALIGNMENT
+                        && functions[*last_i].lib == functions[function_i].lib
+                        && functions[*last_i].member == functions[function_i].member =>
+                {
+                    *last_end = end
+                }
+                _ => units.push((rva, end, function_i)),
+            }
+        }
+        if !units.is_empty() {
+            tool_diagnostic::status("\nsuggested lib-member units:");
+            let mut identified = Include {
+                sections: Vec::new(),
+                symbols: Vec::new(),
+            };
+            for (start, end, function_i) in units {
+                let function = &functions[function_i];
+                tool_diagnostic::status(format_args!(
+                    "  0x{:X}..0x{:X}  {}",
+                    pe.image_base + start as usize,
+                    pe.image_base + end as usize,
+                    lib_member(function)
+                ));
+
+                // units go in a section named like the executable's, as in pod.toml
+                let sec_name = pe
+                    .sections
+                    .iter()
+                    .find(|sec| start >= sec.virtual_address && start < sec.virtual_address + sec.virtual_size.max(sec.size_of_raw_data))
+                    .and_then(|sec| sec.name().ok())
+                    .unwrap_or_default();
+                if identified.sections.last().map(|sec| sec.name.as_str()) != Some(sec_name) {
+                    identified.sections.push(Section {
+                        name: sec_name.to_string(),
+                        addr_virtual: None,
+                        units: Vec::new(),
+                        origin: Default::default(),
+                    });
+                }
+                identified.sections.last_mut().unwrap().units.push(Unit {
+                    name: None,
+                    kind: "lib-member".to_string(),
+                    file: Some(util::relative_to_root(&self.libs[function.lib])),
+                    member: Some(function.member.clone()),
+                    addr_virtual: pe.image_base + start as usize,
+                    raw_size: (end - start) as usize,
+                    status: None,
+                    layout: UnitLayout::default(),
+                    written: None,
+                });
+            }
+
+            let toml_string = toml::to_string_pretty(&identified)
+                .map_err(|err| format!("failed to serialize suggested units ({})", err))?;
+            fs::write(&self.output, toml_string)
+                .map_err(|err| format!("failed to write `{}` ({})", self.output.display(), err))?;
+            tool_diagnostic::status(format_args!(
+                "\nwrote suggested units to `{}`, move them into the config in place of the code they cover",
+                self.output.display()
+            ));
+        }
+
+        tool_diagnostic::status(format_args!("\nadded {} symbols to `{}`", added, util::config_path().display()));
+
+        Ok(())
+    }
+}
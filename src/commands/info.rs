@@ -0,0 +1,463 @@
+use std::fs;
+
+use clap::Args;
+use goblin::pe::{
+    header::{COFF_MACHINE_X86, COFF_MACHINE_X86_64},
+    section_table::{
+        IMAGE_SCN_CNT_CODE, IMAGE_SCN_CNT_INITIALIZED_DATA, IMAGE_SCN_CNT_UNINITIALIZED_DATA,
+        IMAGE_SCN_MEM_EXECUTE, IMAGE_SCN_MEM_READ, IMAGE_SCN_MEM_WRITE,
+    },
+    PE,
+};
+use serde::Serialize;
+
+use crate::{config::Config, rich, util};
+
+use super::CommandExecute;
+
+const DATA_DIRECTORY_NAMES: [&str; 16] = [
+    "export table",
+    "import table",
+    "resource table",
+    "exception table",
+    "certificate table",
+    "base relocation table",
+    "debug",
+    "architecture",
+    "global ptr",
+    "tls table",
+    "load config table",
+    "bound import",
+    "import address table",
+    "delay import descriptor",
+    "clr runtime header",
+    "reserved",
+];
+
+#[derive(Debug, Args)]
+pub struct InfoArgs {
+    /// print the report as JSON
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct UnitReport {
+    index: usize,
+    kind: String,
+    file: Option<String>,
+    status: String,
+    addr_virtual: usize,
+    raw_size: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct SectionReport {
+    name: String,
+    addr_virtual: usize,
+    virtual_size: u32,
+    raw_offset: u32,
+    raw_size: u32,
+    characteristics: u32,
+    flags: String,
+    units: Vec<UnitReport>,
+}
+
+/// where an address lands in `pod.toml`
+#[derive(Debug, Serialize)]
+struct Location {
+    section: String,
+    unit: usize,
+    kind: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DataDirectoryReport {
+    name: &'static str,
+    addr_virtual: usize,
+    size: u32,
+    location: Option<Location>,
+}
+
+#[derive(Debug, Serialize)]
+struct ImportReport {
+    dll: String,
+    functions: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct ExportReport {
+    name: Option<String>,
+    addr_virtual: usize,
+    location: Option<Location>,
+}
+
+#[derive(Debug, Serialize)]
+struct OverlayReport {
+    offset: usize,
+    size: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct RichReport {
+    product: String,
+    build: u16,
+    count: u32,
+    release: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    executable: String,
+    machine: String,
+    image_base: usize,
+    entry_point: usize,
+    entry_point_location: Option<Location>,
+    subsystem: String,
+    timestamp: u32,
+    checksum: u32,
+    sections: Vec<SectionReport>,
+    data_directories: Vec<DataDirectoryReport>,
+    imports: Vec<ImportReport>,
+    exports: Vec<ExportReport>,
+    overlay: Option<OverlayReport>,
+    rich_header: Option<Vec<RichReport>>,
+    toolchain: Vec<String>,
+}
+
+impl CommandExecute for InfoArgs {
+    fn execute(&self) -> Result<(), String> {
+        let config = util::get_config()?;
+
+        let file = fs::read(&config.executable)
+            .map_err(|err| format!("failed to open executable ({})", err))?;
+
+        let pe = PE::parse(&file).map_err(|err| format!("failed to parse executable ({})", err))?;
+
+        let report = build_report(&config, &file, &pe)?;
+
+        if self.json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report)
+                    .map_err(|err| format!("failed to serialize report ({})", err))?
+            );
+        } else {
+            print_report(&report);
+        }
+
+        Ok(())
+    }
+}
+
+fn locate(config: &Config, addr_virtual: usize) -> Option<Location> {
+    config.find_unit(addr_virtual).map(|(sec, unit_i, unit)| Location {
+        section: sec.name.clone(),
+        unit: unit_i,
+        kind: unit.kind.clone(),
+    })
+}
+
+fn section_flags(characteristics: u32) -> String {
+    let mut flags = String::new();
+    flags.push(if characteristics & IMAGE_SCN_MEM_READ != 0 { 'r' } else { '-' });
+    flags.push(if characteristics & IMAGE_SCN_MEM_WRITE != 0 { 'w' } else { '-' });
+    flags.push(if characteristics & IMAGE_SCN_MEM_EXECUTE != 0 { 'x' } else { '-' });
+
+    for (flag, name) in [
+        (IMAGE_SCN_CNT_CODE, "code"),
+        (IMAGE_SCN_CNT_INITIALIZED_DATA, "data"),
+        (IMAGE_SCN_CNT_UNINITIALIZED_DATA, "bss"),
+    ] {
+        if characteristics & flag != 0 {
+            flags += " ";
+            flags += name;
+        }
+    }
+
+    flags
+}
+
+fn build_report(config: &Config, file: &[u8], pe: &PE) -> Result<Report, String> {
+    let optional_header = pe
+        .header
+        .optional_header
+        .ok_or("executable is missing its optional header")?;
+
+    let sections = pe
+        .sections
+        .iter()
+        .map(|sec| {
+            let name = sec
+                .name()
+                .map_err(|err| format!("failed to get section name ({})", err))?
+                .to_string();
+
+            let units = config
+                .sections
+                .iter()
+                .find(|cfg_sec| cfg_sec.name == name)
+                .map(|cfg_sec| {
+                    cfg_sec
+                        .units
+                        .iter()
+                        .enumerate()
+                        .map(|(unit_i, unit)| UnitReport {
+                            index: unit_i,
+                            kind: unit.kind.clone(),
+                            file: unit.file.clone(),
+                            status: unit.status().unwrap_or("invalid").to_string(),
+                            addr_virtual: unit.addr_virtual,
+                            raw_size: unit.raw_size,
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+
+            Ok(SectionReport {
+                name,
+                addr_virtual: pe.image_base + sec.virtual_address as usize,
+                virtual_size: sec.virtual_size,
+                raw_offset: sec.pointer_to_raw_data,
+                raw_size: sec.size_of_raw_data,
+                characteristics: sec.characteristics,
+                flags: section_flags(sec.characteristics),
+                units,
+            })
+        })
+        .collect::<Result<Vec<SectionReport>, String>>()?;
+
+    let data_directories = optional_header
+        .data_directories
+        .data_directories
+        .iter()
+        .enumerate()
+        .filter_map(|(dd_i, dd)| dd.map(|(_, dd)| (dd_i, dd)))
+        .filter(|(_, dd)| dd.size > 0)
+        .map(|(dd_i, dd)| {
+            // the certificate table is the one directory that holds a file offset
+            let addr_virtual = if dd_i == 4 {
+                dd.virtual_address as usize
+            } else {
+                pe.image_base + dd.virtual_address as usize
+            };
+
+            DataDirectoryReport {
+                name: DATA_DIRECTORY_NAMES[dd_i],
+                addr_virtual,
+                size: dd.size,
+                location: if dd_i == 4 { None } else { locate(config, addr_virtual) },
+            }
+        })
+        .collect();
+
+    let mut imports: Vec<ImportReport> = Vec::new();
+    for import in pe.imports.iter() {
+        let function = if import.name.is_empty() {
+            format!("ordinal {}", import.ordinal)
+        } else {
+            import.name.to_string()
+        };
+
+        match imports.iter_mut().find(|i_import| i_import.dll == import.dll) {
+            Some(i_import) => i_import.functions.push(function),
+            None => imports.push(ImportReport {
+                dll: import.dll.to_string(),
+                functions: vec![function],
+            }),
+        }
+    }
+
+    let exports = pe
+        .exports
+        .iter()
+        .map(|export| ExportReport {
+            name: export.name.map(|name| name.to_string()),
+            addr_virtual: pe.image_base + export.rva,
+            location: locate(config, pe.image_base + export.rva),
+        })
+        .collect();
+
+    let sections_end = pe
+        .sections
+        .iter()
+        .map(|sec| (sec.pointer_to_raw_data + sec.size_of_raw_data) as usize)
+        .max()
+        .unwrap_or(0);
+    let overlay = (file.len() > sections_end).then(|| OverlayReport {
+        offset: sections_end,
+        size: file.len() - sections_end,
+    });
+
+    let rich_entries = rich::rich_entries(pe)?;
+
+    let entry_point = pe.image_base + pe.entry;
+
+    Ok(Report {
+        executable: config.executable.clone(),
+        machine: match pe.header.coff_header.machine {
+            COFF_MACHINE_X86 => "x86".to_string(),
+            COFF_MACHINE_X86_64 => "x86-64".to_string(),
+            machine => format!("0x{:04X}", machine),
+        },
+        image_base: pe.image_base,
+        entry_point,
+        entry_point_location: locate(config, entry_point),
+        subsystem: match optional_header.windows_fields.subsystem {
+            1 => "native".to_string(),
+            2 => "windows gui".to_string(),
+            3 => "windows console".to_string(),
+            subsystem => subsystem.to_string(),
+        },
+        timestamp: pe.header.coff_header.time_date_stamp,
+        checksum: optional_header.windows_fields.check_sum,
+        sections,
+        data_directories,
+        imports,
+        exports,
+        overlay,
+        toolchain: rich_entries
+            .as_deref()
+            .map(rich::toolchain_summary)
+            .unwrap_or_default(),
+        rich_header: rich_entries.map(|entries| {
+            entries
+                .iter()
+                .map(|entry| RichReport {
+                    product: entry.product_name(),
+                    build: entry.build,
+                    count: entry.count,
+                    release: entry.release(),
+                })
+                .collect()
+        }),
+    })
+}
+
+fn format_location(location: &Option<Location>) -> String {
+    match location {
+        Some(location) => format!(
+            "section `{}`, unit `{}` ({})",
+            location.section, location.unit, location.kind
+        ),
+        None => "no unit".to_string(),
+    }
+}
+
+fn print_report(report: &Report) {
+    println!("executable: {} ({})", report.executable, report.machine);
+    println!("image base: 0x{:X}", report.image_base);
+    println!(
+        "entry point: 0x{:X}, {}",
+        report.entry_point,
+        format_location(&report.entry_point_location)
+    );
+    println!("subsystem: {}", report.subsystem);
+    println!("timestamp: 0x{:08X}", report.timestamp);
+    println!("checksum: 0x{:08X}", report.checksum);
+
+    println!();
+    println!("sections:");
+    println!(
+        "  {:<8} {:>10} {:>10} {:>10} {:>10}  flags",
+        "name", "address", "vsize", "raw offset", "raw size"
+    );
+    for sec in report.sections.iter() {
+        println!(
+            "  {:<8} {:>10} {:>10} {:>10} {:>10}  {}",
+            sec.name,
+            format!("0x{:X}", sec.addr_virtual),
+            format!("0x{:X}", sec.virtual_size),
+            format!("0x{:X}", sec.raw_offset),
+            format!("0x{:X}", sec.raw_size),
+            sec.flags
+        );
+
+        if sec.units.is_empty() {
+            println!("    missing unit configuration");
+        }
+        for unit in sec.units.iter() {
+            println!(
+                "    unit {:<4} 0x{:X}..0x{:X}  {} {}{}",
+                unit.index,
+                unit.addr_virtual,
+                unit.addr_virtual + unit.raw_size,
+                unit.kind,
+                unit.status,
+                unit.file
+                    .as_ref()
+                    .map(|file| format!(" `{}`", file))
+                    .unwrap_or_default()
+            );
+        }
+    }
+
+    println!();
+    println!("data directories:");
+    for dd in report.data_directories.iter() {
+        println!(
+            "  {:<24} 0x{:<8X} size 0x{:<6X} {}",
+            dd.name,
+            dd.addr_virtual,
+            dd.size,
+            if dd.name == "certificate table" {
+                "file offset".to_string()
+            } else {
+                format_location(&dd.location)
+            }
+        );
+    }
+
+    println!();
+    println!("imports:");
+    for import in report.imports.iter() {
+        println!("  {} ({} functions)", import.dll, import.functions.len());
+        for function in import.functions.iter() {
+            println!("    {}", function);
+        }
+    }
+
+    if !report.exports.is_empty() {
+        println!();
+        println!("exports:");
+        for export in report.exports.iter() {
+            println!(
+                "  0x{:X} {} {}",
+                export.addr_virtual,
+                export.name.as_deref().unwrap_or("(unnamed)"),
+                format_location(&export.location)
+            );
+        }
+    }
+
+    if let Some(overlay) = &report.overlay {
+        println!();
+        println!(
+            "overlay: 0x{:X} bytes at file offset 0x{:X}",
+            overlay.size, overlay.offset
+        );
+    }
+
+    println!();
+    match &report.rich_header {
+        Some(entries) => {
+            println!("rich header:");
+            println!("  {:<24} {:>6} {:>6}  release", "product", "build", "count");
+            for entry in entries.iter() {
+                println!(
+                    "  {:<24} {:>6} {:>6}  {}",
+                    entry.product,
+                    entry.build,
+                    entry.count,
+                    entry.release.unwrap_or("")
+                );
+            }
+
+            println!();
+            for line in report.toolchain.iter() {
+                println!("{}", line);
+            }
+        }
+        None => println!("executable has no rich header"),
+    }
+}
@@ -6,7 +6,10 @@ use std::{
 use clap::Args;
 use goblin::pe::PE;
 
-use crate::config::{Config, Section, Unit};
+use crate::{
+    config::{Config, Origin, Section, Unit, UnitLayout, Workspace},
+    config_edit, rich, tool_diagnostic, util,
+};
 
 use super::CommandExecute;
 
@@ -22,6 +25,37 @@ impl CommandExecute for InitArgs {
                 Ok(pe) => {
                     let hash = blake3::hash(&file).to_string();
 
+                    let existing = if util::config_path().exists() {
+                        Some(fs::read_to_string(util::config_path()).map_err(|err| {
+                            format!("failed to open `{}` ({})", util::config_path().display(), err)
+                        })?)
+                    } else {
+                        None
+                    };
+
+                    // keys the existing config sets aren't suggested, as uncommenting them would set them twice
+                    let entries = rich::rich_entries(&pe)?;
+                    let existing_keys = existing
+                        .as_deref()
+                        .and_then(|existing| toml::from_str::<toml::Table>(existing).ok())
+                        .unwrap_or_default();
+                    let suggested = entries
+                        .as_deref()
+                        .map(rich::suggested_toolchain)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter(|(key, _)| !existing_keys.contains_key(*key))
+                        .collect::<Vec<_>>();
+
+                    // members of a workspace share its toolchain unless they are given their own, and a
+                    // suggested tool is left to its commented out key
+                    let in_workspace = Workspace::find(util::config_path())?.is_some();
+                    let toolchain_default = |key: &str, path: &str| {
+                        (!in_workspace && !suggested.iter().any(|(suggested, _)| *suggested == key)).then(|| path.to_string())
+                    };
+
+                    let executable = util::relative_to_root(&self.executable);
+
                     // "use rust", they said
                     // error handling is easy, they said
                     let sections: Vec<Section> = pe
@@ -33,34 +67,80 @@ impl CommandExecute for InitArgs {
                                     .name()
                                     .map_err(|err| format!("failed to get section name ({})", err))?
                                     .to_string(),
+                                addr_virtual: Some(pe.image_base + section.virtual_address as usize),
                                 units: vec![Unit {
+                                    name: None,
                                     kind: "copy".to_string(),
                                     file: None,
+                                    member: None,
                                     addr_virtual: pe.image_base + section.virtual_address as usize,
                                     raw_size: section.size_of_raw_data as usize,
+                                    status: None,
+                                    layout: UnitLayout::default(),
+                                    written: None,
                                 }],
+                                origin: Origin::default(),
                             })
                         })
                         .collect::<Result<Vec<Section>, String>>()?;
 
                     let config = Config {
-                        executable: self.executable.clone(),
-                        hash: hash,
-                        assembler_path: "ml".to_string(),
-                        compiler_path: "cl".to_string(),
-                        linker_path: "ld".to_string(),
-                        sections: sections,
+                        executable: executable.clone(),
+                        hash,
+                        output: None,
+                        assembler_path: toolchain_default("assembler_path", "ml"),
+                        compiler_path: toolchain_default("compiler_path", "cl"),
+                        linker_path: toolchain_default("linker_path", "ld"),
+                        assembler_wrapper: None,
+                        compiler_wrapper: None,
+                        linker_wrapper: None,
+                        wine: None,
+                        include_dirs: Vec::new(),
+                        timestamp: None,
+                        sections,
+                        symbols: Vec::new(),
+                        include: Vec::new(),
+                        versions: Vec::new(),
+                        units: Default::default(),
+                        version: None,
+                        sources: Vec::new(),
+                        workspace: None,
                     };
 
-                    let toml_string = toml::to_string_pretty(&config).unwrap();
+                    let mut header = String::new();
+                    if let Some(entries) = &entries {
+                        header += &format!("{}\n", config_edit::TOOLCHAIN_HEADER);
+                        for line in rich::toolchain_summary(entries) {
+                            tool_diagnostic::status(&line);
+                            header += &format!("# {}\n", line);
+                        }
+                        if !suggested.is_empty() {
+                            header += "# uncomment to build with it through wine, from where it installs by default\n";
+                            for (key, value) in suggested.iter() {
+                                header += &format!("# {} = {}\n", key, toml::Value::from(value.as_str()));
+                            }
+                        }
+                        header += &format!("{}\n\n", config_edit::TOOLCHAIN_FOOTER);
+                    }
 
-                    let mut cfg_file = File::create("pod.toml").unwrap();
-                    cfg_file.write_all(toml_string.as_bytes()).unwrap();
+                    // re-running init only points the existing config at the new executable, keeping
+                    // the units, symbols and toolchain written there by hand
+                    if let Some(existing) = existing {
+                        let toml_string =
+                            config_edit::set_executable(&existing, util::version(), &executable, &config.hash, &header)?;
+                        fs::write(util::config_path(), toml_string)
+                            .map_err(|err| format!("failed to write `{}` ({})", util::config_path().display(), err))?;
+                    } else {
+                        let toml_string = header + &toml::to_string_pretty(&config).unwrap();
+                        let mut cfg_file = File::create(util::config_path()).unwrap();
+                        cfg_file.write_all(toml_string.as_bytes()).unwrap();
+                    }
 
-                    println!(
-                        "initialized pod.toml for executable at `{}`",
-                        self.executable
-                    );
+                    tool_diagnostic::status(format_args!(
+                        "initialized `{}` for executable at `{}`",
+                        util::config_path().display(),
+                        executable
+                    ));
                     Ok(())
                 }
                 Err(err) => Err(format!("executable parsing failed ({})", err)),
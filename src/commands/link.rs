@@ -1,14 +1,18 @@
-use std::{fs, path::Path, process::Command};
+use std::{fs, path::Path};
 
 use clap::Args;
 use goblin::pe::PE;
 
-use crate::util;
+use crate::{tool_diagnostic, util};
 
 use super::CommandExecute;
 
 #[derive(Debug, Args)]
-pub struct LinkArgs {}
+pub struct LinkArgs {
+    /// link the compiled code of nonmatching units instead of their original bytes
+    #[arg(long)]
+    pub use_nonmatching: bool,
+}
 
 impl CommandExecute for LinkArgs {
     fn execute(&self) -> Result<(), String> {
@@ -19,21 +23,23 @@ impl CommandExecute for LinkArgs {
 
         let pe = PE::parse(&file).map_err(|err| format!("failed to parse executable ({})", err))?;
 
-        let build_dir = Path::new("build");
-        let binding = build_dir.join(
-            Path::new(&config.executable)
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap(),
-        );
+        let build_dir = util::build_dir();
+        let binding = build_dir.join(config.output_name()?);
         let donor_file_path = format!("{}.donor", binding.to_str().unwrap());
 
-        let link_command = Command::new(config.linker_path)
+        let link_script_path = if self.use_nonmatching {
+            build_dir.join("link_nonmatching.ld")
+        } else {
+            build_dir.join("link.ld")
+        };
+
+        let linker = config.linker();
+        let mut link_command = linker.command();
+        link_command
             .arg("-mi386pe")
-            .arg(format!("-o{}", donor_file_path))
+            .arg(format!("-o{}", linker.arg_path(Path::new(&donor_file_path))))
             .arg("-n")
-            .arg("-Tbuild/link.ld")
+            .arg(format!("-T{}", linker.arg_path(&link_script_path)))
             .arg("--subsystem=windows")
             .arg("--strip-debug")
             .arg("--disable-dynamicbase")
@@ -41,18 +47,12 @@ impl CommandExecute for LinkArgs {
             .arg("--strip-all")
             .arg("--major-image-version=0")
             .arg("--file-alignment=1")
-            .arg(format!("--image-base=0x{:X}", pe.image_base))
-            .output()
-            .map_err(|err| format!("failed to execute link command ({})", err))?;
+            .arg(format!("--image-base=0x{:X}", pe.image_base));
 
-        if link_command.status.success() {
-            println!("linked object files into `{}`", donor_file_path);
-        } else {
-            return Err(format!(
-                "linkage failed ({})",
-                String::from_utf8_lossy(&link_command.stderr)
-            ));
-        }
+        linker
+            .run(link_command, &util::log_path("link"))
+            .map_err(|err| format!("linkage failed ({})", err))?;
+        tool_diagnostic::status(format_args!("linked object files into `{}`", donor_file_path));
 
         let donor_file = fs::read(&donor_file_path)
             .map_err(|err| format!("failed to open donor executable ({})", err))?;
@@ -85,12 +85,17 @@ impl CommandExecute for LinkArgs {
                 let original_slice = &file[donee_data_start..donee_data_end];
                 let donor_slice = &donor_file[donor_data_start..donor_data_end];
 
-                for (i, (x, y)) in original_slice.iter().zip(donor_slice.iter()).enumerate() {
-                    if x != y {
-                        return Err(format!(
-                            "donor and original `{}` section mismatch at index `{}`: {:02x} vs {:02x}",
-                            sec_name, i, donor_slice[i], original_slice[i]
-                        ));
+                if let Some(i) = original_slice.iter().zip(donor_slice.iter()).position(|(x, y)| x != y) {
+                    let message = format!(
+                        "donor and original `{}` section mismatch at index `{}`: {:02x} vs {:02x}",
+                        sec_name, i, donor_slice[i], original_slice[i]
+                    );
+
+                    // nonmatching code is expected to differ, so only note it
+                    if self.use_nonmatching {
+                        tool_diagnostic::status(format_args!("warning: {}", message));
+                    } else {
+                        return Err(message);
                     }
                 }
                 /*if original_slice != donor_slice {
@@ -103,7 +108,7 @@ impl CommandExecute for LinkArgs {
                 donee_file[donee_data_start..donee_data_end]
                     .copy_from_slice(&donor_file[donor_data_start..donor_data_end]);
 
-                println!("donated `{}` section to donee executable", sec_name);
+                tool_diagnostic::status(format_args!("donated `{}` section to donee executable", sec_name));
             } else {
                 return Err(format!(
                     "donor executable is missing section `{}`",
@@ -115,7 +120,7 @@ impl CommandExecute for LinkArgs {
         let final_file_path = binding.to_str().unwrap();
         fs::write(final_file_path, donee_file).map_err(|err| format!("failed to write final executable to disk ({})", err))?;
         
-        println!("output final executable at `{}`", final_file_path);
+        tool_diagnostic::status(format_args!("output final executable at `{}`", final_file_path));
 
         Ok(())
     }
@@ -1,10 +1,23 @@
+use std::path::PathBuf;
+
 use clap::{Parser, Subcommand};
 
+use crate::tool_diagnostic::MessageFormat;
+
+pub mod addr;
+pub mod build;
+pub mod doctor;
 pub mod gen;
+pub mod identify;
+pub mod info;
 pub mod init;
 pub mod link;
+pub mod ninja;
+pub mod objdiff;
 pub mod patch_exe;
+pub mod port;
 pub mod split;
+pub mod unit;
 pub trait CommandExecute {
     fn execute(&self) -> Result<(), String>;
 }
@@ -15,6 +28,21 @@ pub trait CommandExecute {
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
+    /// run as if pod was started in this directory
+    #[arg(short = 'C', global = true, value_name = "DIR")]
+    pub directory: Option<PathBuf>,
+    /// config to use, by default `pod.toml` is searched for in the current directory and its parents
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// directory to put build output in, defaults to `build` next to the config
+    #[arg(long, global = true, value_name = "PATH")]
+    pub build_dir: Option<PathBuf>,
+    /// version of the executable to work on, for configs that describe several
+    #[arg(long, global = true, value_name = "NAME")]
+    pub version: Option<String>,
+    /// how to print the diagnostics of the assembler, compiler and linker
+    #[arg(long, global = true, value_enum, default_value_t = MessageFormat::Human)]
+    pub message_format: MessageFormat,
 }
 
 #[derive(Debug, Subcommand)]
@@ -25,4 +53,16 @@ pub enum Commands {
     Gen(gen::GenArgs),
     Link(link::LinkArgs),
     PatchExe(patch_exe::PatchExeArgs),
+    Info(info::InfoArgs),
+    #[command(arg_required_else_help = true)]
+    Addr(addr::AddrArgs),
+    Unit(unit::UnitArgs),
+    Build(build::BuildArgs),
+    #[command(arg_required_else_help = true)]
+    Port(port::PortArgs),
+    #[command(arg_required_else_help = true)]
+    Identify(identify::IdentifyArgs),
+    Objdiff(objdiff::ObjdiffArgs),
+    Ninja(ninja::NinjaArgs),
+    Doctor(doctor::DoctorArgs),
 }
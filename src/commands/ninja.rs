@@ -0,0 +1,282 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use goblin::pe::PE;
+
+use crate::{config::Unit, tool::Tool, tool_diagnostic, util};
+
+use super::{gen, CommandExecute};
+
+#[derive(Debug, Args)]
+pub struct NinjaArgs {
+    /// where to write the ninja build file, relative to the project root
+    #[arg(long, value_name = "PATH", default_value = "build.ninja")]
+    pub output: PathBuf,
+    /// link the compiled code of nonmatching units instead of their original bytes
+    #[arg(long)]
+    pub use_nonmatching: bool,
+}
+
+impl CommandExecute for NinjaArgs {
+    /// writes a ninja build file running the same steps as `pod build`, with an edge for every unit's
+    /// object so only what changed is rebuilt
+    fn execute(&self) -> Result<(), String> {
+        let config = util::get_config()?;
+
+        let file = fs::read(&config.executable)
+            .map_err(|err| format!("failed to open executable ({})", err))?;
+
+        let pe = PE::parse(&file).map_err(|err| format!("failed to parse executable ({})", err))?;
+        config.check(&pe)?;
+
+        let pod_path = env::current_exe().map_err(|err| format!("failed to find the pod executable ({})", err))?;
+
+        // the steps pod runs itself need the same project as this one
+        let mut pod_args = Vec::new();
+        if util::config_path() != Path::new("pod.toml") {
+            pod_args.push(format!("--config {}", util::config_path().display()));
+        }
+        if let Some(build_dir) = util::build_dir_override() {
+            pod_args.push(format!("--build-dir {}", build_dir.display()));
+        }
+        if let Some(version) = util::version() {
+            pod_args.push(format!("--version {}", version));
+        }
+        let step_args = if self.use_nonmatching { " --use-nonmatching" } else { "" };
+
+        let build_dir = util::build_dir();
+        let exe_path = build_dir.join(config.output_name()?);
+        let donee_path = PathBuf::from(format!("{}.donee", exe_path.display()));
+        let donor_path = PathBuf::from(format!("{}.donor", exe_path.display()));
+
+        let include_dirs: Vec<PathBuf> = config
+            .include_dirs()
+            .iter()
+            .map(|include_dir| PathBuf::from(escape(&include_dir.display().to_string())))
+            .collect();
+        let assembler = config.assembler();
+        let compiler = config.compiler();
+        // wrapped tools take Windows paths, which each edge gives them as `tool_out` and `tool_in`
+        let cl_args = gen::compile_args(&compiler, &include_dirs, Path::new("$tool_out"), Path::new("$tool_in"));
+
+        let mut ninja = String::new();
+        ninja += &format!("# generated by `pod ninja`, which this reruns when `{}` changes\n\n", util::config_path().display());
+        ninja += "ninja_required_version = 1.3\n\n";
+        ninja += &format!("pod = {}\n", escape(&pod_path.display().to_string()));
+        ninja += &format!("pod_args = {}\n", escape(&pod_args.join(" ")));
+        ninja += &format!("ml = {}\n", escape(&assembler.command_line()));
+        ninja += &format!("cl = {}\n\n", escape(&compiler.command_line()));
+
+        ninja += "rule configure\n";
+        ninja += &format!(
+            "  command = $pod $pod_args ninja --output {}{}\n",
+            escape(&self.output.display().to_string()),
+            step_args
+        );
+        ninja += "  description = NINJA $out\n";
+        ninja += "  generator = 1\n\n";
+        ninja += "rule split\n";
+        ninja += "  command = $pod $pod_args split\n";
+        ninja += "  description = SPLIT $in\n\n";
+        ninja += "rule ml\n";
+        ninja += "  command = $ml /Fo$tool_out /c $tool_in\n";
+        ninja += "  description = ML $in\n\n";
+        ninja += "rule cl\n";
+        if compiler.wrapper.is_some() {
+            // the headers a wrapped compiler reports have Windows paths ninja can't find, so pod runs
+            // it and writes them into a depfile as it sees them
+            ninja += "  command = $pod $pod_args gen --unit $unit\n";
+            ninja += "  deps = gcc\n";
+            ninja += "  depfile = $dep\n";
+        } else if gen::is_gnu_compiler(&compiler.path) {
+            ninja += &format!("  command = $cl {} -MD -MF $tool_out.d\n", gen::gnu_args(&cl_args).join(" "));
+            ninja += "  deps = gcc\n";
+            ninja += "  depfile = $out.d\n";
+        } else {
+            ninja += &format!("  command = $cl /showIncludes {}\n", cl_args.join(" "));
+            ninja += "  deps = msvc\n";
+        }
+        ninja += "  description = CL $in\n\n";
+        // resources and library members are built by pod, as there is no tool for them
+        ninja += "rule gen\n";
+        ninja += "  command = $pod $pod_args gen --unit $unit\n";
+        ninja += "  description = GEN $out\n\n";
+        ninja += "rule link\n";
+        ninja += &format!(
+            "  command = $pod $pod_args link{} && $pod $pod_args patch-exe{}\n",
+            step_args, step_args
+        );
+        ninja += "  description = LINK $out\n\n";
+
+        let sources: Vec<String> = config.sources.iter().map(|source| escape_path(Path::new(&source.path))).collect();
+        ninja += &format!("build {}: configure {}\n\n", escape_path(&self.output), sources.join(" "));
+
+        let mut split_outputs = vec![
+            escape_path(&donee_path),
+            escape_path(&build_dir.join("link.ld")),
+            escape_path(&build_dir.join("link_nonmatching.ld")),
+        ];
+        let mut edges = String::new();
+        let mut link_inputs = Vec::new();
+        for sec in config.sections.iter() {
+            for (unit_i, unit) in sec.units.iter().enumerate() {
+                let links_original = unit
+                    .links_original()
+                    .map_err(|err| format!("section `{}`, unit `{}` has {}", sec.name, unit_i, err))?;
+
+                let copy_obj_path = build_dir.join(format!("{}_copy_{}.obj", sec.name, unit_i));
+                if unit.kind == "copy" || links_original {
+                    let copy_asm_path = build_dir.join(format!("{}_copy_{}.asm", sec.name, unit_i));
+                    split_outputs.push(escape_path(&copy_asm_path));
+                    edges += &tool_edge(&assembler, "ml", &copy_obj_path, &copy_asm_path);
+                }
+                if unit.kind == "asm" || unit.kind == "c" {
+                    split_outputs.push(escape_path(&build_dir.join(format!("{}_ref_{}.obj", sec.name, unit_i))));
+                }
+
+                let obj_path = unit_object(build_dir, &sec.name, unit_i, unit)?;
+                let file = unit.file.as_deref().map(Path::new);
+                match (unit.kind.as_str(), file) {
+                    ("asm", Some(file)) => {
+                        edges += &tool_edge(&assembler, "ml", &obj_path, file);
+                    }
+                    ("c", Some(file)) if compiler.wrapper.is_some() => {
+                        edges += &format!(
+                            "build {}: cl {}\n  unit = 0x{:X}\n  dep = {}\n",
+                            escape_path(&obj_path),
+                            escape_path(file),
+                            unit.addr_virtual,
+                            escape(&obj_path.with_extension("d").display().to_string())
+                        );
+                    }
+                    ("c", Some(file)) => {
+                        edges += &tool_edge(&compiler, "cl", &obj_path, file);
+                    }
+                    ("rsrc", Some(file)) => {
+                        // split extracts the resources if they aren't yet, so it has to run first
+                        edges += &format!(
+                            "build {}: gen {} || {}\n  unit = 0x{:X}\n",
+                            escape_path(&obj_path),
+                            files_in(file).iter().map(|path| escape_path(path)).collect::<Vec<_>>().join(" "),
+                            escape_path(&donee_path),
+                            unit.addr_virtual
+                        );
+                    }
+                    ("lib-member", Some(file)) => {
+                        edges += &format!(
+                            "build {}: gen {}\n  unit = 0x{:X}\n",
+                            escape_path(&obj_path),
+                            escape_path(file),
+                            unit.addr_virtual
+                        );
+                    }
+                    _ => {}
+                }
+
+                if unit.kind != "copy" && links_original && !self.use_nonmatching {
+                    link_inputs.push(escape_path(&copy_obj_path));
+                } else {
+                    link_inputs.push(escape_path(&obj_path));
+                }
+            }
+        }
+
+        ninja += &format!(
+            "build {}: split {} | {}\n\n",
+            split_outputs.join(" "),
+            escape_path(Path::new(&config.executable)),
+            sources.join(" ")
+        );
+        ninja += &edges;
+        ninja += "\n";
+
+        let link_script_path = if self.use_nonmatching {
+            build_dir.join("link_nonmatching.ld")
+        } else {
+            build_dir.join("link.ld")
+        };
+        ninja += &format!(
+            "build {} {}: link {} | {} {}\n\n",
+            escape_path(&exe_path),
+            escape_path(&donor_path),
+            link_inputs.join(" "),
+            escape_path(&link_script_path),
+            escape_path(&donee_path)
+        );
+        ninja += &format!("default {}\n", escape_path(&exe_path));
+
+        fs::write(&self.output, ninja)
+            .map_err(|err| format!("failed to write `{}` ({})", self.output.display(), err))?;
+
+        tool_diagnostic::status(format_args!(
+            "wrote ninja build file to `{}`, run `ninja -f {}` to build `{}`",
+            self.output.display(),
+            self.output.display(),
+            exe_path.display()
+        ));
+
+        Ok(())
+    }
+}
+
+/// the object a unit's own kind builds, which links in place of the original bytes
+fn unit_object(build_dir: &Path, sec_name: &str, unit_i: usize, unit: &Unit) -> Result<PathBuf, String> {
+    let kind = match unit.kind.as_str() {
+        "obj" => {
+            return unit
+                .file
+                .as_ref()
+                .map(PathBuf::from)
+                .ok_or_else(|| format!("obj unit for section `{}`, unit `{}` is missing file path", sec_name, unit_i))
+        }
+        "lib-member" => "lib",
+        kind => kind,
+    };
+
+    Ok(build_dir.join(format!("{}_{}_{}.obj", sec_name, kind, unit_i)))
+}
+
+/// a build statement making `out` from `input` with a rule running `tool`
+fn tool_edge(tool: &Tool, rule: &str, out: &Path, input: &Path) -> String {
+    format!(
+        "build {}: {} {}\n  tool_out = {}\n  tool_in = {}\n",
+        escape_path(out),
+        rule,
+        escape_path(input),
+        escape(&tool.arg_path(out)),
+        escape(&tool.arg_path(input))
+    )
+}
+
+/// every file under `dir`, or nothing if it doesn't exist yet
+fn files_in(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut files = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            files.extend(files_in(&path));
+        } else {
+            files.push(path);
+        }
+    }
+    files.sort();
+
+    files
+}
+
+/// `text` with the characters ninja treats specially in a variable or command escaped
+fn escape(text: &str) -> String {
+    text.replace('$', "$$")
+}
+
+/// `path` escaped for a build statement, where spaces and colons also separate paths
+fn escape_path(path: &Path) -> String {
+    escape(&path.display().to_string()).replace(' ', "$ ").replace(':', "$:")
+}
@@ -0,0 +1,111 @@
+use std::{fs, path::PathBuf};
+
+use clap::Args;
+use serde::Serialize;
+
+use crate::{tool_diagnostic, util};
+
+use super::CommandExecute;
+
+/// oldest objdiff which reads the project file as written
+const MIN_VERSION: &str = "2.0.0";
+
+#[derive(Debug, Args)]
+pub struct ObjdiffArgs {
+    /// where to write the objdiff project file, relative to the project root
+    #[arg(long, value_name = "PATH", default_value = "objdiff.json")]
+    pub output: PathBuf,
+}
+
+/// an objdiff project file
+#[derive(Debug, Serialize)]
+struct Project {
+    min_version: &'static str,
+    /// objects are built by `pod split` and `pod gen`, which objdiff can't run for a single object
+    build_target: bool,
+    build_base: bool,
+    watch_patterns: Vec<&'static str>,
+    units: Vec<ProjectUnit>,
+}
+
+#[derive(Debug, Serialize)]
+struct ProjectUnit {
+    name: String,
+    /// the original bytes, written by split
+    target_path: String,
+    /// the unit as built by gen
+    base_path: String,
+    metadata: ProjectUnitMetadata,
+}
+
+#[derive(Debug, Serialize)]
+struct ProjectUnitMetadata {
+    complete: bool,
+    source_path: String,
+}
+
+impl CommandExecute for ObjdiffArgs {
+    /// writes an objdiff project comparing every c and asm unit against its original bytes
+    fn execute(&self) -> Result<(), String> {
+        let config = util::get_config()?;
+        let build_dir = util::build_dir();
+
+        let mut units = Vec::new();
+        for sec in config.sections.iter() {
+            for (unit_i, unit) in sec.units.iter().enumerate() {
+                if unit.kind != "c" && unit.kind != "asm" {
+                    continue;
+                }
+                let Some(file) = &unit.file else {
+                    continue;
+                };
+
+                let mut name = match &unit.name {
+                    Some(name) => name.clone(),
+                    None => file.rsplit_once('.').map_or(file.as_str(), |(stem, _)| stem).to_string(),
+                };
+                // objdiff tells units apart by name, and several units can share a file
+                if units.iter().any(|other: &ProjectUnit| other.name == name) {
+                    name = format!("{} ({} {})", name, sec.name, unit_i);
+                }
+                let status = unit
+                    .status()
+                    .map_err(|err| format!("section `{}`, unit `{}` has {}", sec.name, unit_i, err))?;
+
+                units.push(ProjectUnit {
+                    name,
+                    target_path: build_dir.join(format!("{}_ref_{}.obj", sec.name, unit_i)).display().to_string(),
+                    base_path: build_dir
+                        .join(format!("{}_{}_{}.obj", sec.name, unit.kind, unit_i))
+                        .display()
+                        .to_string(),
+                    metadata: ProjectUnitMetadata {
+                        complete: status == "matching",
+                        source_path: file.clone(),
+                    },
+                });
+            }
+        }
+
+        let project = Project {
+            min_version: MIN_VERSION,
+            build_target: false,
+            build_base: false,
+            watch_patterns: vec!["*.c", "*.h", "*.asm", "*.inc"],
+            units,
+        };
+
+        let json = serde_json::to_string_pretty(&project)
+            .map_err(|err| format!("failed to serialize objdiff project ({})", err))?;
+        fs::write(&self.output, json + "\n")
+            .map_err(|err| format!("failed to write `{}` ({})", self.output.display(), err))?;
+
+        tool_diagnostic::status(format_args!(
+            "wrote objdiff project with {} units to `{}`, run `pod split` and `pod gen` to build its objects",
+            project.units.len(),
+            self.output.display()
+        ));
+
+        Ok(())
+    }
+}
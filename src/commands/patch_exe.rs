@@ -1,17 +1,32 @@
-use std::{fs, option, path::Path, process::Command};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
 
 use clap::Args;
 use goblin::pe::{
-    section_table::{self, SectionTable},
+    section_table::SectionTable,
     PE,
 };
 
-use crate::util;
+use crate::{
+    config::Config,
+    image,
+    reloc::{self, BaseRelocations},
+    tool_diagnostic, util,
+};
 
 use super::CommandExecute;
 
 #[derive(Debug, Args)]
-pub struct PatchExeArgs {}
+pub struct PatchExeArgs {
+    /// rebuild the `.reloc` section from the relocations of the linked units
+    #[arg(long)]
+    pub regenerate_relocs: bool,
+    /// take relocations from the compiled code of nonmatching units, as linked by `link --use-nonmatching`
+    #[arg(long)]
+    pub use_nonmatching: bool,
+}
 
 impl CommandExecute for PatchExeArgs {
     fn execute(&self) -> Result<(), String> {
@@ -22,15 +37,10 @@ impl CommandExecute for PatchExeArgs {
 
         let original_pe = PE::parse(&original_file)
             .map_err(|err| format!("failed to parse original executable ({})", err))?;
+        config.check(&original_pe)?;
 
-        let build_dir = Path::new("build");
-        let binding = build_dir.join(
-            Path::new(&config.executable)
-                .file_name()
-                .unwrap()
-                .to_str()
-                .unwrap(),
-        );
+        let build_dir = util::build_dir();
+        let binding = build_dir.join(config.output_name()?);
         let linked_file_path = binding.to_str().unwrap();
 
         let linked_file = fs::read(linked_file_path)
@@ -55,43 +65,204 @@ impl CommandExecute for PatchExeArgs {
 
         let mut off = sig_ptr as usize + 0x18 + linked_pe.header.coff_header.size_of_optional_header as usize;
 
+        let mut reloc_sec_header = None;
         for _ in 0..linked_pe.sections.len() {
             let i_sec = SectionTable::parse(&linked_file, &mut off, 0).map_err(|err| format!("failed to parse section table in linked executable ({})", err))?;
 
+            if i_sec.name().ok() == Some(".reloc") {
+                reloc_sec_header = Some((off - 40, i_sec.clone()));
+            }
+
             if let Some(original_sec) = original_pe.sections.iter().find(|sec| sec.name == i_sec.name) {
                 patched_file[off - 32..off - 28].copy_from_slice(&original_sec.virtual_size.to_le_bytes());
-                println!("patched virtual size for section {}", i_sec.name().unwrap());
+                tool_diagnostic::status(format_args!("patched virtual size for section {}", i_sec.name().unwrap()));
             }
         }
 
-        off = sig_ptr as usize + 0x18 + linked_pe.header.coff_header.size_of_optional_header as usize - 0x80;
-        
         if let Some(optional_header) = original_pe.header.optional_header {
-            if let Some(export_table) = optional_header.data_directories.get_export_table() {
-                patched_file[off..off + 4].copy_from_slice(&export_table.virtual_address.to_le_bytes());
-                patched_file[off + 4..off + 8].copy_from_slice(&export_table.size.to_le_bytes());
-            }
-            off += 8;
-            
-            if let Some(import_table) = optional_header.data_directories.get_import_table() {
-                patched_file[off..off + 4].copy_from_slice(&import_table.virtual_address.to_le_bytes());
-                patched_file[off + 4..off + 8].copy_from_slice(&import_table.size.to_le_bytes());
+            let directories = [
+                optional_header.data_directories.get_export_table(),
+                optional_header.data_directories.get_import_table(),
+                optional_header.data_directories.get_resource_table(),
+            ];
+            for (index, directory) in directories.iter().enumerate() {
+                if let Some(directory) = directory {
+                    let off = image::data_directory_offset(&patched_file, index)?;
+                    patched_file[off..off + 4].copy_from_slice(&directory.virtual_address.to_le_bytes());
+                    patched_file[off + 4..off + 8].copy_from_slice(&directory.size.to_le_bytes());
+                }
             }
-            off += 8;
 
-            if let Some(resource_table) = optional_header.data_directories.get_resource_table() {
-                patched_file[off..off + 4].copy_from_slice(&resource_table.virtual_address.to_le_bytes());
-                patched_file[off + 4..off + 8].copy_from_slice(&resource_table.size.to_le_bytes());
+            // todo: do the rest of these
+        }
+
+        if self.regenerate_relocs {
+            let (reloc_sec_header_off, reloc_sec) = reloc_sec_header
+                .ok_or("linked executable has no `.reloc` section to regenerate")?;
+
+            let relocs = self.collect_relocations(&config, &original_file, &original_pe, build_dir)?;
+            let table = reloc::build_base_relocation_table(&relocs);
+
+            if table.len() > reloc_sec.size_of_raw_data as usize {
+                return Err(format!(
+                    "regenerated base relocation table does not fit in the `.reloc` section: {} vs {} bytes",
+                    table.len(),
+                    reloc_sec.size_of_raw_data
+                ));
             }
-            off += 8;
 
-            // todo: do the rest of these
+            let data_start = reloc_sec.pointer_to_raw_data as usize;
+            let data_end = data_start + reloc_sec.size_of_raw_data as usize;
+            patched_file[data_start..data_end].fill(0);
+            patched_file[data_start..data_start + table.len()].copy_from_slice(&table);
+
+            // every section was given the original's virtual size above, which is only grown when the
+            // new table doesn't fit in it, so a build that matches keeps the original's section header
+            let virtual_size_off = reloc_sec_header_off + 8;
+            let virtual_size = u32::from_le_bytes(
+                patched_file[virtual_size_off..virtual_size_off + 4].try_into().unwrap(),
+            );
+            patched_file[virtual_size_off..virtual_size_off + 4]
+                .copy_from_slice(&virtual_size.max(table.len() as u32).to_le_bytes());
+
+            // the base relocation table is the sixth data directory
+            let dd_off = image::data_directory_offset(&patched_file, 5)?;
+            patched_file[dd_off..dd_off + 4].copy_from_slice(&reloc_sec.virtual_address.to_le_bytes());
+            patched_file[dd_off + 4..dd_off + 8].copy_from_slice(&(table.len() as u32).to_le_bytes());
+
+            tool_diagnostic::status(format_args!("regenerated `.reloc` section with {} base relocations", relocs.len()));
         }
 
+        let timestamp_offs = image::timestamp_offsets(&patched_file, &linked_pe, &original_pe)
+            .map_err(|err| format!("failed to locate timestamps in linked executable ({})", err))?;
+        match config.timestamp()? {
+            None => {
+                let original_offs = image::timestamp_offsets(&original_file, &original_pe, &original_pe)
+                    .map_err(|err| format!("failed to locate timestamps in original executable ({})", err))?;
+                let read = |off: usize| u32::from_le_bytes(original_file[off..off + 4].try_into().unwrap());
+
+                // both are found in the same order, so they pair up as long as the linked image has the
+                // same headers, otherwise there is no telling which is which and the COFF timestamp is
+                // the best guess for all of them
+                let coff_timestamp = original_pe.header.coff_header.time_date_stamp;
+                let paired = original_offs.len() == timestamp_offs.len();
+                for (i, timestamp_off) in timestamp_offs.iter().enumerate() {
+                    let timestamp = if paired { read(original_offs[i]) } else { coff_timestamp };
+                    patched_file[*timestamp_off..*timestamp_off + 4].copy_from_slice(&timestamp.to_le_bytes());
+                }
+                if paired {
+                    tool_diagnostic::status(format_args!("preserved {} original timestamps", timestamp_offs.len()));
+                } else {
+                    tool_diagnostic::status(format_args!(
+                        "set {} timestamps to the original COFF timestamp `0x{:08X}`, as the original has {}",
+                        timestamp_offs.len(),
+                        coff_timestamp,
+                        original_offs.len()
+                    ));
+                }
+            }
+            Some(timestamp) => {
+                for timestamp_off in timestamp_offs.iter() {
+                    patched_file[*timestamp_off..*timestamp_off + 4].copy_from_slice(&timestamp.to_le_bytes());
+                }
+                tool_diagnostic::status(format_args!("set {} timestamps to `0x{:08X}`", timestamp_offs.len(), timestamp));
+            }
+        }
+
+        // only images that shipped with a checksum get one, so matching builds stay matching
+        let checksum_off = image::checksum_offset(&patched_file)?;
+        let original_checksum = original_pe
+            .header
+            .optional_header
+            .map_or(0, |optional_header| optional_header.windows_fields.check_sum);
+        let checksum = if original_checksum != 0 {
+            image::compute_checksum(&patched_file)?
+        } else {
+            0
+        };
+        patched_file[checksum_off..checksum_off + 4].copy_from_slice(&checksum.to_le_bytes());
+        tool_diagnostic::status(format_args!("set checksum to `0x{:08X}`", checksum));
+
         fs::write(linked_file_path, patched_file).map_err(|err| format!("failed to write patched linked executable to disk ({})", err))?;
 
-        println!("successfully wrote patched linked executable to `{}`", linked_file_path);
+        tool_diagnostic::status(format_args!("successfully wrote patched linked executable to `{}`", linked_file_path));
 
         Ok(())
     }
 }
+
+impl PatchExeArgs {
+    /// gathers base relocations for every unit, from the original executable for units that link
+    /// original bytes and from the unit object file otherwise
+    fn collect_relocations(
+        &self,
+        config: &Config,
+        original_file: &[u8],
+        original_pe: &PE,
+        build_dir: &Path,
+    ) -> Result<BaseRelocations, String> {
+        let original_relocs = reloc::parse_base_relocations(original_file, original_pe)
+            .map_err(|err| format!("failed to read original base relocations ({})", err))?;
+
+        let mut relocs = BaseRelocations::new();
+        for cfg_sec in config.sections.iter() {
+            // the table being regenerated does not describe itself
+            if cfg_sec.name == ".reloc" {
+                continue;
+            }
+
+            for (unit_i, unit) in cfg_sec.units.iter().enumerate() {
+                let rva = (unit.addr_virtual - original_pe.image_base) as u32;
+
+                let links_original = unit.links_original().map_err(|err| {
+                    format!("section `{}`, unit `{}` has {}", cfg_sec.name, unit_i, err)
+                })?;
+
+                // prebuilt objects are linked with their grouped sections, see split
+                let (obj_path, obj_sec_name, grouped) = match unit.kind.as_str() {
+                    "asm" if !links_original || self.use_nonmatching => {
+                        (build_dir.join(format!("{}_asm_{}.obj", cfg_sec.name, unit_i)), "POD", false)
+                    }
+                    "c" if !links_original || self.use_nonmatching => (
+                        build_dir.join(format!("{}_c_{}.obj", cfg_sec.name, unit_i)),
+                        cfg_sec.name.as_str(),
+                        false,
+                    ),
+                    "obj" if !links_original || self.use_nonmatching => match &unit.file {
+                        Some(obj_path) => (PathBuf::from(obj_path), cfg_sec.name.as_str(), true),
+                        None => {
+                            return Err(format!(
+                                "obj unit for section `{}`, unit `{}` is missing file path",
+                                cfg_sec.name, unit_i
+                            ))
+                        }
+                    },
+                    "lib-member" if !links_original || self.use_nonmatching => (
+                        build_dir.join(format!("{}_lib_{}.obj", cfg_sec.name, unit_i)),
+                        cfg_sec.name.as_str(),
+                        true,
+                    ),
+                    _ => {
+                        relocs.extend(original_relocs.range(rva..rva + unit.raw_size as u32));
+                        continue;
+                    }
+                };
+
+                let obj_file = fs::read(&obj_path).map_err(|err| {
+                    format!("failed to open object file `{}` ({})", obj_path.display(), err)
+                })?;
+
+                relocs.extend(
+                    reloc::collect_object_relocations(&obj_file, obj_sec_name, grouped, rva).map_err(|err| {
+                        format!(
+                            "failed to collect relocations for section `{}`, unit `{}` ({})",
+                            cfg_sec.name, unit_i, err
+                        )
+                    })?,
+                );
+            }
+        }
+
+        Ok(relocs)
+    }
+}
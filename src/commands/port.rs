@@ -0,0 +1,440 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use goblin::pe::PE;
+
+use crate::{
+    analysis::{self, Function},
+    config::{Config, Section, Symbol, Unit, UnitLayout},
+    tool_diagnostic, util,
+};
+
+use super::CommandExecute;
+
+#[derive(Debug, Args)]
+pub struct PortArgs {
+    /// the executable the config describes
+    #[arg(long, value_name = "EXE")]
+    pub from: String,
+    /// the executable to carry the symbols and units over to
+    #[arg(long, value_name = "EXE")]
+    pub to: String,
+    /// where to write the ported config, relative to the project root
+    #[arg(long, value_name = "PATH", default_value = "pod.ported.toml")]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum MatchKind {
+    Content,
+    Strings,
+    Calls,
+}
+
+impl CommandExecute for PortArgs {
+    /// matches the functions of the old and new executables and writes a config for the new one
+    /// holding every symbol and unit that could be carried over
+    fn execute(&self) -> Result<(), String> {
+        let config = util::get_config()?;
+
+        if config.sources.iter().any(|source| Path::new(&source.path) == self.output) {
+            return Err(format!("`{}` is part of the config being ported, pick another `--output`", self.output.display()));
+        }
+
+        let old_file = fs::read(&self.from)
+            .map_err(|err| format!("failed to open old executable `{}` ({})", self.from, err))?;
+        let old_pe = PE::parse(&old_file).map_err(|err| format!("failed to parse old executable ({})", err))?;
+        if blake3::hash(&old_file).to_string() != config.hash {
+            return Err(format!(
+                "`{}` is not the executable `{}` describes, its hash differs",
+                self.from,
+                util::config_path().display()
+            ));
+        }
+
+        let new_file = fs::read(&self.to)
+            .map_err(|err| format!("failed to open new executable `{}` ({})", self.to, err))?;
+        let new_pe = PE::parse(&new_file).map_err(|err| format!("failed to parse new executable ({})", err))?;
+
+        let old_functions = analysis::find_functions(&old_file, &old_pe)?;
+        let new_functions = analysis::find_functions(&new_file, &new_pe)?;
+        let matches = match_functions(&old_functions, &new_functions);
+
+        // what matched functions point to is matched too, which is how data carries over
+        let mut pointers: BTreeMap<u32, Option<u32>> = BTreeMap::new();
+        for (&old_i, &(new_i, _)) in matches.iter() {
+            let (old_pointers, new_pointers) = (&old_functions[old_i].pointers, &new_functions[new_i].pointers);
+            if old_pointers.len() != new_pointers.len() {
+                continue;
+            }
+
+            for (&old_pointer, &new_pointer) in old_pointers.iter().zip(new_pointers.iter()) {
+                // a pointer matched differently in different places is not trusted
+                let entry = pointers.entry(old_pointer).or_insert(Some(new_pointer));
+                if *entry != Some(new_pointer) {
+                    *entry = None;
+                }
+            }
+        }
+
+        let sections = |pe: &PE| -> Result<BTreeMap<String, (u32, u32)>, String> {
+            pe.sections
+                .iter()
+                .map(|sec| {
+                    let name = sec.name().map_err(|err| format!("failed to get section name ({})", err))?;
+                    Ok((name.to_string(), (sec.virtual_address, sec.virtual_address + sec.size_of_raw_data)))
+                })
+                .collect()
+        };
+        let old_sections = sections(&old_pe)?;
+        let new_sections = sections(&new_pe)?;
+
+        // section boundaries carry over by name, anything else only if it is pointed to by or inside
+        // a matched function
+        let port_rva = |rva: u32| -> Option<u32> {
+            if let Some(&new_rva) = pointers.get(&rva) {
+                return new_rva;
+            }
+
+            for (name, &(start, end)) in old_sections.iter() {
+                if let Some(&(new_start, new_end)) = new_sections.get(name) {
+                    if rva == start {
+                        return Some(new_start);
+                    }
+                    if rva == end {
+                        return Some(new_end);
+                    }
+                }
+            }
+
+            let old_i = old_functions.partition_point(|function| function.rva <= rva).checked_sub(1)?;
+            let (new_i, _) = matches.get(&old_i)?;
+            let offset = rva - old_functions[old_i].rva;
+            (offset < old_functions[old_i].size && offset < new_functions[*new_i].size)
+                .then(|| new_functions[*new_i].rva + offset)
+        };
+        let port_addr = |addr_virtual: usize| -> Option<usize> {
+            let rva = u32::try_from(addr_virtual.checked_sub(old_pe.image_base)?).ok()?;
+            Some(new_pe.image_base + port_rva(rva)? as usize)
+        };
+        // an end is either the start of what follows or just past the last byte of a function
+        let port_end = |end: usize| port_addr(end).or_else(|| Some(port_addr(end.checked_sub(1)?)? + 1));
+
+        let mut symbols = Vec::new();
+        let mut unported_symbols = Vec::new();
+        for symbol in config.symbols.iter() {
+            match port_addr(symbol.addr_virtual) {
+                Some(addr_virtual) => symbols.push(Symbol {
+                    name: symbol.name.clone(),
+                    addr_virtual,
+                    origin: 0,
+                }),
+                None => unported_symbols.push(symbol),
+            }
+        }
+
+        let mut new_config_sections = Vec::new();
+        let mut unported_units = Vec::new();
+        let mut unit_count = 0;
+        for sec in new_pe.sections.iter() {
+            let name = sec.name().map_err(|err| format!("failed to get section name ({})", err))?;
+            let sec_start = new_pe.image_base + sec.virtual_address as usize;
+            let sec_end = sec_start + sec.size_of_raw_data as usize;
+
+            // copy units only fill the gaps, so only units with code or a name of their own are carried over
+            let mut units: Vec<Unit> = Vec::new();
+            for cfg_sec in config.sections.iter().filter(|cfg_sec| cfg_sec.name == name) {
+                for (unit_i, unit) in cfg_sec.units.iter().enumerate() {
+                    if unit.kind == "copy" && unit.name.is_none() {
+                        continue;
+                    }
+                    unit_count += 1;
+
+                    let start = port_addr(unit.addr_virtual);
+                    let end = port_end(unit.addr_virtual + unit.raw_size);
+                    match start.zip(end) {
+                        Some((start, end))
+                            if start < end
+                                && start >= sec_start
+                                && end <= sec_end
+                                && units.iter().all(|other| {
+                                    end <= other.addr_virtual || start >= other.addr_virtual + other.raw_size
+                                }) =>
+                        {
+                            units.push(Unit {
+                                addr_virtual: start,
+                                raw_size: end - start,
+                                layout: UnitLayout {
+                                    shared: unit.layout.shared,
+                                    ..UnitLayout::default()
+                                },
+                                written: None,
+                                ..unit.clone()
+                            })
+                        }
+                        _ => unported_units.push((cfg_sec.name.as_str(), unit_i, unit)),
+                    }
+                }
+            }
+            units.sort_by_key(|unit| unit.addr_virtual);
+
+            let mut filled_units = Vec::new();
+            let mut last_unit_end = sec_start;
+            for unit in units.into_iter().chain(std::iter::once(copy_unit(sec_end, 0))) {
+                if unit.addr_virtual > last_unit_end {
+                    filled_units.push(copy_unit(last_unit_end, unit.addr_virtual - last_unit_end));
+                }
+                last_unit_end = unit.addr_virtual + unit.raw_size;
+                if unit.raw_size > 0 {
+                    filled_units.push(unit);
+                }
+            }
+
+            new_config_sections.push(Section {
+                name: name.to_string(),
+                addr_virtual: Some(sec_start),
+                units: filled_units,
+                origin: Default::default(),
+            });
+        }
+
+        // units of sections the new executable doesn't have have nowhere to go
+        for cfg_sec in config.sections.iter().filter(|cfg_sec| !new_sections.contains_key(&cfg_sec.name)) {
+            for (unit_i, unit) in cfg_sec.units.iter().enumerate() {
+                if unit.kind == "copy" && unit.name.is_none() {
+                    continue;
+                }
+                unit_count += 1;
+                unported_units.push((cfg_sec.name.as_str(), unit_i, unit));
+            }
+        }
+
+        let new_config = Config {
+            executable: util::relative_to_root(&self.to),
+            hash: blake3::hash(&new_file).to_string(),
+            output: None,
+            sections: new_config_sections,
+            symbols,
+            include: Vec::new(),
+            versions: Vec::new(),
+            version: None,
+            sources: Vec::new(),
+            ..config.clone()
+        };
+        let toml_string = toml::to_string_pretty(&new_config)
+            .map_err(|err| format!("failed to serialize ported config ({})", err))?;
+        fs::write(&self.output, toml_string)
+            .map_err(|err| format!("failed to write `{}` ({})", self.output.display(), err))?;
+
+        let count = |kind| matches.values().filter(|(_, match_kind)| *match_kind == kind).count();
+        tool_diagnostic::status(format_args!(
+            "matched {} of {} functions, {} by content, {} by strings and {} by call graph",
+            matches.len(),
+            old_functions.len(),
+            count(MatchKind::Content),
+            count(MatchKind::Strings),
+            count(MatchKind::Calls)
+        ));
+        tool_diagnostic::status(format_args!(
+            "carried over {} of {} symbols and {} of {} units",
+            config.symbols.len() - unported_symbols.len(),
+            config.symbols.len(),
+            unit_count - unported_units.len(),
+            unit_count
+        ));
+
+        let unmatched: Vec<&Function> = old_functions
+            .iter()
+            .enumerate()
+            .filter(|(old_i, _)| !matches.contains_key(old_i))
+            .map(|(_, function)| function)
+            .collect();
+        if !unmatched.is_empty() {
+            tool_diagnostic::status("\nunmatched functions:");
+            for function in unmatched {
+                let addr_virtual = old_pe.image_base + function.rva as usize;
+                let name = config
+                    .symbols
+                    .iter()
+                    .find(|symbol| symbol.addr_virtual == addr_virtual)
+                    .map(|symbol| format!("  {}", symbol.name))
+                    .unwrap_or_default();
+                tool_diagnostic::status(format_args!("  0x{:X}  0x{:X} bytes{}", addr_virtual, function.size, name));
+            }
+        }
+
+        if !unported_symbols.is_empty() {
+            tool_diagnostic::status("\nsymbols not carried over:");
+            for symbol in unported_symbols {
+                tool_diagnostic::status(format_args!("  0x{:X}  {}", symbol.addr_virtual, symbol.name));
+            }
+        }
+
+        if !unported_units.is_empty() {
+            tool_diagnostic::status("\nunits not carried over:");
+            for (sec_name, unit_i, unit) in unported_units {
+                tool_diagnostic::status(format_args!(
+                    "  section `{}`, unit `{}`, {}{}",
+                    sec_name,
+                    unit_i,
+                    unit.kind,
+                    unit.file.as_ref().map(|file| format!(" `{}`", file)).unwrap_or_default()
+                ));
+            }
+        }
+
+        tool_diagnostic::status(format_args!("\nwrote ported config to `{}`", self.output.display()));
+
+        Ok(())
+    }
+}
+
+fn copy_unit(addr_virtual: usize, raw_size: usize) -> Unit {
+    Unit {
+        name: None,
+        kind: "copy".to_string(),
+        file: None,
+        member: None,
+        addr_virtual,
+        raw_size,
+        status: None,
+        layout: UnitLayout::default(),
+        written: None,
+    }
+}
+
+/// pairs old functions with new ones, by index, first those whose masked bytes or referenced
+/// strings are unique to one function on both sides, then the callees of matched functions in the
+/// order they are called, repeating until nothing more matches
+fn match_functions(old: &[Function], new: &[Function]) -> BTreeMap<usize, (usize, MatchKind)> {
+    let mut matches = BTreeMap::new();
+    let mut new_matched = BTreeSet::new();
+
+    let old_index: BTreeMap<u32, usize> = old.iter().enumerate().map(|(i, function)| (function.rva, i)).collect();
+    let new_index: BTreeMap<u32, usize> = new.iter().enumerate().map(|(i, function)| (function.rva, i)).collect();
+
+    loop {
+        let matched_before = matches.len();
+
+        match_unique(old, new, &mut matches, &mut new_matched, MatchKind::Content, |function| {
+            Some(*function.hash.as_bytes())
+        });
+        match_unique(old, new, &mut matches, &mut new_matched, MatchKind::Strings, |function| {
+            (!function.strings.is_empty()).then(|| function.strings.clone())
+        });
+
+        let pairs: Vec<(usize, usize)> = matches.iter().map(|(&old_i, &(new_i, _))| (old_i, new_i)).collect();
+        for (old_i, new_i) in pairs {
+            if old[old_i].calls.len() != new[new_i].calls.len() {
+                continue;
+            }
+
+            for (old_callee, new_callee) in old[old_i].calls.iter().zip(new[new_i].calls.iter()) {
+                let (Some(&old_callee), Some(&new_callee)) = (old_index.get(old_callee), new_index.get(new_callee)) else {
+                    continue;
+                };
+                if !matches.contains_key(&old_callee) && !new_matched.contains(&new_callee) {
+                    matches.insert(old_callee, (new_callee, MatchKind::Calls));
+                    new_matched.insert(new_callee);
+                }
+            }
+        }
+
+        if matches.len() == matched_before {
+            return matches;
+        }
+    }
+}
+
+/// matches the functions not matched yet which are the only one with their `key` on both sides
+fn match_unique<K: Ord>(
+    old: &[Function],
+    new: &[Function],
+    matches: &mut BTreeMap<usize, (usize, MatchKind)>,
+    new_matched: &mut BTreeSet<usize>,
+    kind: MatchKind,
+    key: impl Fn(&Function) -> Option<K>,
+) {
+    let group = |functions: &[Function], matched: &dyn Fn(usize) -> bool| {
+        let mut groups: BTreeMap<K, Vec<usize>> = BTreeMap::new();
+        for (i, function) in functions.iter().enumerate() {
+            if let Some(key) = key(function).filter(|_| !matched(i)) {
+                groups.entry(key).or_default().push(i);
+            }
+        }
+        groups
+    };
+
+    let old_groups = group(old, &|old_i| matches.contains_key(&old_i));
+    let new_groups = group(new, &|new_i| new_matched.contains(&new_i));
+
+    for (key, old_is) in old_groups {
+        if let (&[old_i], Some(&[new_i])) = (&old_is[..], new_groups.get(&key).map(Vec::as_slice)) {
+            matches.insert(old_i, (new_i, kind));
+            new_matched.insert(new_i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::analysis::Function;
+
+    use super::{match_functions, MatchKind};
+
+    fn function(rva: u32, bytes: &[u8], strings: &[&str], calls: &[u32]) -> Function {
+        Function {
+            rva,
+            size: bytes.len() as u32,
+            hash: blake3::hash(bytes),
+            calls: calls.to_vec(),
+            pointers: Vec::new(),
+            strings: strings.iter().map(|string| string.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn matches_by_content_then_strings_then_callees() {
+        let old = [
+            function(0x1000, b"main", &[], &[0x1100, 0x1200]),
+            function(0x1100, b"load v1", &["config.ini"], &[]),
+            function(0x1200, b"ret", &[], &[0x1300]),
+            function(0x1300, b"draw v1", &[], &[]),
+            function(0x1400, b"ret", &[], &[]),
+        ];
+        let new = [
+            function(0x2000, b"ret", &[], &[]),
+            function(0x2100, b"main", &[], &[0x2300, 0x2400]),
+            function(0x2200, b"draw v2", &[], &[]),
+            function(0x2300, b"load v2", &["config.ini"], &[]),
+            function(0x2400, b"ret", &[], &[0x2200]),
+        ];
+
+        let matches = match_functions(&old, &new);
+        assert_eq!(
+            matches.into_iter().collect::<Vec<_>>(),
+            [
+                (0, (1, MatchKind::Content)),
+                (1, (3, MatchKind::Strings)),
+                // the identical `ret`s aren't unique, so the one `main` calls is matched through the
+                // call, and so is what that one calls
+                (2, (4, MatchKind::Calls)),
+                (3, (2, MatchKind::Calls)),
+                // which leaves the other `ret` unique on both sides
+                (4, (0, MatchKind::Content)),
+            ]
+        );
+    }
+
+    #[test]
+    fn callees_of_callers_whose_calls_differ_are_left_alone() {
+        let old = [function(0x1000, b"main", &[], &[0x1100]), function(0x1100, b"a", &[], &[])];
+        let new = [function(0x2000, b"main", &[], &[0x2100, 0x2100]), function(0x2100, b"b", &[], &[])];
+
+        assert_eq!(match_functions(&old, &new).into_iter().collect::<Vec<_>>(), [(0, (0, MatchKind::Content))]);
+    }
+}
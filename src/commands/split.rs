@@ -1,13 +1,24 @@
 use std::{
+    collections::BTreeMap,
     fs::{self, File},
     io::Write,
     path::Path,
 };
 
 use clap::Args;
-use goblin::pe::PE;
+use goblin::pe::{
+    header::COFF_MACHINE_X86,
+    relocation::{IMAGE_REL_I386_DIR32, IMAGE_REL_I386_REL32},
+    section_table::{SectionTable, IMAGE_SCN_CNT_CODE},
+    PE,
+};
 
-use crate::util;
+use crate::{
+    analysis,
+    coff::{Object, ObjectRelocation, ObjectSymbol},
+    reloc::{self, BaseRelocations},
+    rsrc, tool_diagnostic, util,
+};
 
 use super::CommandExecute;
 
@@ -22,8 +33,9 @@ impl CommandExecute for SplitArgs {
             .map_err(|err| format!("failed to open executable ({})", err))?;
 
         let pe = PE::parse(&file).map_err(|err| format!("failed to parse executable ({})", err))?;
+        config.check(&pe)?;
 
-        let build_dir = Path::new("build");
+        let build_dir = util::build_dir();
         if !build_dir.exists() {
             fs::create_dir_all(build_dir)
                 .map_err(|err| format!("failed to create build directory ({})", err))?;
@@ -38,13 +50,7 @@ impl CommandExecute for SplitArgs {
             donee_file_data[data_start..data_end].fill(0);
         }
 
-        let exe_name = match Path::new(&config.executable).file_name() {
-            Some(name_os_str) => match name_os_str.to_str() {
-                Some(name_str) => name_str,
-                None => return Err("failed to parse executable path's executable name".to_string()),
-            },
-            None => return Err("executable path is missing final executable name".to_string()),
-        };
+        let exe_name = config.output_name()?;
 
         let donee_file_path = build_dir.join(format!("{}.donee", exe_name));
         let mut donee_file = File::create(&donee_file_path).map_err(|err| {
@@ -62,98 +68,152 @@ impl CommandExecute for SplitArgs {
             )
         })?;
 
-        println!("generated donee executable at `{}`", donee_file_path.display());
+        tool_diagnostic::status(format_args!("generated donee executable at `{}`", donee_file_path.display()));
 
         let mut link_script = String::new();
         link_script += "ENTRY(_start)\n\nSECTIONS {\n";
 
         link_script += &format!("\t_start = 0x{:X};\n\n", pe.image_base + pe.entry);
 
+        // same as the matching link script, except nonmatching units link their own code
+        let mut nonmatching_link_script = link_script.clone();
+
+        // reference objects name what they can with the config's symbols
+        let symbols: BTreeMap<usize, &str> = config
+            .symbols
+            .iter()
+            .rev()
+            .map(|symbol| (symbol.addr_virtual, symbol.name.as_str()))
+            .collect();
+        let relocs = reloc::parse_base_relocations(&file, &pe)?;
+
         for sec in pe.sections.iter() {
             let sec_name = sec
                 .name()
                 .map_err(|err| format!("failed to get section name ({})", err))?;
             if let Some(cfg_sec) = config.sections.iter().find(|i_sec| i_sec.name == sec_name) {
-                link_script += &format!(
+                let sec_header = format!(
                     "\t{} 0x{:X} : {{\n",
                     sec_name,
                     pe.image_base + sec.virtual_address as usize
                 );
+                link_script += &sec_header;
+                nonmatching_link_script += &sec_header;
 
-                let mut last_unit_end: usize = pe.image_base + sec.virtual_address as usize;
-                let mut unit_i = 0;
-                for unit in cfg_sec.units.iter() {
-                    if unit.addr_virtual != last_unit_end {
-                        return Err(format!("in section `{}`, unit `{}` does not begin at the end of the last unit (or start of section)", sec_name, unit_i));
-                    }
+                for (unit_i, unit) in cfg_sec.units.iter().enumerate() {
+                    let links_original = unit.links_original().map_err(|err| {
+                        format!("section `{}`, unit `{}` has {}", sec_name, unit_i, err)
+                    })?;
 
-                    match unit.kind.as_str() {
-                        "copy" => {
-                            let mut asm = String::new();
-                            asm += ".386\n.MODEL flat\nPOD SEGMENT BYTE\n";
-
-                            let data_start = sec.pointer_to_raw_data as usize + unit.addr_virtual
-                                - sec.virtual_address as usize
-                                - pe.image_base;
-                            let data_end = data_start + unit.raw_size;
-                            let data = &file[data_start..data_end]; // some issue here TODO FIX AVERY
-
-                            // 49 is the max bytes MASM supports in one DB call for some reason
-                            for chunk in data.chunks(49) {
-                                asm += "DB ";
-
-                                for byte in chunk.iter() {
-                                    asm += &byte.to_string();
-                                    asm += ", "
-                                }
+                    let data_start = sec.pointer_to_raw_data as usize + unit.addr_virtual
+                        - sec.virtual_address as usize
+                        - pe.image_base;
+                    let data_end = data_start + unit.raw_size;
+                    let data = &file[data_start..data_end]; // some issue here TODO FIX AVERY
 
-                                asm.pop();
-                                asm.pop();
-                                asm += "\n"
-                            }
+                    let unit_entry = match unit.kind.as_str() {
+                        "copy" => {
+                            write_copy_asm(build_dir, sec_name, unit_i, data)?;
 
-                            asm += "POD ENDS\nEND\n";
+                            format!("\t\t{}/{}_copy_{}.obj(POD)\n", build_dir.display(), sec_name, unit_i)
+                        }
+                        "asm" => {
+                            if let Some(asm_path) = &unit.file {
+                                write_reference_object(
+                                    build_dir,
+                                    sec_name,
+                                    unit_i,
+                                    &reference_object(&pe, sec, unit.addr_virtual, data, &symbols, &relocs),
+                                )?;
+                                tool_diagnostic::status(format_args!(
+                                    "added `{}`, unit `{}` asm file `{}` data to linker script",
+                                    sec_name, unit_i, asm_path
+                                ));
 
-                            let asm_path =
-                                build_dir.join(format!("{}_copy_{}.asm", sec_name, unit_i));
-                            let mut asm_file = File::create(&asm_path).map_err(|err| {
-                                format!(
-                                    "failed to create section `{}`, unit `{}` copy asm file ({})",
+                                format!("\t\t{}/{}_asm_{}.obj(POD)\n", build_dir.display(), sec_name, unit_i)
+                            } else {
+                                return Err(format!(
+                                    "asm unit for section `{}`, unit `{}` is missing file path",
+                                    sec_name, unit_i
+                                ));
+                            }
+                        }
+                        "c" => {
+                            if let Some(c_path) = &unit.file {
+                                write_reference_object(
+                                    build_dir,
                                     sec_name,
                                     unit_i,
-                                    err.to_string()
-                                )
-                            })?;
+                                    &reference_object(&pe, sec, unit.addr_virtual, data, &symbols, &relocs),
+                                )?;
+                                tool_diagnostic::status(format_args!(
+                                    "added `{}`, unit `{}` c file `{}` data to linker script",
+                                    sec_name, unit_i, c_path
+                                ));
 
-                            asm_file.write_all(asm.as_bytes()).map_err(|err| {
                                 format!(
-                                    "failed to write section `{}`, unit `{}`, copy asm file ({})",
-                                    sec_name, unit_i, err
+                                    "\t\t{}/{}_c_{}.obj({})\n",
+                                    build_dir.display(),
+                                    sec_name, unit_i, sec_name
                                 )
-                            })?;
+                            } else {
+                                return Err(format!(
+                                    "c unit for section `{}`, unit `{}` is missing file path",
+                                    sec_name, unit_i
+                                ));
+                            }
+                        }
+                        "rsrc" => {
+                            if let Some(rsrc_path) = &unit.file {
+                                let rsrc_dir = Path::new(rsrc_path);
+                                if rsrc::has_resources(rsrc_dir) {
+                                    tool_diagnostic::status(format_args!(
+                                        "section `{}`, unit `{}` resources already extracted to `{}`, skipping",
+                                        sec_name, unit_i, rsrc_path
+                                    ));
+                                } else {
+                                    extract_resources(&pe, unit.addr_virtual, sec_name, unit_i, data, rsrc_dir)?;
+                                }
 
-                            println!(
-                                "wrote section `{}`, unit `{}` copy asm data to `{}`",
-                                sec_name,
-                                unit_i,
-                                asm_path.display()
-                            );
+                                format!("\t\t{}/{}_rsrc_{}.obj(POD)\n", build_dir.display(), sec_name, unit_i)
+                            } else {
+                                return Err(format!(
+                                    "rsrc unit for section `{}`, unit `{}` is missing directory path",
+                                    sec_name, unit_i
+                                ));
+                            }
+                        }
+                        "obj" => {
+                            if let Some(obj_path) = &unit.file {
+                                tool_diagnostic::status(format_args!(
+                                    "added `{}`, unit `{}` object file `{}` to linker script",
+                                    sec_name, unit_i, obj_path
+                                ));
 
-                            link_script +=
-                                &format!("\t\tbuild/{}_copy_{}.obj(POD)\n", sec_name, unit_i,);
+                                format!("\t\t{}({} {}$*)\n", obj_path, sec_name, sec_name)
+                            } else {
+                                return Err(format!(
+                                    "obj unit for section `{}`, unit `{}` is missing file path",
+                                    sec_name, unit_i
+                                ));
+                            }
                         }
-                        "asm" => {
-                            if let Some(asm_path) = &unit.file {
-                                println!(
-                                    "added `{}`, unit `{}` asm file `{}` data to linker script",
-                                    sec_name, unit_i, asm_path
-                                );
+                        "lib-member" => {
+                            if let (Some(lib_path), Some(member)) = (&unit.file, &unit.member) {
+                                tool_diagnostic::status(format_args!(
+                                    "added `{}`, unit `{}` library member `{}({})` to linker script",
+                                    sec_name, unit_i, lib_path, member
+                                ));
 
-                                link_script +=
-                                    &format!("\t\tbuild/{}_asm_{}.obj(POD)\n", sec_name, unit_i);
+                                // gen extracts the member, since ld can't select sections from a member by name
+                                format!(
+                                    "\t\t{}/{}_lib_{}.obj({} {}$*)\n",
+                                    build_dir.display(),
+                                    sec_name, unit_i, sec_name, sec_name
+                                )
                             } else {
                                 return Err(format!(
-                                    "asm unit for section `{}`, unit `{}` is missing file path",
+                                    "lib-member unit for section `{}`, unit `{}` is missing library path or member",
                                     sec_name, unit_i
                                 ));
                             }
@@ -164,22 +224,22 @@ impl CommandExecute for SplitArgs {
                                 sec_name, unit_i, unit.kind
                             ))
                         }
-                    }
+                    };
 
-                    unit_i += 1;
-                    last_unit_end += unit.raw_size;
-                }
+                    if links_original && unit.kind != "copy" {
+                        // the original bytes stand in for this unit unless nonmatching code is requested
+                        write_copy_asm(build_dir, sec_name, unit_i, data)?;
 
-                if last_unit_end
-                    != pe.image_base + (sec.virtual_address + sec.size_of_raw_data) as usize
-                {
-                    return Err(format!(
-                        "sizes of units for section `{}` is not the same as the section size",
-                        sec_name
-                    ));
+                        link_script +=
+                            &format!("\t\t{}/{}_copy_{}.obj(POD)\n", build_dir.display(), sec_name, unit_i);
+                    } else {
+                        link_script += &unit_entry;
+                    }
+                    nonmatching_link_script += &unit_entry;
                 }
 
                 link_script += "\t}\n\n";
+                nonmatching_link_script += "\t}\n\n";
             } else {
                 return Err(format!(
                     "section `{}` is missing unit configuration",
@@ -188,19 +248,215 @@ impl CommandExecute for SplitArgs {
             }
         }
 
-        link_script.pop();
-        link_script += "}\n";
+        for (script, file_name) in [
+            (&mut link_script, "link.ld"),
+            (&mut nonmatching_link_script, "link_nonmatching.ld"),
+        ] {
+            script.pop();
+            *script += "}\n";
 
-        let link_path = build_dir.join("link.ld");
-        let mut link_file = File::create(&link_path)
-            .map_err(|err| format!("failed to create link script file ({})", err))?;
+            let link_path = build_dir.join(file_name);
+            let mut link_file = File::create(&link_path)
+                .map_err(|err| format!("failed to create link script file ({})", err))?;
 
-        link_file
-            .write_all(link_script.as_bytes())
-            .map_err(|err| format!("failed to write link script file ({})", err))?;
+            link_file
+                .write_all(script.as_bytes())
+                .map_err(|err| format!("failed to write link script file ({})", err))?;
 
-        println!("wrote link.ld file to `{}`", link_path.to_str().unwrap());
+            tool_diagnostic::status(format_args!("wrote {} file to `{}`", file_name, link_path.display()));
+        }
 
         Ok(())
     }
 }
+
+
+/// writes a MASM file which reproduces `data` byte for byte
+fn write_copy_asm(build_dir: &Path, sec_name: &str, unit_i: usize, data: &[u8]) -> Result<(), String> {
+    let asm = util::data_asm(data);
+
+    let asm_path = build_dir.join(format!("{}_copy_{}.asm", sec_name, unit_i));
+    let mut asm_file = File::create(&asm_path).map_err(|err| {
+        format!(
+            "failed to create section `{}`, unit `{}` copy asm file ({})",
+            sec_name, unit_i, err
+        )
+    })?;
+
+    asm_file.write_all(asm.as_bytes()).map_err(|err| {
+        format!(
+            "failed to write section `{}`, unit `{}`, copy asm file ({})",
+            sec_name, unit_i, err
+        )
+    })?;
+
+    tool_diagnostic::status(format_args!(
+        "wrote section `{}`, unit `{}` copy asm data to `{}`",
+        sec_name,
+        unit_i,
+        asm_path.display()
+    ));
+
+    Ok(())
+}
+
+/// the original bytes of a unit, which is `data` at `addr_virtual` in `sec`, as an object holding the
+/// symbols inside it and relocations to every symbol it can be seen pointing or branching to, for
+/// comparing against what the unit's source builds to
+fn reference_object(
+    pe: &PE,
+    sec: &SectionTable,
+    addr_virtual: usize,
+    data: &[u8],
+    symbols: &BTreeMap<usize, &str>,
+    relocs: &BaseRelocations,
+) -> Object {
+    let mut object = Object {
+        machine: pe.header.coff_header.machine,
+        section_name: sec.name().unwrap_or_default().to_string(),
+        characteristics: sec.characteristics,
+        data: data.to_vec(),
+        symbols: Vec::new(),
+        relocations: Vec::new(),
+    };
+
+    let end = addr_virtual + data.len();
+    let code = sec.characteristics & IMAGE_SCN_CNT_CODE != 0;
+    for (&symbol_addr, &name) in symbols.range(addr_virtual..end) {
+        object.symbols.push(ObjectSymbol {
+            name: name.to_string(),
+            value: Some((symbol_addr - addr_virtual) as u32),
+            function: code,
+        });
+    }
+
+    // the relocations are those of x86 objects
+    if pe.header.coff_header.machine != COFF_MACHINE_X86 {
+        return object;
+    }
+
+    let relocate = |object: &mut Object, offset: usize, target: usize, typ: u16| {
+        let Some(&name) = symbols.get(&target) else {
+            return;
+        };
+        if offset + 4 > object.data.len()
+            || object
+                .relocations
+                .iter()
+                .any(|relocation| (relocation.offset as usize).abs_diff(offset) < 4)
+        {
+            return;
+        }
+
+        let symbol = match object.symbols.iter().position(|symbol| symbol.name == name) {
+            Some(symbol) => symbol,
+            None => {
+                object.symbols.push(ObjectSymbol {
+                    name: name.to_string(),
+                    value: None,
+                    function: false,
+                });
+                object.symbols.len() - 1
+            }
+        };
+
+        // the addend is kept in place, and there is none
+        object.data[offset..offset + 4].fill(0);
+        object.relocations.push(ObjectRelocation {
+            offset: offset as u32,
+            symbol,
+            typ,
+        });
+    };
+
+    let rva = (addr_virtual - pe.image_base) as u32;
+    for (&reloc_rva, _) in relocs.range(rva..rva + data.len() as u32) {
+        let offset = (reloc_rva - rva) as usize;
+        if let Some(pointer) = data.get(offset..offset + 4) {
+            let target = u32::from_le_bytes(pointer.try_into().unwrap()) as usize;
+            relocate(&mut object, offset, target, IMAGE_REL_I386_DIR32);
+        }
+    }
+    for (i, target) in analysis::relative_branches(data, rva) {
+        relocate(&mut object, i + 1, pe.image_base + target as usize, IMAGE_REL_I386_REL32);
+    }
+
+    object.relocations.sort_by_key(|relocation| relocation.offset);
+
+    object
+}
+
+/// writes the reference object of a unit
+fn write_reference_object(build_dir: &Path, sec_name: &str, unit_i: usize, object: &Object) -> Result<(), String> {
+    let obj_path = build_dir.join(format!("{}_ref_{}.obj", sec_name, unit_i));
+    fs::write(&obj_path, object.to_bytes()).map_err(|err| {
+        format!(
+            "failed to write section `{}`, unit `{}` reference object ({})",
+            sec_name, unit_i, err
+        )
+    })?;
+
+    tool_diagnostic::status(format_args!(
+        "wrote section `{}`, unit `{}` reference object to `{}`",
+        sec_name,
+        unit_i,
+        obj_path.display()
+    ));
+
+    Ok(())
+}
+
+/// unpacks the resource directory at the start of a unit into editable files
+fn extract_resources(
+    pe: &PE,
+    addr_virtual: usize,
+    sec_name: &str,
+    unit_i: usize,
+    data: &[u8],
+    rsrc_dir: &Path,
+) -> Result<(), String> {
+    let rva = (addr_virtual - pe.image_base) as u32;
+
+    let resource_table = pe
+        .header
+        .optional_header
+        .and_then(|optional_header| optional_header.data_directories.get_resource_table().copied());
+    if resource_table.map(|table| table.virtual_address) != Some(rva) {
+        return Err(format!(
+            "rsrc unit for section `{}`, unit `{}` does not begin at the resource directory",
+            sec_name, unit_i
+        ));
+    }
+
+    let table = rsrc::parse_resources(data, rva).map_err(|err| {
+        format!(
+            "failed to parse resources of section `{}`, unit `{}` ({})",
+            sec_name, unit_i, err
+        )
+    })?;
+
+    rsrc::extract_resources(&table, rsrc_dir)?;
+
+    tool_diagnostic::status(format_args!(
+        "extracted {} resources of section `{}`, unit `{}` to `{}`",
+        table.resources.len(),
+        sec_name,
+        unit_i,
+        rsrc_dir.display()
+    ));
+
+    // a rebuild from the extracted files straight away shows whether gen will be able to match the original
+    let rebuilt = rsrc::build_resources(&rsrc::load_resources(rsrc_dir)?, rva, data.len());
+    if rebuilt.as_deref() != Ok(data) {
+        return Err(format!(
+            "resources of section `{}`, unit `{}` extracted to `{}` don't rebuild to the original, make it a copy unit \
+             with `pod unit set-kind 0x{:X} copy`",
+            sec_name,
+            unit_i,
+            rsrc_dir.display(),
+            addr_virtual
+        ));
+    }
+
+    Ok(())
+}
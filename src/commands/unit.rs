@@ -0,0 +1,246 @@
+use std::{fs, ptr};
+
+use clap::{Args, Subcommand};
+use goblin::pe::PE;
+
+use crate::{
+    config::{Config, Unit, UnitLayout, UNIT_KINDS},
+    diagnostic, tool_diagnostic, util,
+};
+
+use super::CommandExecute;
+
+#[derive(Debug, Args)]
+pub struct UnitArgs {
+    #[command(subcommand)]
+    pub command: UnitCommands,
+}
+
+/// edits the units of pod.toml, all addresses are virtual addresses inside a unit
+#[derive(Debug, Subcommand)]
+pub enum UnitCommands {
+    /// splits the unit containing `addr` in two, the new unit starting at `addr` is a copy unit
+    #[command(arg_required_else_help = true)]
+    Split { addr: String },
+    /// merges two adjacent units into the first, the second must be a copy unit
+    #[command(arg_required_else_help = true)]
+    Merge { a: String, b: String },
+    /// changes the kind of the unit containing `addr`
+    #[command(arg_required_else_help = true)]
+    SetKind {
+        addr: String,
+        /// one of `copy`, `asm`, `c`, `rsrc`, `obj` or `lib-member`
+        kind: String,
+        /// source file, resource directory, object file or library, required unless the kind is `copy`
+        #[arg(long)]
+        file: Option<String>,
+        /// library member to link, required if the kind is `lib-member`
+        #[arg(long)]
+        member: Option<String>,
+        /// one of `matching`, `nonmatching` or `wip`
+        #[arg(long)]
+        status: Option<String>,
+    },
+    /// moves the start of the unit beginning at `addr` to `to`, resizing the unit before it
+    #[command(arg_required_else_help = true)]
+    MoveBoundary { addr: String, to: String },
+}
+
+impl CommandExecute for UnitArgs {
+    fn execute(&self) -> Result<(), String> {
+        let mut config = util::get_config()?;
+
+        let (sec_i, message) = match &self.command {
+            UnitCommands::Split { addr } => {
+                let addr = parse_addr(addr)?;
+                let (sec_i, unit_i) = find_unit(&config, addr)?;
+                let unit = &mut config.sections[sec_i].units[unit_i];
+
+                if addr == unit.addr_virtual {
+                    return Err(format!("`0x{:X}` is already the start of a unit", addr));
+                }
+                if unit.kind == "rsrc" {
+                    return Err("rsrc units can't be split".to_string());
+                }
+
+                let unit_end = unit.addr_virtual + unit.raw_size;
+                let layout = unit.layout;
+                unit.raw_size = addr - unit.addr_virtual;
+                config.sections[sec_i].units.insert(
+                    unit_i + 1,
+                    Unit {
+                        name: None,
+                        kind: "copy".to_string(),
+                        file: None,
+                        member: None,
+                        addr_virtual: addr,
+                        raw_size: unit_end - addr,
+                        status: None,
+                        layout: UnitLayout { shared: false, ..layout },
+                        written: None,
+                    },
+                );
+
+                (sec_i, format!("split unit `{}` of section `{}` at `0x{:X}`", unit_i, config.sections[sec_i].name, addr))
+            }
+            UnitCommands::Merge { a, b } => {
+                let (sec_i, a_i) = find_unit(&config, parse_addr(a)?)?;
+                let (b_sec_i, b_i) = find_unit(&config, parse_addr(b)?)?;
+
+                if (sec_i, a_i) == (b_sec_i, b_i) {
+                    return Err("both addresses are in the same unit".to_string());
+                }
+
+                // either order is fine, the earlier unit is the one that is kept
+                let (first_i, second_i) = (a_i.min(b_i), a_i.max(b_i));
+                if b_sec_i != sec_i || second_i != first_i + 1 {
+                    return Err("only adjacent units in the same section can be merged".to_string());
+                }
+
+                let units = &mut config.sections[sec_i].units;
+                if units[second_i].kind != "copy" {
+                    return Err(format!(
+                        "unit `{}` is a `{}` unit, set its kind to `copy` before merging it",
+                        second_i, units[second_i].kind
+                    ));
+                }
+                if units[first_i].kind == "rsrc" {
+                    return Err("rsrc units can't be merged".to_string());
+                }
+
+                let second = units.remove(second_i);
+                units[first_i].raw_size += second.raw_size;
+
+                (sec_i, format!("merged units `{}` and `{}` of section `{}`", first_i, second_i, config.sections[sec_i].name))
+            }
+            UnitCommands::SetKind { addr, kind, file, member, status } => {
+                let (sec_i, unit_i) = find_unit(&config, parse_addr(addr)?)?;
+
+                if !UNIT_KINDS.contains(&kind.as_str()) {
+                    return Err(format!("invalid unit kind `{}`", kind));
+                }
+
+                // the unit now differs from the shared one, so it is written out in full
+                let unit = &mut config.sections[sec_i].units[unit_i];
+                unit.layout.shared = false;
+                unit.kind = kind.clone();
+                if kind == "copy" {
+                    if file.is_some() {
+                        return Err("copy units don't have a file".to_string());
+                    }
+                    unit.file = None;
+                } else if file.is_some() {
+                    unit.file = file.clone();
+                } else if unit.file.is_none() {
+                    return Err(format!("{} units need a file path, set it with `--file`", kind));
+                }
+                if kind == "lib-member" {
+                    if member.is_some() {
+                        unit.member = member.clone();
+                    } else if unit.member.is_none() {
+                        return Err("lib-member units need a member, set it with `--member`".to_string());
+                    }
+                } else if member.is_some() {
+                    return Err("only lib-member units have a member".to_string());
+                } else {
+                    unit.member = None;
+                }
+                if status.is_some() {
+                    unit.status = status.clone();
+                    unit.status()?;
+                }
+
+                (sec_i, format!("set unit `{}` of section `{}` to kind `{}`", unit_i, config.sections[sec_i].name, kind))
+            }
+            UnitCommands::MoveBoundary { addr, to } => {
+                let addr = parse_addr(addr)?;
+                let to = parse_addr(to)?;
+                let (sec_i, unit_i) = find_unit(&config, addr)?;
+
+                let units = &mut config.sections[sec_i].units;
+                if units[unit_i].addr_virtual != addr {
+                    return Err(format!("`0x{:X}` is not the start of a unit", addr));
+                }
+                if unit_i == 0 {
+                    return Err("the start of the first unit is the start of the section".to_string());
+                }
+
+                let unit_end = units[unit_i].addr_virtual + units[unit_i].raw_size;
+                if to <= units[unit_i - 1].addr_virtual || to >= unit_end {
+                    return Err(format!(
+                        "`0x{:X}` is not between `0x{:X}` and `0x{:X}`, which would leave an empty unit",
+                        to,
+                        units[unit_i - 1].addr_virtual,
+                        unit_end
+                    ));
+                }
+                if units[unit_i - 1].kind == "rsrc" || units[unit_i].kind == "rsrc" {
+                    return Err("rsrc units can't be resized".to_string());
+                }
+
+                units[unit_i - 1].raw_size = to - units[unit_i - 1].addr_virtual;
+                units[unit_i].addr_virtual = to;
+                units[unit_i].raw_size = unit_end - to;
+
+                let message = format!(
+                    "moved the boundary between units `{}` and `{}` of section `{}` to `0x{:X}`",
+                    unit_i - 1,
+                    unit_i,
+                    config.sections[sec_i].name,
+                    to
+                );
+                (sec_i, message)
+            }
+        };
+
+        // the text read no longer matches, so there is nothing to point diagnostics at
+        config.forget_sources();
+        check_section(&config, sec_i)?;
+        util::write_config(&config)?;
+
+        tool_diagnostic::status(message);
+        Ok(())
+    }
+}
+
+pub(super) fn parse_addr(addr: &str) -> Result<usize, String> {
+    util::parse_int(addr)
+        .map(|addr| addr as usize)
+        .map_err(|err| format!("invalid address `{}` ({})", addr, err))
+}
+
+/// finds the section and unit indices of the unit containing `addr`
+pub(super) fn find_unit(config: &Config, addr: usize) -> Result<(usize, usize), String> {
+    let (sec, unit_i, _) = config
+        .find_unit(addr)
+        .ok_or_else(|| format!("no unit contains `0x{:X}`", addr))?;
+
+    // sections can share a name across included files, so it's looked up by identity
+    let sec_i = config.sections.iter().position(|other| ptr::eq(other, sec)).unwrap();
+    Ok((sec_i, unit_i))
+}
+
+/// checks an edited section against the executable before anything is written
+fn check_section(config: &Config, sec_i: usize) -> Result<(), String> {
+    let file = fs::read(&config.executable)
+        .map_err(|err| format!("failed to open executable ({})", err))?;
+
+    let pe = PE::parse(&file).map_err(|err| format!("failed to parse executable ({})", err))?;
+
+    let cfg_sec = &config.sections[sec_i];
+    for sec in pe.sections.iter() {
+        let sec_name = sec
+            .name()
+            .map_err(|err| format!("failed to get section name ({})", err))?;
+
+        if sec_name == cfg_sec.name {
+            return diagnostic::report(config.check_section(
+                sec_i,
+                pe.image_base + sec.virtual_address as usize,
+                sec.size_of_raw_data as usize,
+            ));
+        }
+    }
+
+    Err(format!("section `{}` is not in the executable", cfg_sec.name))
+}
@@ -1,33 +1,1102 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use goblin::pe::PE;
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+use crate::{
+    diagnostic::{self, Diagnostic, Source},
+    tool::Tool,
+    util,
+};
+
+pub const UNIT_KINDS: [&str; 6] = ["copy", "asm", "c", "rsrc", "obj", "lib-member"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "UnitDef", into = "UnitDef")]
 pub struct Unit {
+    /// stable name, which is the same in every version and can take the unit's kind, file and
+    /// status from `[units]`
+    pub name: Option<String>,
     pub kind: String,
     pub file: Option<String>,
+    /// name of the archive member a `lib-member` unit links, its file being the archive
+    pub member: Option<String>,
     pub addr_virtual: usize,
     pub raw_size: usize,
+    /// one of `matching`, `nonmatching` or `wip`, defaults to `matching`
+    pub status: Option<String>,
+    /// how the position and size were written, so they are written back the same way
+    pub layout: UnitLayout,
+    /// the position and size exactly as read from pod.toml, until `Config::resolve` checks them
+    pub written: Option<UnitPlacement>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UnitLayout {
+    /// the unit is placed with `offset` from the start of its section rather than `addr_virtual`
+    pub relative: bool,
+    /// the unit is sized with `end` rather than `raw_size`
+    pub uses_end: bool,
+    /// the kind, file, member and status come from the shared unit in `[units]` that has this unit's name
+    pub shared: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UnitPlacement {
+    addr_virtual: Option<usize>,
+    offset: Option<usize>,
+    raw_size: Option<usize>,
+    end: Option<usize>,
+}
+
+/// a unit as written in pod.toml, where the position is given by either `addr_virtual` or a section
+/// relative `offset`, and the size by either `raw_size` or an `end` in the same terms as the position
+#[derive(Serialize, Deserialize)]
+struct UnitDef {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    kind: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    member: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    addr_virtual: Option<Hex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    offset: Option<Hex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    raw_size: Option<Hex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    end: Option<Hex>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    status: Option<String>,
+}
+
+impl From<UnitDef> for Unit {
+    fn from(def: UnitDef) -> Self {
+        let value = |hex: Option<Hex>| hex.map(|Hex(value)| value);
+
+        Unit {
+            name: def.name,
+            // a unit without a kind is completed from `[units]` by `Config::resolve`
+            kind: def.kind.unwrap_or_default(),
+            file: def.file,
+            member: def.member,
+            addr_virtual: 0,
+            raw_size: 0,
+            status: def.status,
+            layout: UnitLayout::default(),
+            written: Some(UnitPlacement {
+                addr_virtual: value(def.addr_virtual),
+                offset: value(def.offset),
+                raw_size: value(def.raw_size),
+                end: value(def.end),
+            }),
+        }
+    }
+}
+
+impl From<Unit> for UnitDef {
+    /// a relative unit is expected to already hold its offset in `addr_virtual`
+    fn from(unit: Unit) -> Self {
+        let start = Some(Hex(unit.addr_virtual));
+        let (size, end) = if unit.layout.uses_end {
+            (None, Some(Hex(unit.addr_virtual + unit.raw_size)))
+        } else {
+            (Some(Hex(unit.raw_size)), None)
+        };
+
+        let shared = unit.layout.shared;
+        UnitDef {
+            name: unit.name,
+            kind: (!shared).then_some(unit.kind),
+            file: unit.file.filter(|_| !shared),
+            member: unit.member.filter(|_| !shared),
+            addr_virtual: if unit.layout.relative { None } else { start },
+            offset: if unit.layout.relative { start } else { None },
+            raw_size: size,
+            end,
+            status: unit.status.filter(|_| !shared),
+        }
+    }
+}
+
+impl Unit {
+    pub fn status(&self) -> Result<&str, String> {
+        match self.status.as_deref() {
+            None => Ok("matching"),
+            Some(status @ ("matching" | "nonmatching" | "wip")) => Ok(status),
+            Some(status) => Err(format!("invalid unit status `{}`", status)),
+        }
+    }
+
+    /// whether the original bytes are linked in place of this unit's code in a matching build
+    pub fn links_original(&self) -> Result<bool, String> {
+        Ok(self.status()? != "matching")
+    }
+
+    /// works out the position and size from how they were written, `sec_addr_virtual` is the start
+    /// of the section for units with an `offset`, errors name the key which is at fault
+    fn resolve(&mut self, sec_addr_virtual: Option<usize>) -> Result<(), (&'static str, String)> {
+        let Some(written) = self.written.take() else {
+            return Ok(());
+        };
+
+        let (start, relative) = match (written.addr_virtual, written.offset) {
+            (Some(addr_virtual), None) => (addr_virtual, false),
+            (None, Some(offset)) => (offset, true),
+            (Some(_), Some(_)) => {
+                return Err(("offset", "has both `addr_virtual` and `offset`, only one is allowed".to_string()))
+            }
+            (None, None) => return Err(("", "is missing `addr_virtual` or `offset`".to_string())),
+        };
+
+        let raw_size = match (written.raw_size, written.end) {
+            (Some(raw_size), None) => raw_size,
+            (raw_size, Some(end)) => {
+                let size = end
+                    .checked_sub(start)
+                    .ok_or_else(|| ("end", format!("`end` `0x{:X}` is before its start `0x{:X}`", end, start)))?;
+                if let Some(raw_size) = raw_size.filter(|&raw_size| raw_size != size) {
+                    return Err((
+                        "raw_size",
+                        format!(
+                            "`raw_size` `0x{:X}` does not match its `end` `0x{:X}`, which gives a size of `0x{:X}`",
+                            raw_size, end, size
+                        ),
+                    ));
+                }
+                size
+            }
+            (None, None) => return Err(("", "is missing `raw_size` or `end`".to_string())),
+        };
+
+        self.addr_virtual = if relative {
+            start
+                + sec_addr_virtual.ok_or_else(|| {
+                    (
+                        "offset",
+                        "has an `offset`, but its section has no `addr_virtual` for it to be relative to".to_string(),
+                    )
+                })?
+        } else {
+            start
+        };
+        self.raw_size = raw_size;
+        self.layout = UnitLayout {
+            relative,
+            uses_end: written.raw_size.is_none(),
+            ..self.layout
+        };
+
+        Ok(())
+    }
+}
+
+/// an address or size, read from either an integer or a decimal or `0x` prefixed hex string and
+/// always written as hex
+#[derive(Debug, Clone, Copy)]
+struct Hex(usize);
+
+impl Serialize for Hex {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        hex::serialize(&self.0, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Hex {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        hex::deserialize(deserializer).map(Hex)
+    }
+}
+
+/// numbers such as addresses, flags and styles, read from either an integer or a decimal or `0x`
+/// prefixed hex string and always written as hex
+pub mod hex {
+    use std::fmt::UpperHex;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use crate::util;
+
+    pub fn serialize<T: Copy + UpperHex, S: Serializer>(value: &T, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("0x{:X}", value))
+    }
+
+    pub fn deserialize<'de, T: TryFrom<u64>, D: Deserializer<'de>>(deserializer: D) -> Result<T, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum IntOrString {
+            // TOML integers are signed, so negative ones have to be read to be told apart
+            Int(i64),
+            String(String),
+        }
+
+        let negative = |value: &dyn std::fmt::Display| {
+            serde::de::Error::custom(format!("`{}` is negative, expected an address, size or other unsigned number", value))
+        };
+        let value = match IntOrString::deserialize(deserializer)? {
+            IntOrString::Int(value) => u64::try_from(value).map_err(|_| negative(&value))?,
+            IntOrString::String(value) if value.trim_start().starts_with('-') => return Err(negative(&value)),
+            IntOrString::String(value) => util::parse_int(&value)
+                .map_err(|err| serde::de::Error::custom(format!("invalid number `{}` ({})", value, err)))?,
+        };
+        T::try_from(value).map_err(|_| serde::de::Error::custom(format!("`0x{:X}` is out of range", value)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "SectionDef", into = "SectionDef")]
 pub struct Section {
     pub name: String,
     //pub addr_file: u32,
     //pub size_file: u32,
-    //pub addr_virtual: u32,
+    /// start of the section, which units with an `offset` are placed relative to
+    pub addr_virtual: Option<usize>,
     //pub size_virtual: u32,
     //pub flags: u32,
     pub units: Vec<Unit>,
+    /// where this was read from, for diagnostics and for writing it back
+    pub origin: Origin,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Serialize, Deserialize)]
+struct SectionDef {
+    name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    addr_virtual: Option<Hex>,
+    units: Vec<Unit>,
+}
+
+impl From<SectionDef> for Section {
+    fn from(def: SectionDef) -> Self {
+        Section {
+            name: def.name,
+            addr_virtual: def.addr_virtual.map(|Hex(addr_virtual)| addr_virtual),
+            units: def.units,
+            origin: Origin::default(),
+        }
+    }
+}
+
+impl From<Section> for SectionDef {
+    fn from(sec: Section) -> Self {
+        let mut units = sec.units;
+        for unit in units.iter_mut() {
+            if unit.layout.relative {
+                unit.addr_virtual -= sec.addr_virtual.unwrap_or(0);
+            }
+        }
+
+        SectionDef {
+            name: sec.name,
+            addr_virtual: sec.addr_virtual.map(Hex),
+            units,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Symbol {
+    pub name: String,
+    #[serde(with = "hex")]
+    pub addr_virtual: usize,
+    /// index into `Config::sources` of the file this was read from
+    #[serde(skip)]
+    pub origin: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
+    /// the executable and its hash, which are given by each version instead when there are versions
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub executable: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub hash: String,
-    pub assembler_path: String,
-    pub compiler_path: String,
-    pub linker_path: String,
+    /// name of the built executable in the build directory, defaults to the executable's file name
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// the toolchain, which falls back to the workspace's and then to `ml`, `cl` and `ld`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assembler_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiler_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linker_path: Option<String>,
+    /// programs the tools are run through, such as `wine` for a Windows toolchain, which are given
+    /// Windows paths, falling back to the workspace's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assembler_wrapper: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compiler_wrapper: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linker_wrapper: Option<String>,
+    /// how wrapped tools are run, instead of the workspace's
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wine: Option<Wine>,
+    /// directories searched for included headers, along with the workspace's
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_dirs: Vec<String>,
+    /// timestamp written into the COFF, export, debug and resource headers of the final image: `preserve`,
+    /// `zero` or a value, defaults to `preserve`, which keeps the original executable's timestamps
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>,
     //pub base_addr_virtual: u64,
     //pub entry: u32,
     //pub subsystem: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub sections: Vec<Section>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<Symbol>,
+    /// files to read more sections and symbols from, relative to this one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// revisions of the executable built from the same source, one of which is picked with `--version`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub versions: Vec<Version>,
+    /// kind, file and status of units by name, for units which are the same in every version
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub units: BTreeMap<String, SharedUnit>,
+    /// name of the version that was picked
+    #[serde(skip)]
+    pub version: Option<String>,
+    /// every file the config was read from, pod.toml first, which diagnostics point into
+    #[serde(skip)]
+    pub sources: Vec<Source>,
+    /// the workspace this config is a member of, if any
+    #[serde(skip)]
+    pub workspace: Option<Workspace>,
+}
+
+/// one revision of the executable, whose sections are read from its own included files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Version {
+    pub name: String,
+    pub executable: String,
+    pub hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output: Option<String>,
+    /// files to read the sections and symbols of this version from, relative to pod.toml
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+}
+
+/// what a unit is, separate from where it is, so one source file serves every version
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedUnit {
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub member: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+}
+
+/// the environment wine runs wrapped tools in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Wine {
+    /// `WINEPREFIX`, relative to the config it is set in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix: Option<String>,
+    /// `WINEDEBUG`, defaults to `-all`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub debug: Option<String>,
+}
+
+/// a workspace pod.toml, which holds only a `[workspace]` table
+#[derive(Debug, Deserialize)]
+struct WorkspaceFile {
+    workspace: Workspace,
+}
+
+/// several executables built together, each in a member directory with its own pod.toml
+#[derive(Debug, Clone, Deserialize)]
+pub struct Workspace {
+    /// member directories, relative to the workspace
+    pub members: Vec<String>,
+    pub assembler_path: Option<String>,
+    pub compiler_path: Option<String>,
+    pub linker_path: Option<String>,
+    pub assembler_wrapper: Option<String>,
+    pub compiler_wrapper: Option<String>,
+    pub linker_wrapper: Option<String>,
+    pub wine: Option<Wine>,
+    /// directories searched for included headers by every member, relative to the workspace
+    #[serde(default)]
+    pub include_dirs: Vec<String>,
+    /// directory of the workspace pod.toml
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+impl Workspace {
+    /// reads the workspace at `path`, or `None` if it is a regular config
+    pub fn load(path: &Path) -> Result<Option<Workspace>, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("failed to open `{}` ({})", path.display(), err))?;
+
+        let table: toml::Table =
+            toml::from_str(&text).map_err(|err| format!("failed to parse `{}` ({})", path.display(), err))?;
+        if !table.contains_key("workspace") {
+            return Ok(None);
+        }
+
+        let file: WorkspaceFile = toml::from_str(&text)
+            .map_err(|err| format!("failed to parse workspace `{}` ({})", path.display(), err))?;
+        Ok(Some(Workspace {
+            root: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            ..file.workspace
+        }))
+    }
+
+    /// finds the workspace that the project configured by `config_path` is a member of, by searching
+    /// its parents for a config of the same name
+    pub fn find(config_path: &Path) -> Result<Option<Workspace>, String> {
+        let file_name = config_path.file_name().unwrap_or("pod.toml".as_ref());
+        let root = config_path.parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        let root = root
+            .canonicalize()
+            .map_err(|err| format!("failed to resolve `{}` ({})", root.display(), err))?;
+
+        for dir in root.ancestors().skip(1) {
+            let path = dir.join(file_name);
+            if !path.is_file() {
+                continue;
+            }
+
+            // configs of unrelated or half edited projects further up are none of this project's
+            // business, only a broken workspace that lists it as a member is reported
+            let Some(table) = fs::read_to_string(&path).ok().and_then(|text| toml::from_str::<toml::Table>(&text).ok())
+            else {
+                continue;
+            };
+            let lists_root = table
+                .get("workspace")
+                .and_then(|workspace| workspace.get("members"))
+                .and_then(toml::Value::as_array)
+                .is_some_and(|members| {
+                    members
+                        .iter()
+                        .filter_map(toml::Value::as_str)
+                        .any(|member| dir.join(member).canonicalize().is_ok_and(|member| member == root))
+                });
+            if lists_root {
+                return Workspace::load(&path);
+            }
+        }
+
+        Ok(None)
+    }
+
+    pub fn member_dirs(&self) -> Vec<PathBuf> {
+        self.members.iter().map(|member| self.root.join(member)).collect()
+    }
+}
+
+/// a file included by pod.toml, which only holds sections and symbols, anything else such as
+/// another `include` or the executable is refused rather than silently ignored
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Include {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sections: Vec<Section>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub symbols: Vec<Symbol>,
+}
+
+/// where a section was read from, as indices into `Config::sources` and that file's sections
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Origin {
+    pub source: usize,
+    pub index: usize,
+}
+
+impl Config {
+    /// reads the config at `path` along with every file it includes, `version` picks one of the
+    /// versions it describes, which is required when it has any
+    pub fn load(path: &Path, version: Option<&str>) -> Result<Config, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("failed to open `{}` ({})", path.display(), err))?;
+        let mut config: Config = toml::from_str(&text).map_err(|err| {
+            match Workspace::load(path) {
+                Ok(Some(workspace)) => format!(
+                    "`{}` is a workspace, run pod in one of its members ({}) or use `pod build`",
+                    path.display(),
+                    workspace.members.iter().map(|member| format!("`{}`", member)).collect::<Vec<_>>().join(", ")
+                ),
+                _ => format!("failed to parse `{}` ({})", path.display(), err),
+            }
+        })?;
+        config.workspace = Workspace::find(path)?;
+
+        config.sources.push(Source {
+            path: path.display().to_string(),
+            text: Some(text),
+        });
+        for (sec_i, sec) in config.sections.iter_mut().enumerate() {
+            sec.origin = Origin { source: 0, index: sec_i };
+        }
+
+        let mut includes = config.include.clone();
+        includes.extend(config.select_version(version)?);
+
+        let base_dir = path.parent().unwrap_or(Path::new(""));
+        for include_path in includes {
+            let include_path = base_dir.join(include_path);
+            let text = fs::read_to_string(&include_path)
+                .map_err(|err| format!("failed to open included config `{}` ({})", include_path.display(), err))?;
+            let include: Include = toml::from_str(&text)
+                .map_err(|err| format!("failed to parse included config `{}` ({})", include_path.display(), err))?;
+
+            let source = config.sources.len();
+            config.sources.push(Source {
+                path: include_path.display().to_string(),
+                text: Some(text),
+            });
+            for (sec_i, mut sec) in include.sections.into_iter().enumerate() {
+                sec.origin = Origin { source, index: sec_i };
+                config.sections.push(sec);
+            }
+            for mut symbol in include.symbols {
+                symbol.origin = source;
+                config.symbols.push(symbol);
+            }
+        }
+
+        config.resolve()?;
+
+        Ok(config)
+    }
+
+    /// names of the versions the config at `path` describes
+    pub fn version_names(path: &Path) -> Result<Vec<String>, String> {
+        #[derive(Deserialize)]
+        struct Versions {
+            #[serde(default)]
+            versions: Vec<Version>,
+        }
+
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("failed to open `{}` ({})", path.display(), err))?;
+        let versions: Versions =
+            toml::from_str(&text).map_err(|err| format!("failed to parse `{}` ({})", path.display(), err))?;
+
+        Ok(versions.versions.into_iter().map(|version| version.name).collect())
+    }
+
+    /// takes the executable from the picked version, returning the files it includes
+    fn select_version(&mut self, version: Option<&str>) -> Result<Vec<String>, String> {
+        let path = &self.sources[0].path;
+        let names = || self.versions.iter().map(|version| format!("`{}`", version.name)).collect::<Vec<_>>().join(", ");
+
+        let Some(name) = version else {
+            if !self.versions.is_empty() {
+                return Err(format!("`{}` describes the versions {}, pick one with `--version`", path, names()));
+            }
+            if let Some(key) = [("executable", &self.executable), ("hash", &self.hash)]
+                .into_iter()
+                .find_map(|(key, value)| value.is_empty().then_some(key))
+            {
+                return Err(format!("`{}` is missing `{}`", path, key));
+            }
+            return Ok(Vec::new());
+        };
+
+        if self.versions.is_empty() {
+            return Err(format!("`{}` does not describe any versions, but `--version {}` was given", path, name));
+        }
+        if let Some(key) = [("executable", !self.executable.is_empty()), ("hash", !self.hash.is_empty()), ("output", self.output.is_some())]
+            .into_iter()
+            .find_map(|(key, set)| set.then_some(key))
+        {
+            return Err(Diagnostic::new(format!("`{}` describes versions, so `{}` belongs in each of them", path, key))
+                .at(self.sources.first(), &[key.to_string()])
+                .to_string());
+        }
+
+        let version = self
+            .versions
+            .iter()
+            .find(|version| version.name == name)
+            .cloned()
+            .ok_or_else(|| format!("there is no version `{}`, expected one of {}", name, names()))?;
+
+        self.executable = version.executable;
+        self.hash = version.hash;
+        self.output = version.output;
+        self.version = Some(version.name);
+
+        Ok(version.include)
+    }
+
+    /// resolves every unit as it was written, its kind from `[units]` if it has none and its
+    /// position and size
+    fn resolve(&mut self) -> Result<(), String> {
+        let mut errors = Vec::new();
+        for (sec_i, sec) in self.sections.iter_mut().enumerate() {
+            for (unit_i, unit) in sec.units.iter_mut().enumerate() {
+                if unit.kind.is_empty() {
+                    match unit.name.as_ref().map(|name| (name, self.units.get(name))) {
+                        Some((_, Some(shared))) => {
+                            unit.kind = shared.kind.clone();
+                            unit.file = shared.file.clone();
+                            unit.member = shared.member.clone();
+                            unit.status = shared.status.clone();
+                            unit.layout.shared = true;
+                        }
+                        Some((name, None)) => errors.push((
+                            sec_i,
+                            unit_i,
+                            "name",
+                            format!("section `{}`, unit `{}` has no `kind` and there is no `[units.{}]`", sec.name, unit_i, name),
+                        )),
+                        None => errors.push((
+                            sec_i,
+                            unit_i,
+                            "",
+                            format!("section `{}`, unit `{}` is missing `kind` or a `name` from `[units]`", sec.name, unit_i),
+                        )),
+                    }
+                }
+
+                if let Err((key, err)) = unit.resolve(sec.addr_virtual) {
+                    errors.push((sec_i, unit_i, key, format!("section `{}`, unit `{}` {}", sec.name, unit_i, err)));
+                }
+            }
+        }
+
+        diagnostic::report(
+            errors
+                .into_iter()
+                .map(|(sec_i, unit_i, key, err)| {
+                    Diagnostic::new(err).at(self.section_source(sec_i), &self.unit_path(sec_i, unit_i, key))
+                })
+                .collect(),
+        )
+    }
+
+    pub fn assembler_path(&self) -> &str {
+        self.tool_path(&self.assembler_path, |workspace| &workspace.assembler_path).unwrap_or("ml")
+    }
+
+    pub fn compiler_path(&self) -> &str {
+        self.tool_path(&self.compiler_path, |workspace| &workspace.compiler_path).unwrap_or("cl")
+    }
+
+    pub fn linker_path(&self) -> &str {
+        self.tool_path(&self.linker_path, |workspace| &workspace.linker_path).unwrap_or("ld")
+    }
+
+    pub fn assembler(&self) -> Tool {
+        self.tool(self.assembler_path(), &self.assembler_wrapper, |workspace| &workspace.assembler_wrapper)
+    }
+
+    pub fn compiler(&self) -> Tool {
+        self.tool(self.compiler_path(), &self.compiler_wrapper, |workspace| &workspace.compiler_wrapper)
+    }
+
+    pub fn linker(&self) -> Tool {
+        self.tool(self.linker_path(), &self.linker_wrapper, |workspace| &workspace.linker_wrapper)
+    }
+
+    fn tool<'a>(
+        &'a self,
+        path: &str,
+        wrapper: &'a Option<String>,
+        workspace_wrapper: impl Fn(&'a Workspace) -> &'a Option<String>,
+    ) -> Tool {
+        // the project's wine settings replace the workspace's, and each is relative to its own config
+        let (wine, root) = match (&self.wine, &self.workspace) {
+            (Some(wine), _) => (Some(wine), Path::new("")),
+            (None, Some(workspace)) => (workspace.wine.as_ref(), workspace.root.as_path()),
+            (None, None) => (None, Path::new("")),
+        };
+
+        Tool {
+            path: path.to_string(),
+            wrapper: self.tool_path(wrapper, workspace_wrapper).map(str::to_string),
+            wine_prefix: wine
+                .and_then(|wine| wine.prefix.as_ref())
+                .and_then(|prefix| std::path::absolute(root.join(prefix)).ok()),
+            wine_debug: wine.and_then(|wine| wine.debug.clone()),
+        }
+    }
+
+    fn tool_path<'a>(
+        &'a self,
+        path: &'a Option<String>,
+        workspace_path: impl Fn(&'a Workspace) -> &'a Option<String>,
+    ) -> Option<&'a str> {
+        path.as_deref()
+            .or_else(|| self.workspace.as_ref().and_then(|workspace| workspace_path(workspace).as_deref()))
+    }
+
+    /// every directory searched for included headers, the workspace's last
+    pub fn include_dirs(&self) -> Vec<PathBuf> {
+        let mut include_dirs: Vec<PathBuf> = self.include_dirs.iter().map(PathBuf::from).collect();
+        if let Some(workspace) = &self.workspace {
+            include_dirs.extend(workspace.include_dirs.iter().map(|dir| workspace.root.join(dir)));
+        }
+
+        include_dirs
+    }
+
+    /// file name of the built executable in the build directory
+    pub fn output_name(&self) -> Result<&str, String> {
+        match &self.output {
+            Some(output) => Ok(output),
+            None => Path::new(&self.executable)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or_else(|| format!("executable path `{}` is missing a file name", self.executable)),
+        }
+    }
+
+    /// forgets the text of every config file, for once the config has been edited and no longer
+    /// matches it
+    pub fn forget_sources(&mut self) {
+        for source in self.sources.iter_mut() {
+            source.text = None;
+        }
+    }
+
+    /// the config file `sec_i` was read from
+    fn section_source(&self, sec_i: usize) -> Option<&Source> {
+        self.sources.get(self.sections[sec_i].origin.source)
+    }
+
+    /// path to `key` of a section in the file it was read from, for pointing diagnostics at it
+    fn section_path(&self, sec_i: usize, key: &str) -> Vec<String> {
+        vec![
+            "sections".to_string(),
+            self.sections[sec_i].origin.index.to_string(),
+            key.to_string(),
+        ]
+    }
+
+    /// path to `key` of a unit in the file it was read from, an empty key being the unit itself
+    fn unit_path(&self, sec_i: usize, unit_i: usize, key: &str) -> Vec<String> {
+        vec![
+            "sections".to_string(),
+            self.sections[sec_i].origin.index.to_string(),
+            "units".to_string(),
+            unit_i.to_string(),
+            key.to_string(),
+        ]
+    }
+
+    /// checks the whole config against the executable, reporting every problem at once
+    pub fn check(&self, pe: &PE) -> Result<(), String> {
+        let mut diagnostics = Vec::new();
+
+        for sec in pe.sections.iter() {
+            let sec_name = sec
+                .name()
+                .map_err(|err| format!("failed to get section name ({})", err))?;
+
+            if !self.sections.iter().any(|cfg_sec| cfg_sec.name == sec_name) {
+                diagnostics.push(
+                    Diagnostic::new(format!("section `{}` is missing unit configuration", sec_name))
+                        .at(self.sources.first(), &["sections".to_string()]),
+                );
+            }
+        }
+
+        for (sec_i, cfg_sec) in self.sections.iter().enumerate() {
+            if self.sections[..sec_i].iter().any(|prev_sec| prev_sec.name == cfg_sec.name) {
+                diagnostics.push(
+                    Diagnostic::new(format!("section `{}` is configured more than once", cfg_sec.name))
+                        .at(self.section_source(sec_i), &self.section_path(sec_i, "name")),
+                );
+                continue;
+            }
+
+            let mut pe_sec = None;
+            for sec in pe.sections.iter() {
+                if sec.name().is_ok_and(|sec_name| sec_name == cfg_sec.name) {
+                    pe_sec = Some(sec);
+                }
+            }
+
+            match pe_sec {
+                Some(sec) => diagnostics.extend(self.check_section(
+                    sec_i,
+                    pe.image_base + sec.virtual_address as usize,
+                    sec.size_of_raw_data as usize,
+                )),
+                None => diagnostics.push(
+                    Diagnostic::new(format!("section `{}` is not in the executable", cfg_sec.name))
+                        .at(self.section_source(sec_i), &self.section_path(sec_i, "name")),
+                ),
+            }
+
+            for (unit_i, unit) in cfg_sec.units.iter().enumerate() {
+                let unit_diagnostic = |message: String, key: &str| {
+                    Diagnostic::new(format!("section `{}`, unit `{}` {}", cfg_sec.name, unit_i, message))
+                        .at(self.section_source(sec_i), &self.unit_path(sec_i, unit_i, key))
+                };
+
+                if let Err(err) = unit.status() {
+                    diagnostics.push(
+                        unit_diagnostic(format!("has {}", err), "status")
+                            .note("expected one of `matching`, `nonmatching` or `wip`".to_string()),
+                    );
+                }
+
+                if !UNIT_KINDS.contains(&unit.kind.as_str()) {
+                    diagnostics.push(
+                        unit_diagnostic(format!("has invalid kind `{}`", unit.kind), "kind")
+                            .note(format!("expected one of {}", UNIT_KINDS.map(|kind| format!("`{}`", kind)).join(", "))),
+                    );
+                    continue;
+                }
+
+                match &unit.file {
+                    None if unit.kind != "copy" => {
+                        diagnostics.push(unit_diagnostic(format!("is a {} unit, but has no `file`", unit.kind), ""))
+                    }
+                    // split extracts resources into their directory when it doesn't exist yet
+                    Some(file) if unit.kind != "rsrc" && !Path::new(file).exists() => {
+                        diagnostics.push(unit_diagnostic(format!("has a `file` `{}` which does not exist", file), "file"))
+                    }
+                    _ => (),
+                }
+
+                match &unit.member {
+                    None if unit.kind == "lib-member" => {
+                        diagnostics.push(unit_diagnostic("is a lib-member unit, but has no `member`".to_string(), ""))
+                    }
+                    Some(_) if unit.kind != "lib-member" => diagnostics.push(unit_diagnostic(
+                        format!("is a {} unit, but has a `member`, which only lib-member units take", unit.kind),
+                        "member",
+                    )),
+                    _ => (),
+                }
+            }
+        }
+
+        diagnostic::report(diagnostics)
+    }
+
+    /// checks that the units of a section are contiguous and exactly cover its raw data, which
+    /// starts at `addr_virtual` and is `raw_size` bytes long
+    pub fn check_section(&self, sec_i: usize, addr_virtual: usize, raw_size: usize) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+
+        let cfg_sec = &self.sections[sec_i];
+
+        if let Some(cfg_addr_virtual) = cfg_sec.addr_virtual.filter(|&cfg_addr_virtual| cfg_addr_virtual != addr_virtual) {
+            diagnostics.push(
+                Diagnostic::new(format!(
+                    "section `{}` is configured at `0x{:X}`, but starts at `0x{:X}` in the executable",
+                    cfg_sec.name, cfg_addr_virtual, addr_virtual
+                ))
+                .at(self.section_source(sec_i), &self.section_path(sec_i, "addr_virtual")),
+            );
+        }
+
+        let mut last_unit_end = addr_virtual;
+        for (unit_i, unit) in cfg_sec.units.iter().enumerate() {
+            let start_key = if unit.layout.relative { "offset" } else { "addr_virtual" };
+            let previous = if unit_i == 0 {
+                "the start of the section".to_string()
+            } else {
+                format!("the end of unit `{}`", unit_i - 1)
+            };
+
+            if unit.addr_virtual > last_unit_end {
+                diagnostics.push(
+                    Diagnostic::new(format!(
+                        "section `{}`, unit `{}` leaves a gap of `0x{:X}` bytes after {}",
+                        cfg_sec.name,
+                        unit_i,
+                        unit.addr_virtual - last_unit_end,
+                        previous
+                    ))
+                    .at(self.section_source(sec_i), &self.unit_path(sec_i, unit_i, start_key))
+                    .note(format!("expected it to begin at `0x{:X}`, but it begins at `0x{:X}`", last_unit_end, unit.addr_virtual)),
+                );
+            } else if unit.addr_virtual < last_unit_end {
+                diagnostics.push(
+                    Diagnostic::new(format!(
+                        "section `{}`, unit `{}` overlaps {} by `0x{:X}` bytes",
+                        cfg_sec.name,
+                        unit_i,
+                        if unit_i == 0 { "the previous section".to_string() } else { format!("unit `{}`", unit_i - 1) },
+                        last_unit_end - unit.addr_virtual
+                    ))
+                    .at(self.section_source(sec_i), &self.unit_path(sec_i, unit_i, start_key))
+                    .note(format!("expected it to begin at `0x{:X}`, but it begins at `0x{:X}`", last_unit_end, unit.addr_virtual)),
+                );
+            }
+
+            if unit.raw_size == 0 {
+                diagnostics.push(
+                    Diagnostic::new(format!("section `{}`, unit `{}` is empty", cfg_sec.name, unit_i))
+                        .at(self.section_source(sec_i), &self.unit_path(sec_i, unit_i, "")),
+                );
+            }
+
+            last_unit_end = unit.addr_virtual + unit.raw_size;
+        }
+
+        let sec_end = addr_virtual + raw_size;
+        if last_unit_end != sec_end {
+            let location = match cfg_sec.units.last() {
+                Some(unit) => self.unit_path(
+                    sec_i,
+                    cfg_sec.units.len() - 1,
+                    if unit.layout.uses_end { "end" } else { "raw_size" },
+                ),
+                None => self.section_path(sec_i, "units"),
+            };
+
+            let message = if last_unit_end < sec_end {
+                format!("units of section `{}` stop `0x{:X}` bytes short of its end", cfg_sec.name, sec_end - last_unit_end)
+            } else {
+                format!("units of section `{}` run `0x{:X}` bytes past its end", cfg_sec.name, last_unit_end - sec_end)
+            };
+            diagnostics.push(
+                Diagnostic::new(message)
+                    .at(self.section_source(sec_i), &location)
+                    .note(format!("expected the last unit to end at `0x{:X}`, but it ends at `0x{:X}`", sec_end, last_unit_end)),
+            );
+        }
+
+        diagnostics
+    }
+
+    /// finds the unit containing `addr_virtual`, along with its section and index
+    pub fn find_unit(&self, addr_virtual: usize) -> Option<(&Section, usize, &Unit)> {
+        self.sections.iter().find_map(|sec| {
+            sec.units
+                .iter()
+                .enumerate()
+                .find(|(_, unit)| addr_virtual >= unit.addr_virtual && addr_virtual < unit.addr_virtual + unit.raw_size)
+                .map(|(unit_i, unit)| (sec, unit_i, unit))
+        })
+    }
+
+    /// the timestamp to force in the final image, or `None` to preserve the original's
+    pub fn timestamp(&self) -> Result<Option<u32>, String> {
+        match self.timestamp.as_deref() {
+            None | Some("preserve") => Ok(None),
+            Some("zero") => Ok(Some(0)),
+            Some(value) => util::parse_int(value)
+                .and_then(|timestamp| u32::try_from(timestamp).map_err(|err| err.to_string()))
+                .map(Some)
+                .map_err(|err| format!("invalid timestamp `{}` ({})", value, err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Config, Include, Origin};
+    use crate::diagnostic::Source;
+
+    const UNITS: &str = r#"executable = "w.exe"
+hash = "0"
+
+[[sections]]
+name = ".text"
+addr_virtual = "0x401000"
+
+[[sections.units]]
+kind = "copy"
+addr_virtual = "0x401000"
+raw_size = 16
+
+[[sections.units]]
+kind = "copy"
+offset = "0x18"
+end = "40"
+
+[[sections.units]]
+kind = "copy"
+addr_virtual = 4198428
+raw_size = "0X14"
+"#;
+
+    /// a config read from `text` as `Config::load` reads the file it is given, without includes
+    fn parse(text: &str) -> Result<Config, String> {
+        let mut config: Config = toml::from_str(text).map_err(|err| err.to_string())?;
+        config.sources.push(Source {
+            path: "pod.toml".to_string(),
+            text: Some(text.to_string()),
+        });
+        for (sec_i, sec) in config.sections.iter_mut().enumerate() {
+            sec.origin = Origin { source: 0, index: sec_i };
+        }
+        config.resolve()?;
+        Ok(config)
+    }
+
+    #[test]
+    fn units_take_decimal_and_hex_positions_and_sizes() {
+        let config = parse(UNITS).unwrap();
+        let units = &config.sections[0].units;
+
+        assert_eq!(
+            units.iter().map(|unit| (unit.addr_virtual, unit.raw_size)).collect::<Vec<_>>(),
+            [(0x401000, 0x10), (0x401018, 0x10), (0x40101C, 0x14)]
+        );
+        assert!(units[1].layout.relative && units[1].layout.uses_end);
+        assert!(!units[2].layout.relative && !units[2].layout.uses_end);
+    }
+
+    #[test]
+    fn negative_numbers_are_rejected() {
+        for value in ["-16", "\"-0x10\""] {
+            let err = parse(&UNITS.replace("raw_size = 16", &format!("raw_size = {}", value))).unwrap_err();
+            assert!(err.contains("is negative, expected an address, size or other unsigned number"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn gaps_overlaps_and_the_section_end_are_reported_at_the_unit() {
+        let config = parse(UNITS).unwrap();
+        let diagnostics = config.check_section(0, 0x401000, 0x40);
+
+        assert_eq!(
+            diagnostics.iter().map(|diagnostic| diagnostic.message.as_str()).collect::<Vec<_>>(),
+            [
+                "section `.text`, unit `1` leaves a gap of `0x8` bytes after the end of unit `0`",
+                "section `.text`, unit `2` overlaps unit `1` by `0xC` bytes",
+                "units of section `.text` stop `0x10` bytes short of its end",
+            ]
+        );
+        let lines = diagnostics
+            .iter()
+            .map(|diagnostic| diagnostic.location.as_ref().map(|location| location.line_text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [Some("offset = \"0x18\""), Some("addr_virtual = 4198428"), Some("raw_size = \"0X14\"")]);
+    }
+
+    #[test]
+    fn units_that_cant_be_placed_are_reported() {
+        let err = parse(&UNITS.replace("offset = \"0x18\"", "offset = \"0x18\"\naddr_virtual = \"0x401018\"")).unwrap_err();
+        assert!(err.starts_with("section `.text`, unit `1` has both `addr_virtual` and `offset`, only one is allowed"), "{}", err);
+
+        let err = parse(&UNITS.replace("end = \"40\"", "end = \"0x10\"")).unwrap_err();
+        assert!(err.starts_with("section `.text`, unit `1` `end` `0x10` is before its start `0x18`"), "{}", err);
+
+        let err = parse(&UNITS.replace("end = \"40\"", "end = \"40\"\nraw_size = 8")).unwrap_err();
+        assert!(err.contains("`raw_size` `0x8` does not match its `end` `0x28`, which gives a size of `0x10`"), "{}", err);
+    }
+
+    #[test]
+    fn includes_refuse_what_only_pod_toml_takes() {
+        let err = toml::from_str::<Include>("include = [\"more.toml\"]\n").unwrap_err().to_string();
+        assert!(err.contains("unknown field `include`, expected `sections` or `symbols`"), "{}", err);
+        assert!(toml::from_str::<Include>("executable = \"w.exe\"\n").is_err());
+    }
 }
@@ -0,0 +1,328 @@
+use toml_edit::{ArrayOfTables, DocumentMut, Item, Table, Value};
+
+use serde::Serialize;
+
+use crate::util;
+
+/// first and last lines of the comment `init` writes at the top of a config about the toolchain in
+/// the rich header
+pub const TOOLCHAIN_HEADER: &str = "# toolchain suggested by the rich header";
+pub const TOOLCHAIN_FOOTER: &str = "# end of the toolchain suggested by the rich header";
+
+/// rewrites the `existing` text of a config file so it holds `config`, keeping the comments, key
+/// order and number formatting of everything that is still there
+pub fn merge_config<T: Serialize>(existing: &str, config: &T) -> Result<String, String> {
+    let mut doc: DocumentMut = existing
+        .parse()
+        .map_err(|err| format!("failed to parse config ({})", err))?;
+
+    let new_doc: DocumentMut = toml::to_string_pretty(config)
+        .map_err(|err| format!("failed to serialize config ({})", err))?
+        .parse()
+        .map_err(|err| format!("failed to parse serialized config ({})", err))?;
+
+    merge_table(doc.as_table_mut(), new_doc.as_table());
+
+    Ok(doc.to_string())
+}
+
+/// rewrites the `existing` text of a config file to describe another build of its executable, changing
+/// only `executable` and `hash`, those of `version` if one is given, and the toolchain comment at the
+/// top, which is replaced by `header`
+pub fn set_executable(
+    existing: &str,
+    version: Option<&str>,
+    executable: &str,
+    hash: &str,
+    header: &str,
+) -> Result<String, String> {
+    let mut doc: DocumentMut = existing
+        .parse()
+        .map_err(|err| format!("failed to parse config ({})", err))?;
+
+    let table = match version {
+        Some(version) => doc
+            .get_mut("versions")
+            .and_then(Item::as_array_of_tables_mut)
+            .and_then(|versions| {
+                versions
+                    .iter_mut()
+                    .find(|table| table.get("name").and_then(Item::as_str) == Some(version))
+            })
+            .ok_or_else(|| format!("there is no version `{}`", version))?,
+        None if doc.contains_key("versions") => {
+            return Err("the config describes several versions, pick one with `--version`".to_string())
+        }
+        None => doc.as_table_mut(),
+    };
+    for (key, value) in [("executable", executable), ("hash", hash)] {
+        match table.get_mut(key).and_then(Item::as_value_mut) {
+            Some(old) => merge_value(old, &Value::from(value)),
+            None => {
+                table.insert(key, toml_edit::value(value));
+            }
+        }
+    }
+
+    let text = doc.to_string();
+    let is_line = |line: &str, marker: &str| line.trim_end() == marker;
+    let mut lines = text.split_inclusive('\n').peekable();
+    let mut body = String::new();
+    if lines.peek().is_some_and(|line| is_line(line, TOOLCHAIN_HEADER))
+        && text.lines().any(|line| is_line(line, TOOLCHAIN_FOOTER))
+    {
+        // the old comment runs up to its footer, and whatever in it was uncommented is kept
+        for line in lines.by_ref().skip(1) {
+            if is_line(line, TOOLCHAIN_FOOTER) {
+                break;
+            }
+            if !line.trim().is_empty() && !line.starts_with('#') {
+                body.push_str(line);
+            }
+        }
+        while lines.peek().is_some_and(|line| line.trim().is_empty()) {
+            lines.next();
+        }
+    }
+    body.extend(lines);
+
+    Ok(format!("{}{}", header, body))
+}
+
+fn merge_table(old: &mut Table, new: &Table) {
+    let removed: Vec<String> = old
+        .iter()
+        .filter(|(key, _)| !new.contains_key(key))
+        .map(|(key, _)| key.to_string())
+        .collect();
+    for key in removed {
+        old.remove(&key);
+    }
+
+    for (key, new_item) in new.iter() {
+        match old.get_mut(key) {
+            Some(old_item) => merge_item(key, old_item, new_item),
+            None => {
+                old.insert(key, new_item.clone());
+            }
+        }
+    }
+}
+
+fn merge_item(key: &str, old: &mut Item, new: &Item) {
+    match (old, new) {
+        (Item::Value(old_value), Item::Value(new_value)) => merge_value(old_value, new_value),
+        (Item::Table(old_table), Item::Table(new_table)) => merge_table(old_table, new_table),
+        (Item::ArrayOfTables(old_array), Item::ArrayOfTables(new_array)) => {
+            merge_array_of_tables(key, old_array, new_array)
+        }
+        (old, new) => *old = new.clone(),
+    }
+}
+
+fn merge_value(old: &mut Value, new: &Value) {
+    let new_value = match (number(old), number(new)) {
+        // addresses may be written as integers or strings, in hex or decimal, keep whichever was used
+        (Some(old_number), Some(new_number)) => {
+            if old_number == new_number {
+                return;
+            }
+
+            let hex = match &*old {
+                Value::Integer(int) => int.display_repr().starts_with("0x"),
+                Value::String(string) => string.value().starts_with("0x"),
+                _ => false,
+            };
+            let repr = if hex { format!("0x{:X}", new_number) } else { new_number.to_string() };
+            if old.is_str() {
+                Value::from(repr)
+            } else {
+                repr.parse::<Value>().unwrap()
+            }
+        }
+        _ => match (&*old, new) {
+            (Value::String(old_str), Value::String(new_str)) if old_str.value() == new_str.value() => return,
+            (Value::Boolean(old_bool), Value::Boolean(new_bool)) if old_bool.value() == new_bool.value() => return,
+            _ => new.clone(),
+        },
+    };
+
+    let decor = old.decor().clone();
+    *old = new_value;
+    *old.decor_mut() = decor;
+}
+
+/// the value of an integer, or of a string holding one
+fn number(value: &Value) -> Option<u64> {
+    match value {
+        Value::Integer(int) => u64::try_from(*int.value()).ok(),
+        Value::String(string) => util::parse_int(string.value()).ok(),
+        _ => None,
+    }
+}
+
+/// matches each new table to an old one so the old one's comments survive the edit, sections,
+/// symbols and versions are matched by name, units by name or else where they start or end, so a
+/// unit keeps its comments when it is resized from either side
+fn merge_array_of_tables(key: &str, old: &mut ArrayOfTables, new: &ArrayOfTables) {
+    let identities = |table: &Table| -> Vec<String> {
+        let number = |key| table.get(key).and_then(Item::as_value).and_then(number);
+        match key {
+            "sections" | "symbols" | "versions" => table
+                .get("name")
+                .and_then(Item::as_str)
+                .map(|name| vec![format!("name {}", name)])
+                .unwrap_or_default(),
+            "units" => {
+                let start = number("addr_virtual").or_else(|| number("offset"));
+                let end = number("end").or_else(|| Some(start? + number("raw_size")?));
+                table
+                    .get("name")
+                    .and_then(Item::as_str)
+                    .map(|name| format!("name {}", name))
+                    .into_iter()
+                    .chain(start.map(|start| format!("start {}", start)))
+                    .chain(end.map(|end| format!("end {}", end)))
+                    .collect()
+            }
+            _ => Vec::new(),
+        }
+    };
+
+    let old_identities: Vec<Vec<String>> = old.iter().map(identities).collect();
+    let mut old_tables: Vec<Option<Table>> = old.iter().cloned().map(Some).collect();
+
+    let mut merged = ArrayOfTables::new();
+    for (new_i, new_table) in new.iter().enumerate() {
+        let new_identities = identities(new_table);
+
+        let matched = if new_identities.is_empty() {
+            Some(new_i).filter(|&old_i| old_i < old_tables.len())
+        } else {
+            new_identities.iter().find_map(|identity| {
+                (0..old_tables.len())
+                    .find(|&old_i| old_tables[old_i].is_some() && old_identities[old_i].contains(identity))
+            })
+        };
+
+        let table = match matched.and_then(|old_i| old_tables[old_i].take()) {
+            Some(mut old_table) => {
+                merge_table(&mut old_table, new_table);
+                old_table
+            }
+            None => new_table.clone(),
+        };
+        merged.push(table);
+    }
+
+    *old = merged;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{merge_config, set_executable, TOOLCHAIN_FOOTER, TOOLCHAIN_HEADER};
+
+    const CONFIG: &str = r#"# the game
+executable = "game.exe"
+hash = "old"
+
+[[sections]]
+name = ".text"
+
+# the whole of .text
+[[sections.units]]
+addr_virtual = "0x401000"
+raw_size = 8192 # decimal on purpose
+kind = "copy"
+
+[[sections.units]]
+kind = "c"
+file = "src/main.c" # the entry point
+addr_virtual = 0x403000
+raw_size = 0x100
+"#;
+
+    fn config(text: &str) -> toml::Table {
+        toml::from_str(text).unwrap()
+    }
+
+    #[test]
+    fn merging_an_unchanged_config_changes_nothing() {
+        assert_eq!(merge_config(CONFIG, &config(CONFIG)).unwrap(), CONFIG);
+    }
+
+    #[test]
+    fn splitting_a_unit_keeps_comments_number_style_and_key_order() {
+        let split = config(
+            r#"
+executable = "game.exe"
+hash = "old"
+
+[[sections]]
+name = ".text"
+
+[[sections.units]]
+addr_virtual = "0x401000"
+raw_size = "0x1000"
+kind = "copy"
+
+[[sections.units]]
+addr_virtual = "0x402000"
+raw_size = "0x1000"
+kind = "asm"
+file = "src/split.s"
+
+[[sections.units]]
+kind = "c"
+file = "src/main.c"
+addr_virtual = "0x403000"
+raw_size = "0x100"
+"#,
+        );
+
+        let merged = merge_config(CONFIG, &split).unwrap();
+        assert!(merged.starts_with("# the game\nexecutable"));
+        assert!(merged.contains(
+            "# the whole of .text\n[[sections.units]]\naddr_virtual = \"0x401000\"\nraw_size = 4096 # decimal on purpose\nkind = \"copy\"\n"
+        ));
+        assert!(merged.contains("addr_virtual = \"0x402000\"\nfile = \"src/split.s\"\nkind = \"asm\"\nraw_size = \"0x1000\"\n"));
+        // the unit after the split one is still matched by where it starts
+        assert!(merged.contains("file = \"src/main.c\" # the entry point\naddr_virtual = 0x403000\nraw_size = 0x100\n"));
+    }
+
+    #[test]
+    fn resizing_a_unit_from_its_start_keeps_its_comments() {
+        let resized = CONFIG
+            .replace("addr_virtual = \"0x401000\"\nraw_size = 8192", "addr_virtual = \"0x401000\"\nraw_size = 4096")
+            .replace("addr_virtual = 0x403000\nraw_size = 0x100", "addr_virtual = 0x402000\nraw_size = 0x1100");
+
+        let merged = merge_config(CONFIG, &config(&resized)).unwrap();
+        assert!(merged.contains("file = \"src/main.c\" # the entry point\naddr_virtual = 0x402000\nraw_size = 0x1100\n"));
+    }
+
+    #[test]
+    fn setting_the_executable_replaces_only_the_toolchain_comment() {
+        let existing = format!(
+            "{}\r\n# compiler: old\r\ncompiler_path = \"cl\"\r\n{}\r\n\r\n# mine\r\n{}",
+            TOOLCHAIN_HEADER,
+            TOOLCHAIN_FOOTER,
+            CONFIG.replace('\n', "\r\n")
+        );
+        let header = format!("{}\n# compiler: new\n{}\n\n", TOOLCHAIN_HEADER, TOOLCHAIN_FOOTER);
+
+        let text = set_executable(&existing, None, "new.exe", "new", &header).unwrap();
+        // toml_edit writes line endings back as `\n`
+        assert!(text.starts_with(&format!("{}compiler_path = \"cl\"\n# mine\n# the game\n", header)));
+        assert!(text.contains("executable = \"new.exe\"\nhash = \"new\"\n"));
+        assert!(!text.contains("# compiler: old"));
+    }
+
+    #[test]
+    fn setting_the_executable_of_a_version() {
+        let existing = "[[versions]]\nname = \"1.0\"\nexecutable = \"a.exe\" # first\nhash = \"a\"\n";
+        assert!(set_executable(existing, None, "b.exe", "b", "").is_err());
+
+        let text = set_executable(existing, Some("1.0"), "b.exe", "b", "").unwrap();
+        assert_eq!(text, "[[versions]]\nname = \"1.0\"\nexecutable = \"b.exe\" # first\nhash = \"b\"\n");
+    }
+}
@@ -0,0 +1,172 @@
+use std::fmt;
+
+/// a problem with the config, pointed at where it is in pod.toml when that is known
+#[derive(Debug)]
+pub struct Diagnostic {
+    pub message: String,
+    pub location: Option<Location>,
+    pub notes: Vec<String>,
+}
+
+/// a config file that was read
+#[derive(Debug, Clone)]
+pub struct Source {
+    pub path: String,
+    /// contents as read, or `None` once the config no longer matches them
+    pub text: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Location {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub line_text: String,
+    /// length of the item on its first line, in characters
+    pub length: usize,
+}
+
+impl Diagnostic {
+    pub fn new(message: String) -> Diagnostic {
+        Diagnostic {
+            message,
+            location: None,
+            notes: Vec::new(),
+        }
+    }
+
+    /// points the diagnostic at the TOML item in `source` found by following `path`, see `locate`
+    pub fn at(mut self, source: Option<&Source>, path: &[String]) -> Diagnostic {
+        self.location = source.and_then(|source| locate(&source.path, source.text.as_deref()?, path));
+        self
+    }
+
+    pub fn note(mut self, note: String) -> Diagnostic {
+        self.notes.push(note);
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+
+        if let Some(location) = &self.location {
+            let gutter = " ".repeat(location.line.to_string().len());
+            write!(f, "\n{}--> {}:{}:{}", gutter, location.file, location.line, location.column)?;
+            write!(f, "\n{} |", gutter)?;
+            write!(f, "\n{} | {}", location.line, location.line_text)?;
+            write!(
+                f,
+                "\n{} | {}{}",
+                gutter,
+                " ".repeat(location.column - 1),
+                "^".repeat(location.length.max(1))
+            )?;
+        }
+
+        for note in self.notes.iter() {
+            write!(f, "\n  = note: {}", note)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// turns every diagnostic into one error, to be printed after `error: `
+pub fn report(diagnostics: Vec<Diagnostic>) -> Result<(), String> {
+    match diagnostics.len() {
+        0 => Ok(()),
+        1 => Err(diagnostics[0].to_string()),
+        count => Err(format!(
+            "{}\n\nerror: found {} problems in the config",
+            diagnostics.iter().map(|diagnostic| diagnostic.to_string()).collect::<Vec<_>>().join("\n\nerror: "),
+            count
+        )),
+    }
+}
+
+/// finds the TOML item in `text` found by following `path`, where numbers index into arrays
+pub fn locate(file: &str, text: &str, path: &[String]) -> Option<Location> {
+    let doc = toml_edit::ImDocument::parse(text).ok()?;
+
+    let mut item = doc.as_item();
+    let mut span = item.span();
+    for key in path.iter().filter(|key| !key.is_empty()) {
+        // a missing key points at the item it would be in
+        let next = match key.parse::<usize>() {
+            Ok(index) => item.get(index),
+            Err(_) => item.get(key.as_str()),
+        };
+        match next {
+            Some(next) => item = next,
+            None => break,
+        }
+        span = item.span().or(span);
+    }
+
+    let span = span?;
+    let offset = span.start;
+    let line_start = text[..offset].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_end = text[offset..].find('\n').map_or(text.len(), |newline| offset + newline);
+
+    Some(Location {
+        file: file.to_string(),
+        line: text[..offset].matches('\n').count() + 1,
+        column: text[line_start..offset].chars().count() + 1,
+        line_text: text[line_start..line_end].trim_end().to_string(),
+        length: text[offset..span.end.min(line_end)].trim_end().chars().count(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::locate;
+
+    /// written with Windows line endings, which are left out of the located line
+    const CONFIG: &str = "executable = \"w.exe\"\r
+\r
+[[sections]]\r
+name = \".text\"\r
+units = [\r
+    { kind = \"copy\", addr_virtual = \"0x401000\", raw_size = \"0x10\" },\r
+    { kind = \"c\", addr_virtual = \"0x401010\", raw_size = \"0x8\" },\r
+]\r
+";
+
+    fn path(keys: &[&str]) -> Vec<String> {
+        keys.iter().map(|key| key.to_string()).collect()
+    }
+
+    /// the line, column and length of what `path` leads to, along with the line's text
+    fn span(path: &[String]) -> (usize, usize, usize, String) {
+        let location = locate("pod.toml", CONFIG, path).unwrap();
+        assert_eq!(location.file, "pod.toml");
+        (location.line, location.column, location.length, location.line_text)
+    }
+
+    #[test]
+    fn locates_keys_through_tables_and_arrays() {
+        assert_eq!(span(&path(&["executable"])), (1, 14, 7, "executable = \"w.exe\"".to_string()));
+        assert_eq!(
+            span(&path(&["sections", "0", "units", "1", "raw_size"])),
+            (7, 57, 5, "    { kind = \"c\", addr_virtual = \"0x401010\", raw_size = \"0x8\" },".to_string())
+        );
+    }
+
+    #[test]
+    fn missing_keys_point_at_where_they_would_be() {
+        // the unit itself, as it has no `end`
+        assert_eq!(
+            span(&path(&["sections", "0", "units", "0", "end"])),
+            (6, 5, 63, "    { kind = \"copy\", addr_virtual = \"0x401000\", raw_size = \"0x10\" },".to_string())
+        );
+        // an empty key is the item itself
+        assert_eq!(span(&path(&["sections", "0", "name", ""])), (4, 8, 7, "name = \".text\"".to_string()));
+    }
+
+    #[test]
+    fn unparseable_text_has_no_location() {
+        assert!(locate("pod.toml", "units = [", &path(&["units"])).is_none());
+    }
+}
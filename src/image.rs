@@ -0,0 +1,168 @@
+use goblin::pe::PE;
+
+use crate::util;
+
+const RESOURCE_DIRECTORY_SIZE: usize = 16;
+const DEBUG_DIRECTORY_SIZE: usize = 28;
+const DATA_DIRECTORY_SIZE: usize = 8;
+
+/// size of the optional header's standard and Windows fields, which the data directories follow,
+/// for PE32 and PE32+ images
+const PE32_OPTIONAL_FIELDS_SIZE: usize = 28 + 68;
+const PE32_PLUS_OPTIONAL_FIELDS_SIZE: usize = 24 + 88;
+
+/// offset of the PE signature, as stored at `0x3C` in the DOS header
+pub fn pe_signature_offset(file: &[u8]) -> Result<usize, String> {
+    file.get(0x3C..0x40)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+        .ok_or_else(|| "executable is too small to hold a DOS header".to_string())
+}
+
+/// offset of `OptionalHeader.CheckSum`, which is at the same place for PE32 and PE32+
+pub fn checksum_offset(file: &[u8]) -> Result<usize, String> {
+    Ok(pe_signature_offset(file)? + 0x18 + 0x40)
+}
+
+/// offset of the data directory at `index`, checked against `NumberOfRvaAndSizes` as the optional
+/// header can hold fewer than the usual 16
+pub fn data_directory_offset(file: &[u8], index: usize) -> Result<usize, String> {
+    let optional_header_off = pe_signature_offset(file)? + 0x18;
+    let read = |off: usize, len: usize| {
+        file.get(off..off + len)
+            .ok_or_else(|| "executable is too small to hold its optional header".to_string())
+    };
+
+    let magic = u16::from_le_bytes(read(optional_header_off, 2)?.try_into().unwrap());
+    let directories_off = optional_header_off
+        + match magic {
+            0x10B => PE32_OPTIONAL_FIELDS_SIZE,
+            0x20B => PE32_PLUS_OPTIONAL_FIELDS_SIZE,
+            magic => return Err(format!("optional header has unknown magic `0x{:X}`", magic)),
+        };
+
+    // `NumberOfRvaAndSizes` is the last of the Windows fields
+    let count = u32::from_le_bytes(read(directories_off - 4, 4)?.try_into().unwrap()) as usize;
+    if index >= count {
+        return Err(format!("optional header has {} data directories, not one at index {}", count, index));
+    }
+
+    Ok(directories_off + index * DATA_DIRECTORY_SIZE)
+}
+
+/// computes the standard PE image checksum, as done by `CheckSumMappedFile`
+pub fn compute_checksum(file: &[u8]) -> Result<u32, String> {
+    let checksum_off = checksum_offset(file)?;
+
+    let mut sum: u64 = 0;
+    for (i, chunk) in file.chunks(2).enumerate() {
+        // the checksum field itself is treated as zero
+        if i * 2 == checksum_off || i * 2 == checksum_off + 2 {
+            continue;
+        }
+
+        sum += u16::from_le_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)]) as u64;
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+
+    sum = (sum & 0xFFFF) + (sum >> 16);
+
+    Ok(sum as u32 + file.len() as u32)
+}
+
+/// finds the file offsets of every timestamp in the image: the COFF header, the export directory,
+/// each debug directory entry and each resource directory table
+///
+/// `pe` describes the layout of `file`, data directories are taken from `directories_pe` since
+/// they may not have been patched into `file` yet
+pub fn timestamp_offsets(file: &[u8], pe: &PE, directories_pe: &PE) -> Result<Vec<usize>, String> {
+    let mut offsets = vec![pe_signature_offset(file)? + 8];
+
+    let data_directories = match directories_pe.header.optional_header {
+        Some(optional_header) => optional_header.data_directories,
+        None => return Ok(offsets),
+    };
+
+    if let Some(export_table) = data_directories.get_export_table() {
+        if let Some(off) = util::rva_to_file_offset(pe, export_table.virtual_address as usize) {
+            offsets.push(off + 4);
+        }
+    }
+
+    if let Some(debug_table) = data_directories.get_debug_table() {
+        if let Some(off) = util::rva_to_file_offset(pe, debug_table.virtual_address as usize) {
+            for entry_i in 0..debug_table.size as usize / DEBUG_DIRECTORY_SIZE {
+                offsets.push(off + entry_i * DEBUG_DIRECTORY_SIZE + 4);
+            }
+        }
+    }
+
+    if let Some(resource_table) = data_directories.get_resource_table() {
+        if let Some(off) = util::rva_to_file_offset(pe, resource_table.virtual_address as usize) {
+            let rsrc_end = off + resource_table.size as usize;
+            let rsrc = file
+                .get(off..rsrc_end.min(file.len()))
+                .ok_or("resource table is outside of the executable")?;
+            collect_resource_timestamp_offsets(rsrc, 0, off, &mut offsets)?;
+        }
+    }
+
+    Ok(offsets)
+}
+
+fn collect_resource_timestamp_offsets(
+    rsrc: &[u8],
+    dir_off: usize,
+    rsrc_off: usize,
+    offsets: &mut Vec<usize>,
+) -> Result<(), String> {
+    let dir = rsrc
+        .get(dir_off..dir_off + RESOURCE_DIRECTORY_SIZE)
+        .ok_or_else(|| format!("resource directory at `0x{:X}` is out of bounds", dir_off))?;
+    offsets.push(rsrc_off + dir_off + 4);
+
+    let entry_count = u16::from_le_bytes([dir[12], dir[13]]) as usize + u16::from_le_bytes([dir[14], dir[15]]) as usize;
+    for entry_i in 0..entry_count {
+        let entry_off = dir_off + RESOURCE_DIRECTORY_SIZE + entry_i * 8;
+        let entry = rsrc
+            .get(entry_off..entry_off + 8)
+            .ok_or_else(|| format!("resource directory entry at `0x{:X}` is out of bounds", entry_off))?;
+
+        let data_off = u32::from_le_bytes(entry[4..8].try_into().unwrap());
+        if data_off & 0x8000_0000 != 0 {
+            let sub_dir_off = (data_off & 0x7FFF_FFFF) as usize;
+            if sub_dir_off <= dir_off {
+                return Err(format!("resource directory at `0x{:X}` is recursive", dir_off));
+            }
+            collect_resource_timestamp_offsets(rsrc, sub_dir_off, rsrc_off, offsets)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{checksum_offset, compute_checksum};
+
+    /// an image of odd length with just the DOS header's PE signature offset and the signature
+    /// itself filled in, the rest arbitrary bytes
+    fn image() -> Vec<u8> {
+        let mut file = (0..0x1FF).map(|i| ((i * 7 + 3) % 251) as u8).collect::<Vec<_>>();
+        file[0x3C..0x40].copy_from_slice(&0x80u32.to_le_bytes());
+        file[0x80..0x84].copy_from_slice(b"PE\0\0");
+        file
+    }
+
+    #[test]
+    fn checksum_matches_checksum_mapped_file() {
+        // worked out by summing 32-bit words and folding the carries back in, as pefile checks it,
+        // rather than the 16-bit words summed here
+        let mut file = image();
+        let checksum_off = checksum_offset(&file).unwrap();
+        assert_eq!(compute_checksum(&file).unwrap(), 0xE9B9);
+
+        // the stored checksum doesn't count towards the new one
+        file[checksum_off..checksum_off + 4].copy_from_slice(&0xDEADBEEFu32.to_le_bytes());
+        assert_eq!(compute_checksum(&file).unwrap(), 0xE9B9);
+    }
+}
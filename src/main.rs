@@ -1,12 +1,57 @@
+mod analysis;
+mod archive;
+mod build_state;
+mod coff;
 mod commands;
 mod config;
+mod config_edit;
+mod diagnostic;
+mod image;
+mod reloc;
+mod rich;
+mod rsrc;
+mod rsrc_text;
+mod tool;
+mod tool_diagnostic;
 mod util;
 
 use clap::Parser;
 use commands::{Cli, CommandExecute, Commands};
 
 fn main() {
-    let args = Cli::parse();
+    let mut args = Cli::parse();
+
+    // executables and libraries are given relative to where pod was run, or the `-C` directory, which
+    // is no longer the current directory once in the project
+    if let Ok(cwd) = std::env::current_dir() {
+        let cwd = match &args.directory {
+            Some(directory) => cwd.join(directory),
+            None => cwd,
+        };
+        let absolute = |path: &mut String| *path = cwd.join(&*path).display().to_string();
+        match &mut args.command {
+            Commands::Init(init_args) => absolute(&mut init_args.executable),
+            Commands::Port(port_args) => {
+                absolute(&mut port_args.from);
+                absolute(&mut port_args.to);
+            }
+            Commands::Identify(identify_args) => identify_args.libs.iter_mut().for_each(absolute),
+            _ => (),
+        }
+    }
+
+    tool_diagnostic::set_message_format(args.message_format);
+
+    if let Err(err) = util::enter_project(
+        args.directory.as_deref(),
+        args.config.as_deref(),
+        args.build_dir.as_deref(),
+        args.version.as_deref(),
+        !matches!(args.command, Commands::Init(_)),
+    ) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
 
     let result = match args.command {
         Commands::Init(args) => args.execute(),
@@ -14,6 +59,15 @@ fn main() {
         Commands::Gen(args) => args.execute(),
         Commands::Link(args) => args.execute(),
         Commands::PatchExe(args) => args.execute(),
+        Commands::Info(args) => args.execute(),
+        Commands::Addr(args) => args.execute(),
+        Commands::Unit(args) => args.execute(),
+        Commands::Build(args) => args.execute(),
+        Commands::Port(args) => args.execute(),
+        Commands::Identify(args) => args.execute(),
+        Commands::Objdiff(args) => args.execute(),
+        Commands::Ninja(args) => args.execute(),
+        Commands::Doctor(args) => args.execute(),
     };
 
     if let Err(err) = result {
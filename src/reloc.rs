@@ -0,0 +1,254 @@
+use std::collections::BTreeMap;
+
+use goblin::pe::{
+    header::{COFF_MACHINE_X86, COFF_MACHINE_X86_64},
+    relocation::{IMAGE_REL_AMD64_ADDR64, IMAGE_REL_I386_DIR32},
+    Coff, PE,
+};
+
+use crate::util;
+
+pub const IMAGE_REL_BASED_ABSOLUTE: u16 = 0;
+pub const IMAGE_REL_BASED_HIGHLOW: u16 = 3;
+pub const IMAGE_REL_BASED_DIR64: u16 = 10;
+
+const PAGE_SIZE: u32 = 0x1000;
+
+/// base relocation types, keyed by the RVA they apply to
+pub type BaseRelocations = BTreeMap<u32, u16>;
+
+/// reads the base relocation table of an executable
+pub fn parse_base_relocations(file: &[u8], pe: &PE) -> Result<BaseRelocations, String> {
+    let table = match pe
+        .header
+        .optional_header
+        .and_then(|optional_header| optional_header.data_directories.get_base_relocation_table().copied())
+    {
+        Some(table) if table.size > 0 => table,
+        _ => return Ok(BaseRelocations::new()),
+    };
+
+    let table_start = util::rva_to_file_offset(pe, table.virtual_address as usize)
+        .ok_or("base relocation table is not backed by section data")?;
+    let table_data = file
+        .get(table_start..table_start + table.size as usize)
+        .ok_or("base relocation table extends past the end of the executable")?;
+
+    parse_base_relocation_table(table_data)
+}
+
+/// reads the blocks of a base relocation table, leaving out the padding entries
+fn parse_base_relocation_table(table_data: &[u8]) -> Result<BaseRelocations, String> {
+    let mut relocs = BaseRelocations::new();
+
+    let mut off = 0;
+    while off + 8 <= table_data.len() {
+        let page_rva = u32::from_le_bytes(table_data[off..off + 4].try_into().unwrap());
+        let block_size = u32::from_le_bytes(table_data[off + 4..off + 8].try_into().unwrap()) as usize;
+        if block_size < 8 || off + block_size > table_data.len() {
+            return Err(format!(
+                "malformed base relocation block for page `0x{:X}`",
+                page_rva
+            ));
+        }
+
+        for entry in table_data[off + 8..off + block_size].chunks_exact(2) {
+            let entry = u16::from_le_bytes([entry[0], entry[1]]);
+            let typ = entry >> 12;
+            let rva = page_rva + (entry & 0xFFF) as u32;
+            if typ != IMAGE_REL_BASED_ABSOLUTE {
+                relocs.insert(rva, typ);
+            }
+        }
+
+        off += block_size;
+    }
+
+    Ok(relocs)
+}
+
+/// collects the absolute relocations of the `section_name` sections of a COFF object which is linked at `rva`,
+/// along with its `section_name$` grouped sections if `grouped` is set, as they are then linked with it
+pub fn collect_object_relocations(
+    obj_file: &[u8],
+    section_name: &str,
+    grouped: bool,
+    rva: u32,
+) -> Result<BaseRelocations, String> {
+    let coff = Coff::parse(obj_file).map_err(|err| format!("failed to parse object file ({})", err))?;
+
+    let (absolute_typ, base_typ) = match coff.header.machine {
+        COFF_MACHINE_X86 => (IMAGE_REL_I386_DIR32, IMAGE_REL_BASED_HIGHLOW),
+        COFF_MACHINE_X86_64 => (IMAGE_REL_AMD64_ADDR64, IMAGE_REL_BASED_DIR64),
+        machine => return Err(format!("unsupported object machine type `0x{:X}`", machine)),
+    };
+
+    let mut relocs = BaseRelocations::new();
+
+    // the linker places the selected sections one after another, each at its own alignment
+    let mut sec_rva = rva;
+    for sec in coff.sections.iter() {
+        let name = sec.name().map_err(|err| format!("failed to get object section name ({})", err))?;
+        let in_group = grouped && name.strip_prefix(section_name).is_some_and(|group| group.starts_with('$'));
+        if name != section_name && !in_group {
+            continue;
+        }
+
+        let align_bits = (sec.characteristics >> 20) & 0xF;
+        if align_bits > 0 {
+            let align = 1u32 << (align_bits - 1);
+            sec_rva = (sec_rva + align - 1) & !(align - 1);
+        }
+
+        for reloc in sec
+            .relocations(obj_file)
+            .map_err(|err| format!("failed to parse object relocations ({})", err))?
+        {
+            if reloc.typ == absolute_typ {
+                relocs.insert(sec_rva + reloc.virtual_address - sec.virtual_address, base_typ);
+            }
+        }
+
+        sec_rva += sec.size_of_raw_data;
+    }
+
+    Ok(relocs)
+}
+
+/// builds a base relocation table the way MSVC's linker lays it out, one block per page with
+/// each block padded to a multiple of four bytes
+pub fn build_base_relocation_table(relocs: &BaseRelocations) -> Vec<u8> {
+    let mut table = Vec::new();
+
+    let mut relocs = relocs.iter().peekable();
+    while let Some((&first_rva, _)) = relocs.peek() {
+        let page_rva = first_rva & !(PAGE_SIZE - 1);
+
+        let mut entries = Vec::new();
+        while let Some((&rva, &typ)) = relocs.peek() {
+            if rva >= page_rva + PAGE_SIZE {
+                break;
+            }
+
+            entries.push((typ << 12) | (rva - page_rva) as u16);
+            relocs.next();
+        }
+
+        if entries.len() % 2 != 0 {
+            entries.push(IMAGE_REL_BASED_ABSOLUTE << 12);
+        }
+
+        table.extend_from_slice(&page_rva.to_le_bytes());
+        table.extend_from_slice(&(8 + entries.len() as u32 * 2).to_le_bytes());
+        for entry in entries {
+            table.extend_from_slice(&entry.to_le_bytes());
+        }
+    }
+
+    table
+}
+
+#[cfg(test)]
+mod tests {
+    use goblin::pe::{
+        header::COFF_MACHINE_X86,
+        relocation::{IMAGE_REL_I386_DIR32, IMAGE_REL_I386_REL32},
+    };
+
+    use super::{
+        build_base_relocation_table, collect_object_relocations, parse_base_relocation_table, BaseRelocations,
+        IMAGE_REL_BASED_HIGHLOW,
+    };
+
+    /// two pages of relocations as MSVC's linker writes them, the first with an odd number of
+    /// entries and so padded with an absolute one
+    const TABLE: [u8; 28] = [
+        0x00, 0x10, 0x00, 0x00, 0x10, 0x00, 0x00, 0x00, 0x04, 0x30, 0x10, 0x30, 0xFC, 0x3F, 0x00, 0x00, // page 0x1000
+        0x00, 0x30, 0x00, 0x00, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x30, 0x08, 0x30, // page 0x3000
+    ];
+
+    fn highlow(rvas: &[u32]) -> BaseRelocations {
+        rvas.iter().map(|&rva| (rva, IMAGE_REL_BASED_HIGHLOW)).collect()
+    }
+
+    #[test]
+    fn known_table_round_trips() {
+        let relocs = parse_base_relocation_table(&TABLE).unwrap();
+        assert_eq!(relocs, highlow(&[0x1004, 0x1010, 0x1FFC, 0x3000, 0x3008]));
+        assert_eq!(build_base_relocation_table(&relocs), TABLE);
+    }
+
+    #[test]
+    fn blocks_split_at_page_boundaries() {
+        let table = build_base_relocation_table(&highlow(&[0x1FFC, 0x2000, 0x2FFF, 0x7000]));
+        // no blocks for the pages without relocations in between
+        assert_eq!(
+            table,
+            [
+                0x00, 0x10, 0x00, 0x00, 0x0C, 0x00, 0x00, 0x00, 0xFC, 0x3F, 0x00, 0x00, // page 0x1000
+                0x00, 0x20, 0x00, 0x00, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x30, 0xFF, 0x3F, // page 0x2000
+                0x00, 0x70, 0x00, 0x00, 0x0C, 0x00, 0x00, 0x00, 0x00, 0x30, 0x00, 0x00, // page 0x7000
+            ]
+        );
+        assert_eq!(parse_base_relocation_table(&table).unwrap(), highlow(&[0x1FFC, 0x2000, 0x2FFF, 0x7000]));
+    }
+
+    #[test]
+    fn malformed_blocks_are_reported() {
+        let mut table = TABLE;
+        table[4] = 0x40;
+        assert!(parse_base_relocation_table(&table).is_err());
+    }
+
+    /// a section's name, alignment in bytes, size and relocations as offset and type
+    type ObjectSection<'a> = (&'a str, u32, u32, &'a [(u32, u16)]);
+
+    /// an x86 object with the given sections
+    fn object(sections: &[ObjectSection]) -> Vec<u8> {
+        let mut file = Vec::new();
+        file.extend_from_slice(&COFF_MACHINE_X86.to_le_bytes());
+        file.extend_from_slice(&(sections.len() as u16).to_le_bytes());
+        file.extend_from_slice(&[0; 16]);
+
+        let mut relocs_off = 20 + sections.len() * 40;
+        for &(name, align, size, relocs) in sections {
+            let mut header = [0; 40];
+            header[..name.len()].copy_from_slice(name.as_bytes());
+            header[16..20].copy_from_slice(&size.to_le_bytes());
+            header[24..28].copy_from_slice(&(relocs_off as u32).to_le_bytes());
+            header[32..34].copy_from_slice(&(relocs.len() as u16).to_le_bytes());
+            header[36..40].copy_from_slice(&((align.trailing_zeros() + 1) << 20).to_le_bytes());
+            file.extend_from_slice(&header);
+            relocs_off += relocs.len() * 10;
+        }
+        for &(_, _, _, relocs) in sections {
+            for &(offset, typ) in relocs {
+                file.extend_from_slice(&offset.to_le_bytes());
+                file.extend_from_slice(&0u32.to_le_bytes());
+                file.extend_from_slice(&typ.to_le_bytes());
+            }
+        }
+
+        file
+    }
+
+    #[test]
+    fn collects_absolute_relocations_of_grouped_sections() {
+        let obj = object(&[
+            (".text", 16, 0x13, &[(0x1, IMAGE_REL_I386_DIR32), (0x8, IMAGE_REL_I386_REL32)]),
+            (".data", 4, 0x8, &[(0x0, IMAGE_REL_I386_DIR32)]),
+            (".text$mn", 16, 0x10, &[(0x4, IMAGE_REL_I386_DIR32)]),
+        ]);
+
+        assert_eq!(
+            collect_object_relocations(&obj, ".text", false, 0x1000).unwrap(),
+            highlow(&[0x1001])
+        );
+        // `.text$mn` follows `.text` at its own alignment
+        assert_eq!(
+            collect_object_relocations(&obj, ".text", true, 0x1000).unwrap(),
+            highlow(&[0x1001, 0x1024])
+        );
+        assert_eq!(collect_object_relocations(&obj, ".data", true, 0x3000).unwrap(), highlow(&[0x3000]));
+    }
+}
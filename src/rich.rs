@@ -0,0 +1,517 @@
+use goblin::pe::{header::RichHeader, PE};
+
+/// Rich header product IDs, with the release of Visual Studio the tool shipped in
+const PRODUCTS: &[(u16, &str, Option<&str>)] = &[
+    (0x0000, "Unknown", None),
+    (0x0001, "Import0", None),
+    (0x0002, "Linker510", Some("Visual C++ 5.0")),
+    (0x0003, "Cvtomf510", Some("Visual C++ 5.0")),
+    (0x0004, "Linker600", Some("Visual C++ 6.0")),
+    (0x0005, "Cvtomf600", Some("Visual C++ 6.0")),
+    (0x0006, "Cvtres500", Some("Visual C++ 5.0")),
+    (0x0007, "Utc11_Basic", Some("Visual C++ 5.0")),
+    (0x0008, "Utc11_C", Some("Visual C++ 5.0")),
+    (0x0009, "Utc12_Basic", Some("Visual C++ 6.0")),
+    (0x000A, "Utc12_C", Some("Visual C++ 6.0")),
+    (0x000B, "Utc12_CPP", Some("Visual C++ 6.0")),
+    (0x000C, "AliasObj60", Some("Visual C++ 6.0")),
+    (0x000D, "VisualBasic60", Some("Visual Basic 6.0")),
+    (0x000E, "Masm613", Some("MASM 6.13")),
+    (0x000F, "Masm710", Some("Visual Studio .NET 2003")),
+    (0x0010, "Linker511", Some("Visual C++ 5.0")),
+    (0x0011, "Cvtomf511", Some("Visual C++ 5.0")),
+    (0x0012, "Masm614", Some("MASM 6.14")),
+    (0x0013, "Linker512", Some("Visual C++ 5.0")),
+    (0x0014, "Cvtomf512", Some("Visual C++ 5.0")),
+    (0x0015, "Utc12_C_Std", Some("Visual C++ 6.0")),
+    (0x0016, "Utc12_CPP_Std", Some("Visual C++ 6.0")),
+    (0x0017, "Utc12_C_Book", Some("Visual C++ 6.0")),
+    (0x0018, "Utc12_CPP_Book", Some("Visual C++ 6.0")),
+    (0x0019, "Implib700", Some("Visual Studio .NET 2002")),
+    (0x001A, "Cvtomf700", Some("Visual Studio .NET 2002")),
+    (0x001B, "Utc13_Basic", Some("Visual Studio .NET 2002")),
+    (0x001C, "Utc13_C", Some("Visual Studio .NET 2002")),
+    (0x001D, "Utc13_CPP", Some("Visual Studio .NET 2002")),
+    (0x001E, "Linker610", Some("Visual C++ 6.0")),
+    (0x001F, "Cvtomf610", Some("Visual C++ 6.0")),
+    (0x0020, "Linker601", Some("Visual C++ 6.0")),
+    (0x0021, "Cvtomf601", Some("Visual C++ 6.0")),
+    (0x0022, "Utc12_1_Basic", Some("Visual C++ 6.0")),
+    (0x0023, "Utc12_1_C", Some("Visual C++ 6.0")),
+    (0x0024, "Utc12_1_CPP", Some("Visual C++ 6.0")),
+    (0x0025, "Linker620", Some("Visual C++ 6.0")),
+    (0x0026, "Cvtomf620", Some("Visual C++ 6.0")),
+    (0x0027, "AliasObj70", Some("Visual Studio .NET 2002")),
+    (0x0028, "Linker621", Some("Visual C++ 6.0")),
+    (0x0029, "Cvtomf621", Some("Visual C++ 6.0")),
+    (0x002A, "Masm615", Some("MASM 6.15")),
+    (0x002B, "Utc13_LTCG_C", Some("Visual Studio .NET 2002")),
+    (0x002C, "Utc13_LTCG_CPP", Some("Visual Studio .NET 2002")),
+    (0x002D, "Masm620", Some("MASM 6.20")),
+    (0x002E, "ILAsm100", None),
+    (0x002F, "Utc12_2_Basic", Some("Visual C++ 6.0")),
+    (0x0030, "Utc12_2_C", Some("Visual C++ 6.0")),
+    (0x0031, "Utc12_2_CPP", Some("Visual C++ 6.0")),
+    (0x0032, "Utc12_2_C_Std", Some("Visual C++ 6.0")),
+    (0x0033, "Utc12_2_CPP_Std", Some("Visual C++ 6.0")),
+    (0x0034, "Utc12_2_C_Book", Some("Visual C++ 6.0")),
+    (0x0035, "Utc12_2_CPP_Book", Some("Visual C++ 6.0")),
+    (0x0036, "Implib622", Some("Visual C++ 6.0")),
+    (0x0037, "Cvtomf622", Some("Visual C++ 6.0")),
+    (0x0038, "Cvtres501", Some("Visual C++ 5.0")),
+    (0x0039, "Utc13_C_Std", Some("Visual Studio .NET 2002")),
+    (0x003A, "Utc13_CPP_Std", Some("Visual Studio .NET 2002")),
+    (0x003B, "Cvtpgd1300", Some("Visual Studio .NET 2002")),
+    (0x003C, "Linker622", Some("Visual C++ 6.0")),
+    (0x003D, "Linker700", Some("Visual Studio .NET 2002")),
+    (0x003E, "Export622", Some("Visual C++ 6.0")),
+    (0x003F, "Export700", Some("Visual Studio .NET 2002")),
+    (0x0040, "Masm700", Some("Visual Studio .NET 2002")),
+    (0x0041, "Utc13_POGO_I_C", Some("Visual Studio .NET 2002")),
+    (0x0042, "Utc13_POGO_I_CPP", Some("Visual Studio .NET 2002")),
+    (0x0043, "Utc13_POGO_O_C", Some("Visual Studio .NET 2002")),
+    (0x0044, "Utc13_POGO_O_CPP", Some("Visual Studio .NET 2002")),
+    (0x0045, "Cvtres700", Some("Visual Studio .NET 2002")),
+    (0x0046, "Cvtres710p", Some("Visual Studio .NET 2003")),
+    (0x0047, "Linker710p", Some("Visual Studio .NET 2003")),
+    (0x0048, "Cvtomf710p", Some("Visual Studio .NET 2003")),
+    (0x0049, "Export710p", Some("Visual Studio .NET 2003")),
+    (0x004A, "Implib710p", Some("Visual Studio .NET 2003")),
+    (0x004B, "Masm710p", Some("Visual Studio .NET 2003")),
+    (0x004C, "Utc1310p_C", Some("Visual Studio .NET 2003")),
+    (0x004D, "Utc1310p_CPP", Some("Visual Studio .NET 2003")),
+    (0x004E, "Utc1310p_C_Std", Some("Visual Studio .NET 2003")),
+    (0x004F, "Utc1310p_CPP_Std", Some("Visual Studio .NET 2003")),
+    (0x0050, "Utc1310p_LTCG_C", Some("Visual Studio .NET 2003")),
+    (0x0051, "Utc1310p_LTCG_CPP", Some("Visual Studio .NET 2003")),
+    (0x0052, "Utc1310p_POGO_I_C", Some("Visual Studio .NET 2003")),
+    (0x0053, "Utc1310p_POGO_I_CPP", Some("Visual Studio .NET 2003")),
+    (0x0054, "Utc1310p_POGO_O_C", Some("Visual Studio .NET 2003")),
+    (0x0055, "Utc1310p_POGO_O_CPP", Some("Visual Studio .NET 2003")),
+    (0x0056, "Linker624", Some("Visual C++ 6.0")),
+    (0x0057, "Cvtomf624", Some("Visual C++ 6.0")),
+    (0x0058, "Export624", Some("Visual C++ 6.0")),
+    (0x0059, "Implib624", Some("Visual C++ 6.0")),
+    (0x005A, "Linker710", Some("Visual Studio .NET 2003")),
+    (0x005B, "Cvtomf710", Some("Visual Studio .NET 2003")),
+    (0x005C, "Export710", Some("Visual Studio .NET 2003")),
+    (0x005D, "Implib710", Some("Visual Studio .NET 2003")),
+    (0x005E, "Cvtres710", Some("Visual Studio .NET 2003")),
+    (0x005F, "Utc1310_C", Some("Visual Studio .NET 2003")),
+    (0x0060, "Utc1310_CPP", Some("Visual Studio .NET 2003")),
+    (0x0061, "Utc1310_C_Std", Some("Visual Studio .NET 2003")),
+    (0x0062, "Utc1310_CPP_Std", Some("Visual Studio .NET 2003")),
+    (0x0063, "Utc1310_LTCG_C", Some("Visual Studio .NET 2003")),
+    (0x0064, "Utc1310_LTCG_CPP", Some("Visual Studio .NET 2003")),
+    (0x0065, "Utc1310_POGO_I_C", Some("Visual Studio .NET 2003")),
+    (0x0066, "Utc1310_POGO_I_CPP", Some("Visual Studio .NET 2003")),
+    (0x0067, "Utc1310_POGO_O_C", Some("Visual Studio .NET 2003")),
+    (0x0068, "Utc1310_POGO_O_CPP", Some("Visual Studio .NET 2003")),
+    (0x0069, "AliasObj710", Some("Visual Studio .NET 2003")),
+    (0x006A, "AliasObj710p", Some("Visual Studio .NET 2003")),
+    (0x006B, "Cvtpgd1310", Some("Visual Studio .NET 2003")),
+    (0x006C, "Cvtpgd1310p", Some("Visual Studio .NET 2003")),
+    (0x006D, "Utc1400_C", Some("Visual Studio 2005")),
+    (0x006E, "Utc1400_CPP", Some("Visual Studio 2005")),
+    (0x006F, "Utc1400_C_Std", Some("Visual Studio 2005")),
+    (0x0070, "Utc1400_CPP_Std", Some("Visual Studio 2005")),
+    (0x0071, "Utc1400_LTCG_C", Some("Visual Studio 2005")),
+    (0x0072, "Utc1400_LTCG_CPP", Some("Visual Studio 2005")),
+    (0x0073, "Utc1400_POGO_I_C", Some("Visual Studio 2005")),
+    (0x0074, "Utc1400_POGO_I_CPP", Some("Visual Studio 2005")),
+    (0x0075, "Utc1400_POGO_O_C", Some("Visual Studio 2005")),
+    (0x0076, "Utc1400_POGO_O_CPP", Some("Visual Studio 2005")),
+    (0x0077, "Cvtpgd1400", Some("Visual Studio 2005")),
+    (0x0078, "Linker800", Some("Visual Studio 2005")),
+    (0x0079, "Cvtomf800", Some("Visual Studio 2005")),
+    (0x007A, "Export800", Some("Visual Studio 2005")),
+    (0x007B, "Implib800", Some("Visual Studio 2005")),
+    (0x007C, "Cvtres800", Some("Visual Studio 2005")),
+    (0x007D, "Masm800", Some("Visual Studio 2005")),
+    (0x007E, "AliasObj800", Some("Visual Studio 2005")),
+    (0x007F, "PhoenixPrerelease", None),
+    (0x0080, "Utc1400_CVTCIL_C", Some("Visual Studio 2005")),
+    (0x0081, "Utc1400_CVTCIL_CPP", Some("Visual Studio 2005")),
+    (0x0082, "Utc1400_LTCG_MSIL", Some("Visual Studio 2005")),
+    (0x0083, "Utc1500_C", Some("Visual Studio 2008")),
+    (0x0084, "Utc1500_CPP", Some("Visual Studio 2008")),
+    (0x0085, "Utc1500_C_Std", Some("Visual Studio 2008")),
+    (0x0086, "Utc1500_CPP_Std", Some("Visual Studio 2008")),
+    (0x0087, "Utc1500_CVTCIL_C", Some("Visual Studio 2008")),
+    (0x0088, "Utc1500_CVTCIL_CPP", Some("Visual Studio 2008")),
+    (0x0089, "Utc1500_LTCG_C", Some("Visual Studio 2008")),
+    (0x008A, "Utc1500_LTCG_CPP", Some("Visual Studio 2008")),
+    (0x008B, "Utc1500_LTCG_MSIL", Some("Visual Studio 2008")),
+    (0x008C, "Utc1500_POGO_I_C", Some("Visual Studio 2008")),
+    (0x008D, "Utc1500_POGO_I_CPP", Some("Visual Studio 2008")),
+    (0x008E, "Utc1500_POGO_O_C", Some("Visual Studio 2008")),
+    (0x008F, "Utc1500_POGO_O_CPP", Some("Visual Studio 2008")),
+    (0x0090, "Cvtpgd1500", Some("Visual Studio 2008")),
+    (0x0091, "Linker900", Some("Visual Studio 2008")),
+    (0x0092, "Export900", Some("Visual Studio 2008")),
+    (0x0093, "Implib900", Some("Visual Studio 2008")),
+    (0x0094, "Cvtres900", Some("Visual Studio 2008")),
+    (0x0095, "Masm900", Some("Visual Studio 2008")),
+    (0x0096, "AliasObj900", Some("Visual Studio 2008")),
+    (0x0097, "Resource", None),
+    (0x0098, "AliasObj1000", Some("Visual Studio 2010")),
+    (0x0099, "Cvtpgd1600", Some("Visual Studio 2010")),
+    (0x009A, "Cvtres1000", Some("Visual Studio 2010")),
+    (0x009B, "Export1000", Some("Visual Studio 2010")),
+    (0x009C, "Implib1000", Some("Visual Studio 2010")),
+    (0x009D, "Linker1000", Some("Visual Studio 2010")),
+    (0x009E, "Masm1000", Some("Visual Studio 2010")),
+    (0x009F, "Phx1600_C", Some("Visual Studio 2010")),
+    (0x00A0, "Phx1600_CPP", Some("Visual Studio 2010")),
+    (0x00A1, "Phx1600_CVTCIL_C", Some("Visual Studio 2010")),
+    (0x00A2, "Phx1600_CVTCIL_CPP", Some("Visual Studio 2010")),
+    (0x00A3, "Phx1600_LTCG_C", Some("Visual Studio 2010")),
+    (0x00A4, "Phx1600_LTCG_CPP", Some("Visual Studio 2010")),
+    (0x00A5, "Phx1600_LTCG_MSIL", Some("Visual Studio 2010")),
+    (0x00A6, "Phx1600_POGO_I_C", Some("Visual Studio 2010")),
+    (0x00A7, "Phx1600_POGO_I_CPP", Some("Visual Studio 2010")),
+    (0x00A8, "Phx1600_POGO_O_C", Some("Visual Studio 2010")),
+    (0x00A9, "Phx1600_POGO_O_CPP", Some("Visual Studio 2010")),
+    (0x00AA, "Utc1600_C", Some("Visual Studio 2010")),
+    (0x00AB, "Utc1600_CPP", Some("Visual Studio 2010")),
+    (0x00AC, "Utc1600_CVTCIL_C", Some("Visual Studio 2010")),
+    (0x00AD, "Utc1600_CVTCIL_CPP", Some("Visual Studio 2010")),
+    (0x00AE, "Utc1600_LTCG_C", Some("Visual Studio 2010")),
+    (0x00AF, "Utc1600_LTCG_CPP", Some("Visual Studio 2010")),
+    (0x00B0, "Utc1600_LTCG_MSIL", Some("Visual Studio 2010")),
+    (0x00B1, "Utc1600_POGO_I_C", Some("Visual Studio 2010")),
+    (0x00B2, "Utc1600_POGO_I_CPP", Some("Visual Studio 2010")),
+    (0x00B3, "Utc1600_POGO_O_C", Some("Visual Studio 2010")),
+    (0x00B4, "Utc1600_POGO_O_CPP", Some("Visual Studio 2010")),
+    (0x00B5, "AliasObj1010", Some("Visual Studio 2010")),
+    (0x00B6, "Cvtpgd1610", Some("Visual Studio 2010")),
+    (0x00B7, "Cvtres1010", Some("Visual Studio 2010")),
+    (0x00B8, "Export1010", Some("Visual Studio 2010")),
+    (0x00B9, "Implib1010", Some("Visual Studio 2010")),
+    (0x00BA, "Linker1010", Some("Visual Studio 2010")),
+    (0x00BB, "Masm1010", Some("Visual Studio 2010")),
+    (0x00BC, "Utc1610_C", Some("Visual Studio 2010")),
+    (0x00BD, "Utc1610_CPP", Some("Visual Studio 2010")),
+    (0x00BE, "Utc1610_CVTCIL_C", Some("Visual Studio 2010")),
+    (0x00BF, "Utc1610_CVTCIL_CPP", Some("Visual Studio 2010")),
+    (0x00C0, "Utc1610_LTCG_C", Some("Visual Studio 2010")),
+    (0x00C1, "Utc1610_LTCG_CPP", Some("Visual Studio 2010")),
+    (0x00C2, "Utc1610_LTCG_MSIL", Some("Visual Studio 2010")),
+    (0x00C3, "Utc1610_POGO_I_C", Some("Visual Studio 2010")),
+    (0x00C4, "Utc1610_POGO_I_CPP", Some("Visual Studio 2010")),
+    (0x00C5, "Utc1610_POGO_O_C", Some("Visual Studio 2010")),
+    (0x00C6, "Utc1610_POGO_O_CPP", Some("Visual Studio 2010")),
+    (0x00C7, "AliasObj1100", Some("Visual Studio 2012")),
+    (0x00C8, "Cvtpgd1700", Some("Visual Studio 2012")),
+    (0x00C9, "Cvtres1100", Some("Visual Studio 2012")),
+    (0x00CA, "Export1100", Some("Visual Studio 2012")),
+    (0x00CB, "Implib1100", Some("Visual Studio 2012")),
+    (0x00CC, "Linker1100", Some("Visual Studio 2012")),
+    (0x00CD, "Masm1100", Some("Visual Studio 2012")),
+    (0x00CE, "Utc1700_C", Some("Visual Studio 2012")),
+    (0x00CF, "Utc1700_CPP", Some("Visual Studio 2012")),
+    (0x00D0, "Utc1700_CVTCIL_C", Some("Visual Studio 2012")),
+    (0x00D1, "Utc1700_CVTCIL_CPP", Some("Visual Studio 2012")),
+    (0x00D2, "Utc1700_LTCG_C", Some("Visual Studio 2012")),
+    (0x00D3, "Utc1700_LTCG_CPP", Some("Visual Studio 2012")),
+    (0x00D4, "Utc1700_LTCG_MSIL", Some("Visual Studio 2012")),
+    (0x00D5, "Utc1700_POGO_I_C", Some("Visual Studio 2012")),
+    (0x00D6, "Utc1700_POGO_I_CPP", Some("Visual Studio 2012")),
+    (0x00D7, "Utc1700_POGO_O_C", Some("Visual Studio 2012")),
+    (0x00D8, "Utc1700_POGO_O_CPP", Some("Visual Studio 2012")),
+    (0x00D9, "AliasObj1200", Some("Visual Studio 2013")),
+    (0x00DA, "Cvtpgd1800", Some("Visual Studio 2013")),
+    (0x00DB, "Cvtres1200", Some("Visual Studio 2013")),
+    (0x00DC, "Export1200", Some("Visual Studio 2013")),
+    (0x00DD, "Implib1200", Some("Visual Studio 2013")),
+    (0x00DE, "Linker1200", Some("Visual Studio 2013")),
+    (0x00DF, "Masm1200", Some("Visual Studio 2013")),
+    (0x00E0, "Utc1800_C", Some("Visual Studio 2013")),
+    (0x00E1, "Utc1800_CPP", Some("Visual Studio 2013")),
+    (0x00E2, "Utc1800_CVTCIL_C", Some("Visual Studio 2013")),
+    (0x00E3, "Utc1800_CVTCIL_CPP", Some("Visual Studio 2013")),
+    (0x00E4, "Utc1800_LTCG_C", Some("Visual Studio 2013")),
+    (0x00E5, "Utc1800_LTCG_CPP", Some("Visual Studio 2013")),
+    (0x00E6, "Utc1800_LTCG_MSIL", Some("Visual Studio 2013")),
+    (0x00E7, "Utc1800_POGO_I_C", Some("Visual Studio 2013")),
+    (0x00E8, "Utc1800_POGO_I_CPP", Some("Visual Studio 2013")),
+    (0x00E9, "Utc1800_POGO_O_C", Some("Visual Studio 2013")),
+    (0x00EA, "Utc1800_POGO_O_CPP", Some("Visual Studio 2013")),
+    (0x00EB, "AliasObj1210", Some("Visual Studio 2013")),
+    (0x00EC, "Cvtpgd1810", Some("Visual Studio 2013")),
+    (0x00ED, "Cvtres1210", Some("Visual Studio 2013")),
+    (0x00EE, "Export1210", Some("Visual Studio 2013")),
+    (0x00EF, "Implib1210", Some("Visual Studio 2013")),
+    (0x00F0, "Linker1210", Some("Visual Studio 2013")),
+    (0x00F1, "Masm1210", Some("Visual Studio 2013")),
+    (0x00F2, "Utc1810_C", Some("Visual Studio 2013")),
+    (0x00F3, "Utc1810_CPP", Some("Visual Studio 2013")),
+    (0x00F4, "Utc1810_CVTCIL_C", Some("Visual Studio 2013")),
+    (0x00F5, "Utc1810_CVTCIL_CPP", Some("Visual Studio 2013")),
+    (0x00F6, "Utc1810_LTCG_C", Some("Visual Studio 2013")),
+    (0x00F7, "Utc1810_LTCG_CPP", Some("Visual Studio 2013")),
+    (0x00F8, "Utc1810_LTCG_MSIL", Some("Visual Studio 2013")),
+    (0x00F9, "Utc1810_POGO_I_C", Some("Visual Studio 2013")),
+    (0x00FA, "Utc1810_POGO_I_CPP", Some("Visual Studio 2013")),
+    (0x00FB, "Utc1810_POGO_O_C", Some("Visual Studio 2013")),
+    (0x00FC, "Utc1810_POGO_O_CPP", Some("Visual Studio 2013")),
+    (0x00FD, "AliasObj1400", Some("Visual Studio 2015 or later")),
+    (0x00FE, "Cvtpgd1900", Some("Visual Studio 2015 or later")),
+    (0x00FF, "Cvtres1400", Some("Visual Studio 2015 or later")),
+    (0x0100, "Export1400", Some("Visual Studio 2015 or later")),
+    (0x0101, "Implib1400", Some("Visual Studio 2015 or later")),
+    (0x0102, "Linker1400", Some("Visual Studio 2015 or later")),
+    (0x0103, "Masm1400", Some("Visual Studio 2015 or later")),
+    (0x0104, "Utc1900_C", Some("Visual Studio 2015 or later")),
+    (0x0105, "Utc1900_CPP", Some("Visual Studio 2015 or later")),
+    (0x0106, "Utc1900_CVTCIL_C", Some("Visual Studio 2015 or later")),
+    (0x0107, "Utc1900_CVTCIL_CPP", Some("Visual Studio 2015 or later")),
+    (0x0108, "Utc1900_LTCG_C", Some("Visual Studio 2015 or later")),
+    (0x0109, "Utc1900_LTCG_CPP", Some("Visual Studio 2015 or later")),
+    (0x010A, "Utc1900_LTCG_MSIL", Some("Visual Studio 2015 or later")),
+    (0x010B, "Utc1900_POGO_I_C", Some("Visual Studio 2015 or later")),
+    (0x010C, "Utc1900_POGO_I_CPP", Some("Visual Studio 2015 or later")),
+    (0x010D, "Utc1900_POGO_O_C", Some("Visual Studio 2015 or later")),
+    (0x010E, "Utc1900_POGO_O_CPP", Some("Visual Studio 2015 or later")),
+];
+
+/// where releases installed their compiler and assembler by default, as Windows paths
+const INSTALL_DIRS: &[(&str, &str)] = &[
+    ("Visual C++ 5.0", "C:\\Program Files\\DevStudio\\VC\\BIN"),
+    ("Visual C++ 6.0", "C:\\Program Files\\Microsoft Visual Studio\\VC98\\Bin"),
+    // MASM 6.1x was shipped as updates to Visual C++ 6.0 and installed over its own
+    ("MASM 6.13", "C:\\Program Files\\Microsoft Visual Studio\\VC98\\Bin"),
+    ("MASM 6.14", "C:\\Program Files\\Microsoft Visual Studio\\VC98\\Bin"),
+    ("MASM 6.15", "C:\\Program Files\\Microsoft Visual Studio\\VC98\\Bin"),
+    ("Visual Studio .NET 2002", "C:\\Program Files\\Microsoft Visual Studio .NET\\Vc7\\bin"),
+    ("Visual Studio .NET 2003", "C:\\Program Files\\Microsoft Visual Studio .NET 2003\\Vc7\\bin"),
+    ("Visual Studio 2005", "C:\\Program Files\\Microsoft Visual Studio 8\\VC\\bin"),
+    ("Visual Studio 2008", "C:\\Program Files\\Microsoft Visual Studio 9.0\\VC\\bin"),
+    ("Visual Studio 2010", "C:\\Program Files\\Microsoft Visual Studio 10.0\\VC\\bin"),
+    ("Visual Studio 2012", "C:\\Program Files\\Microsoft Visual Studio 11.0\\VC\\bin"),
+    ("Visual Studio 2013", "C:\\Program Files\\Microsoft Visual Studio 12.0\\VC\\bin"),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolKind {
+    Compiler,
+    Assembler,
+    Linker,
+    Resource,
+    Import,
+    Other,
+}
+
+/// a single Rich header record, the number of objects a specific tool build contributed
+#[derive(Debug)]
+pub struct RichEntry {
+    pub product_id: u16,
+    pub build: u16,
+    pub count: u32,
+}
+
+impl RichEntry {
+    pub fn product_name(&self) -> String {
+        match PRODUCTS.iter().find(|(id, _, _)| *id == self.product_id) {
+            Some((_, name, _)) => name.to_string(),
+            None => format!("Product{:04X}", self.product_id),
+        }
+    }
+
+    pub fn release(&self) -> Option<&'static str> {
+        PRODUCTS
+            .iter()
+            .find(|(id, _, _)| *id == self.product_id)
+            .and_then(|(_, _, release)| *release)
+    }
+
+    pub fn kind(&self) -> ToolKind {
+        let name = self.product_name();
+        if name.starts_with("Utc") || name.starts_with("Phx") {
+            ToolKind::Compiler
+        } else if name.starts_with("Masm") || name.starts_with("ILAsm") {
+            ToolKind::Assembler
+        } else if name.starts_with("Linker") {
+            ToolKind::Linker
+        } else if name.starts_with("Cvtres") || name == "Resource" {
+            ToolKind::Resource
+        } else if name.starts_with("Import") || name.starts_with("Implib") || name.starts_with("Export") {
+            ToolKind::Import
+        } else {
+            ToolKind::Other
+        }
+    }
+}
+
+/// decodes the Rich header of an executable, if it has one
+pub fn rich_entries(pe: &PE) -> Result<Option<Vec<RichEntry>>, String> {
+    pe.header.rich_header.as_ref().map(decode_entries).transpose()
+}
+
+fn decode_entries(rich_header: &RichHeader) -> Result<Vec<RichEntry>, String> {
+    rich_header
+        .metadatas()
+        .map(|metadata| {
+            metadata
+                .map(|metadata| RichEntry {
+                    product_id: metadata.product,
+                    build: metadata.build,
+                    count: metadata.use_count,
+                })
+                .map_err(|err| format!("failed to decode rich header entry ({})", err))
+        })
+        .collect()
+}
+
+/// the tool of a kind which most likely built the executable's own code, preferring tools from the
+/// same build as the linker over the statically linked runtime, then whichever contributed the most objects
+pub fn primary_tool(entries: &[RichEntry], kind: ToolKind) -> Option<&RichEntry> {
+    let linker_build = entries
+        .iter()
+        .filter(|entry| entry.kind() == ToolKind::Linker)
+        .max_by_key(|entry| entry.count)
+        .map(|entry| entry.build);
+
+    let of_kind = || entries.iter().filter(move |entry| entry.kind() == kind);
+
+    of_kind()
+        .filter(|entry| Some(entry.build) == linker_build)
+        .max_by_key(|entry| entry.count)
+        .or_else(|| of_kind().max_by_key(|entry| entry.count))
+}
+
+/// a rough number of units, one per object produced by a compiler or assembler
+pub fn estimated_unit_count(entries: &[RichEntry]) -> u32 {
+    entries
+        .iter()
+        .filter(|entry| matches!(entry.kind(), ToolKind::Compiler | ToolKind::Assembler))
+        .map(|entry| entry.count)
+        .sum()
+}
+
+/// summary lines describing the toolchain that built the executable
+pub fn toolchain_summary(entries: &[RichEntry]) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for (kind, label) in [
+        (ToolKind::Compiler, "compiler"),
+        (ToolKind::Assembler, "assembler"),
+        (ToolKind::Linker, "linker"),
+    ] {
+        if let Some(entry) = primary_tool(entries, kind) {
+            lines.push(format!(
+                "{}: {} ({} build {}, {} objects)",
+                label,
+                entry.release().unwrap_or("unknown release"),
+                entry.product_name(),
+                entry.build,
+                entry.count
+            ));
+        }
+    }
+
+    lines.push(format!("rough target unit count: {}", estimated_unit_count(entries)));
+
+    lines
+}
+
+/// config keys running the compiler and assembler that built the executable through wine, from where
+/// their release installed them, for the releases that had a fixed place
+pub fn suggested_toolchain(entries: &[RichEntry]) -> Vec<(&'static str, String)> {
+    let mut keys = Vec::new();
+
+    for (kind, path_key, wrapper_key, program) in [
+        (ToolKind::Compiler, "compiler_path", "compiler_wrapper", "CL.EXE"),
+        (ToolKind::Assembler, "assembler_path", "assembler_wrapper", "ML.EXE"),
+    ] {
+        let install_dir = primary_tool(entries, kind)
+            .and_then(RichEntry::release)
+            .and_then(|release| INSTALL_DIRS.iter().find(|(name, _)| *name == release))
+            .map(|(_, install_dir)| install_dir);
+        if let Some(install_dir) = install_dir {
+            keys.push((path_key, format!("{}\\{}", install_dir, program)));
+            keys.push((wrapper_key, "wine".to_string()));
+        }
+    }
+
+    keys
+}
+
+#[cfg(test)]
+mod tests {
+    use goblin::pe::header::RichHeader;
+
+    use super::{decode_entries, suggested_toolchain, toolchain_summary};
+
+    const KEY: u32 = 0x5A5A_1234;
+
+    /// a DOS header and stub holding a Rich header of `(product, build, count)` records, as the
+    /// linker writes it: XORed with a key and padded after the `DanS` marker
+    fn stub(records: &[(u16, u16, u32)]) -> Vec<u8> {
+        let mut rich = vec![u32::from_le_bytes(*b"DanS") ^ KEY, KEY, KEY, KEY];
+        for &(product, build, count) in records {
+            rich.push(((product as u32) << 16 | build as u32) ^ KEY);
+            rich.push(count ^ KEY);
+        }
+        rich.push(u32::from_le_bytes(*b"Rich"));
+        rich.push(KEY);
+
+        let mut file = vec![0; 0x80];
+        file[..2].copy_from_slice(b"MZ");
+        file.extend(rich.iter().flat_map(|value| value.to_le_bytes()));
+        let pe_off = file.len() as u32;
+        file[0x3C..0x40].copy_from_slice(&pe_off.to_le_bytes());
+        file.extend_from_slice(b"PE\0\0");
+        file
+    }
+
+    #[test]
+    fn decodes_rich_header_and_suggests_its_toolchain() {
+        let file = stub(&[
+            (0x0001, 0, 60),     // Import0
+            (0x0030, 8966, 40),  // Utc12_2_C, the statically linked runtime
+            (0x000A, 8168, 12),  // Utc12_C
+            (0x000E, 7299, 2),   // Masm613
+            (0x0004, 8168, 1),   // Linker600
+        ]);
+        let rich_header = RichHeader::parse(&file).unwrap().unwrap();
+        let entries = decode_entries(&rich_header).unwrap();
+
+        assert_eq!(
+            entries.iter().map(|entry| (entry.product_name(), entry.build, entry.count)).collect::<Vec<_>>(),
+            [
+                ("Import0".to_string(), 0, 60),
+                ("Utc12_2_C".to_string(), 8966, 40),
+                ("Utc12_C".to_string(), 8168, 12),
+                ("Masm613".to_string(), 7299, 2),
+                ("Linker600".to_string(), 8168, 1),
+            ]
+        );
+        // the compiler of the linker's build is preferred over the one with more objects
+        assert_eq!(
+            toolchain_summary(&entries),
+            [
+                "compiler: Visual C++ 6.0 (Utc12_C build 8168, 12 objects)",
+                "assembler: MASM 6.13 (Masm613 build 7299, 2 objects)",
+                "linker: Visual C++ 6.0 (Linker600 build 8168, 1 objects)",
+                "rough target unit count: 54",
+            ]
+        );
+        assert_eq!(
+            suggested_toolchain(&entries),
+            [
+                ("compiler_path", "C:\\Program Files\\Microsoft Visual Studio\\VC98\\Bin\\CL.EXE".to_string()),
+                ("compiler_wrapper", "wine".to_string()),
+                ("assembler_path", "C:\\Program Files\\Microsoft Visual Studio\\VC98\\Bin\\ML.EXE".to_string()),
+                ("assembler_wrapper", "wine".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn later_releases_get_no_suggestion() {
+        let file = stub(&[(0x0104, 24215, 30), (0x0102, 24215, 1)]);
+        let entries = decode_entries(&RichHeader::parse(&file).unwrap().unwrap()).unwrap();
+        assert!(suggested_toolchain(&entries).is_empty());
+    }
+}
@@ -0,0 +1,740 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    fs,
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::rsrc_text;
+
+const RESOURCE_DIRECTORY_SIZE: usize = 16;
+const RESOURCE_DIRECTORY_ENTRY_SIZE: usize = 8;
+const RESOURCE_DATA_ENTRY_SIZE: usize = 16;
+
+pub const RT_CURSOR: u16 = 1;
+pub const RT_BITMAP: u16 = 2;
+pub const RT_ICON: u16 = 3;
+pub const RT_MENU: u16 = 4;
+pub const RT_DIALOG: u16 = 5;
+pub const RT_STRING: u16 = 6;
+pub const RT_ACCELERATOR: u16 = 9;
+pub const RT_VERSION: u16 = 16;
+pub const RT_HTML: u16 = 23;
+pub const RT_MANIFEST: u16 = 24;
+
+const INDEX_FILE_NAME: &str = "resources.toml";
+
+/// what MSVC's linker fills alignment gaps and the end of the section with
+const PADDING_FILLER: &[u8] = b"PADDINGXXPADDING";
+
+/// how gaps between resource data and the end of the section are filled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Padding {
+    Zero,
+    PaddingXX,
+}
+
+/// a resource type, name or language, either an integer ID or a string name
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ResourceId {
+    // names sort before IDs, as in a resource directory
+    Name(String),
+    Id(u16),
+}
+
+#[derive(Debug)]
+pub struct Resource {
+    pub typ: ResourceId,
+    pub name: ResourceId,
+    pub language: u16,
+    pub codepage: u32,
+    pub data: Vec<u8>,
+}
+
+/// a resource directory, with resources in the order their data is laid out
+#[derive(Debug)]
+pub struct ResourceTable {
+    pub timestamp: u32,
+    pub characteristics: u32,
+    pub major_version: u16,
+    pub minor_version: u16,
+    pub data_alignment: usize,
+    pub padding: Padding,
+    pub resources: Vec<Resource>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ResourceIndexEntry {
+    #[serde(rename = "type")]
+    typ: ResourceId,
+    name: ResourceId,
+    language: u16,
+    codepage: u32,
+    file: String,
+}
+
+/// `resources.toml`, which lists every extracted resource file
+#[derive(Debug, Serialize, Deserialize)]
+struct ResourceIndex {
+    timestamp: u32,
+    characteristics: u32,
+    major_version: u16,
+    minor_version: u16,
+    data_alignment: usize,
+    padding: Padding,
+    resources: Vec<ResourceIndexEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct StringTable {
+    strings: Vec<String>,
+}
+
+fn read_u16(data: &[u8], off: usize) -> Result<u16, String> {
+    data.get(off..off + 2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| format!("resource data at `0x{:X}` is out of bounds", off))
+}
+
+fn read_u32(data: &[u8], off: usize) -> Result<u32, String> {
+    data.get(off..off + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+        .ok_or_else(|| format!("resource data at `0x{:X}` is out of bounds", off))
+}
+
+fn align(value: usize, alignment: usize) -> usize {
+    (value + alignment - 1) & !(alignment - 1)
+}
+
+fn fill_padding(gap: &mut [u8]) {
+    for (byte, filler) in gap.iter_mut().zip(PADDING_FILLER.iter().cycle()) {
+        *byte = *filler;
+    }
+}
+
+fn is_named(id: &ResourceId) -> bool {
+    matches!(id, ResourceId::Name(_))
+}
+
+fn type_dir_name(typ: &ResourceId) -> String {
+    match typ {
+        ResourceId::Id(1) => "cursor".to_string(),
+        ResourceId::Id(2) => "bitmap".to_string(),
+        ResourceId::Id(3) => "icon".to_string(),
+        ResourceId::Id(4) => "menu".to_string(),
+        ResourceId::Id(5) => "dialog".to_string(),
+        ResourceId::Id(6) => "string".to_string(),
+        ResourceId::Id(7) => "fontdir".to_string(),
+        ResourceId::Id(8) => "font".to_string(),
+        ResourceId::Id(9) => "accelerator".to_string(),
+        ResourceId::Id(10) => "rcdata".to_string(),
+        ResourceId::Id(11) => "messagetable".to_string(),
+        ResourceId::Id(12) => "group_cursor".to_string(),
+        ResourceId::Id(14) => "group_icon".to_string(),
+        ResourceId::Id(16) => "version".to_string(),
+        ResourceId::Id(23) => "html".to_string(),
+        ResourceId::Id(24) => "manifest".to_string(),
+        id => id_file_name(id),
+    }
+}
+
+fn id_file_name(id: &ResourceId) -> String {
+    match id {
+        ResourceId::Id(id) => id.to_string(),
+        ResourceId::Name(name) => name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect(),
+    }
+}
+
+/// parses the resource directory in `rsrc`, which is mapped at `rva`
+pub fn parse_resources(rsrc: &[u8], rva: u32) -> Result<ResourceTable, String> {
+    let mut table = ResourceTable {
+        timestamp: read_u32(rsrc, 4)?,
+        characteristics: read_u32(rsrc, 0)?,
+        major_version: read_u16(rsrc, 8)?,
+        minor_version: read_u16(rsrc, 10)?,
+        data_alignment: 8,
+        padding: Padding::Zero,
+        resources: Vec::new(),
+    };
+
+    let mut data_offs = Vec::new();
+    let mut dirs = VecDeque::from([(0usize, Vec::<ResourceId>::new())]);
+    while let Some((dir_off, path)) = dirs.pop_front() {
+        let entry_count = read_u16(rsrc, dir_off + 12)? as usize + read_u16(rsrc, dir_off + 14)? as usize;
+
+        for entry_i in 0..entry_count {
+            let entry_off = dir_off + RESOURCE_DIRECTORY_SIZE + entry_i * RESOURCE_DIRECTORY_ENTRY_SIZE;
+            let name = read_u32(rsrc, entry_off)?;
+            let off = read_u32(rsrc, entry_off + 4)?;
+
+            let id = if name & 0x8000_0000 != 0 {
+                let name_off = (name & 0x7FFF_FFFF) as usize;
+                let name_len = read_u16(rsrc, name_off)? as usize;
+                let name_units = (0..name_len)
+                    .map(|i| read_u16(rsrc, name_off + 2 + i * 2))
+                    .collect::<Result<Vec<u16>, String>>()?;
+
+                ResourceId::Name(String::from_utf16(&name_units).map_err(|err| {
+                    format!("resource name at `0x{:X}` is not valid UTF-16 ({})", name_off, err)
+                })?)
+            } else {
+                ResourceId::Id(name as u16)
+            };
+
+            let mut entry_path = path.clone();
+            entry_path.push(id);
+
+            if off & 0x8000_0000 != 0 {
+                let sub_dir_off = (off & 0x7FFF_FFFF) as usize;
+                if entry_path.len() > 2 || sub_dir_off <= dir_off {
+                    return Err(format!("malformed resource directory at `0x{:X}`", sub_dir_off));
+                }
+                dirs.push_back((sub_dir_off, entry_path));
+            } else {
+                if entry_path.len() != 3 {
+                    return Err(format!("resource data entry at `0x{:X}` is not at the language level", off));
+                }
+
+                let off = off as usize;
+                let data_off = (read_u32(rsrc, off)? as usize)
+                    .checked_sub(rva as usize)
+                    .ok_or_else(|| format!("resource data entry at `0x{:X}` points before the resource directory", off))?;
+                let data_size = read_u32(rsrc, off + 4)? as usize;
+                let data = rsrc
+                    .get(data_off..data_off + data_size)
+                    .ok_or_else(|| format!("resource data at `0x{:X}` is out of bounds", data_off))?;
+
+                let language = match entry_path[2] {
+                    ResourceId::Id(language) => language,
+                    ResourceId::Name(_) => return Err("named resource languages are not supported".to_string()),
+                };
+
+                data_offs.push(data_off);
+                table.resources.push(Resource {
+                    typ: entry_path[0].clone(),
+                    name: entry_path[1].clone(),
+                    language,
+                    codepage: read_u32(rsrc, off + 8)?,
+                    data: data.to_vec(),
+                });
+            }
+        }
+    }
+
+    // the index is kept in data order, which is the order the resources were compiled in
+    let mut order: Vec<usize> = (0..table.resources.len()).collect();
+    order.sort_by_key(|i| data_offs[*i]);
+    let mut resources: Vec<Option<Resource>> = table.resources.into_iter().map(Some).collect();
+    table.resources = order.iter().map(|i| resources[*i].take().unwrap()).collect();
+
+    // linkers differ in how they align and pad resource data, so pick whichever reproduces it
+    for (data_alignment, padding) in [8, 4, 2, 1]
+        .into_iter()
+        .flat_map(|alignment| [(alignment, Padding::Zero), (alignment, Padding::PaddingXX)])
+    {
+        table.data_alignment = data_alignment;
+        table.padding = padding;
+        if build_resources(&table, rva, rsrc.len()).as_deref() == Ok(rsrc) {
+            return Ok(table);
+        }
+    }
+
+    // gen could only build something else, so the unit is better left a copy unit
+    Err("no alignment and padding of the resource data reproduces the resource directory".to_string())
+}
+
+/// lays out a resource directory the way MSVC does: directory tables breadth first, then data
+/// entries, then name strings and finally the resource data, padded out to `size` bytes
+pub fn build_resources(table: &ResourceTable, rva: u32, size: usize) -> Result<Vec<u8>, String> {
+    let mut seen = HashSet::new();
+    for resource in table.resources.iter() {
+        if !seen.insert((&resource.typ, &resource.name, resource.language)) {
+            return Err(format!(
+                "duplicate resource with type `{:?}`, name `{:?}` and language `{}`",
+                resource.typ, resource.name, resource.language
+            ));
+        }
+    }
+
+    let mut types: Vec<&ResourceId> = table.resources.iter().map(|resource| &resource.typ).collect();
+    types.sort();
+    types.dedup();
+
+    let mut names: Vec<(&ResourceId, &ResourceId)> = table
+        .resources
+        .iter()
+        .map(|resource| (&resource.typ, &resource.name))
+        .collect();
+    names.sort();
+    names.dedup();
+
+    let mut leaves: Vec<(&ResourceId, &ResourceId, u16, usize)> = table
+        .resources
+        .iter()
+        .enumerate()
+        .map(|(i, resource)| (&resource.typ, &resource.name, resource.language, i))
+        .collect();
+    leaves.sort();
+
+    let dir_size = |entry_count: usize| RESOURCE_DIRECTORY_SIZE + entry_count * RESOURCE_DIRECTORY_ENTRY_SIZE;
+
+    // directory tables: root, then one per type, then one per type and name
+    let mut type_dir_offs = Vec::new();
+    let mut off = dir_size(types.len());
+    for typ in types.iter() {
+        type_dir_offs.push(off);
+        off += dir_size(names.iter().filter(|(i_typ, _)| i_typ == typ).count());
+    }
+    let mut name_dir_offs = Vec::new();
+    for name in names.iter() {
+        name_dir_offs.push(off);
+        off += dir_size(leaves.iter().filter(|(typ, i_name, _, _)| (*typ, *i_name) == *name).count());
+    }
+
+    let data_entries_off = off;
+    off += leaves.len() * RESOURCE_DATA_ENTRY_SIZE;
+
+    // name strings, in the order the directories reference them
+    let mut strings = Vec::new();
+    let mut string_offs = Vec::new();
+    for id in types.iter().copied().chain(names.iter().map(|(_, name)| *name)) {
+        if let ResourceId::Name(name) = id {
+            string_offs.push(off + strings.len());
+            let units: Vec<u16> = name.encode_utf16().collect();
+            strings.extend_from_slice(&(units.len() as u16).to_le_bytes());
+            for unit in units {
+                strings.extend_from_slice(&unit.to_le_bytes());
+            }
+        }
+    }
+    off += strings.len();
+    let strings_end = off;
+
+    let mut data_offs = Vec::new();
+    // the data is aligned relative to where it starts, as the linker places it as a whole
+    let data_start = align(off, 4);
+    for resource in table.resources.iter() {
+        off = data_start + align(off.max(data_start) - data_start, table.data_alignment);
+        data_offs.push(off);
+        off += resource.data.len();
+    }
+
+    let table_end = align(off, 4);
+    if table_end > size {
+        return Err(format!(
+            "resources are {} bytes, larger than the unit size of {} bytes",
+            table_end, size
+        ));
+    }
+
+    let mut rsrc = vec![0u8; size];
+    if table.padding == Padding::PaddingXX {
+        // every gap starts the filler over, including the end of the section
+        let mut gap_start = strings_end;
+        for (resource, data_off) in table.resources.iter().zip(data_offs.iter()) {
+            fill_padding(&mut rsrc[gap_start..*data_off]);
+            gap_start = data_off + resource.data.len();
+        }
+        fill_padding(&mut rsrc[gap_start..table_end]);
+        fill_padding(&mut rsrc[table_end..]);
+    }
+
+    let write_dir = |rsrc: &mut Vec<u8>, dir_off: usize, named_count: usize, id_count: usize| {
+        rsrc[dir_off..dir_off + 4].copy_from_slice(&table.characteristics.to_le_bytes());
+        rsrc[dir_off + 4..dir_off + 8].copy_from_slice(&table.timestamp.to_le_bytes());
+        rsrc[dir_off + 8..dir_off + 10].copy_from_slice(&table.major_version.to_le_bytes());
+        rsrc[dir_off + 10..dir_off + 12].copy_from_slice(&table.minor_version.to_le_bytes());
+        rsrc[dir_off + 12..dir_off + 14].copy_from_slice(&(named_count as u16).to_le_bytes());
+        rsrc[dir_off + 14..dir_off + 16].copy_from_slice(&(id_count as u16).to_le_bytes());
+    };
+
+    let mut string_offs = string_offs.into_iter();
+    let mut write_entry = |rsrc: &mut Vec<u8>, entry_off: usize, id: &ResourceId, target: u32| {
+        let name = match id {
+            ResourceId::Name(_) => 0x8000_0000 | string_offs.next().unwrap() as u32,
+            ResourceId::Id(id) => *id as u32,
+        };
+        rsrc[entry_off..entry_off + 4].copy_from_slice(&name.to_le_bytes());
+        rsrc[entry_off + 4..entry_off + 8].copy_from_slice(&target.to_le_bytes());
+    };
+
+    let named_types = types.iter().filter(|typ| is_named(typ)).count();
+    write_dir(&mut rsrc, 0, named_types, types.len() - named_types);
+    for (type_i, typ) in types.iter().enumerate() {
+        write_entry(
+            &mut rsrc,
+            dir_size(type_i),
+            typ,
+            0x8000_0000 | type_dir_offs[type_i] as u32,
+        );
+    }
+
+    let mut name_i = 0;
+    for (type_i, typ) in types.iter().enumerate() {
+        let type_names: Vec<&ResourceId> = names
+            .iter()
+            .filter(|(i_typ, _)| i_typ == typ)
+            .map(|(_, name)| *name)
+            .collect();
+        let named_names = type_names.iter().filter(|name| is_named(name)).count();
+
+        write_dir(&mut rsrc, type_dir_offs[type_i], named_names, type_names.len() - named_names);
+        for (entry_i, name) in type_names.iter().enumerate() {
+            write_entry(
+                &mut rsrc,
+                type_dir_offs[type_i] + dir_size(entry_i),
+                name,
+                0x8000_0000 | name_dir_offs[name_i + entry_i] as u32,
+            );
+        }
+        name_i += type_names.len();
+    }
+
+    let mut leaf_i = 0;
+    for (name_i, name) in names.iter().enumerate() {
+        let name_leaves: Vec<_> = leaves
+            .iter()
+            .filter(|(typ, i_name, _, _)| (*typ, *i_name) == *name)
+            .collect();
+
+        write_dir(&mut rsrc, name_dir_offs[name_i], 0, name_leaves.len());
+        for (entry_i, (_, _, language, _)) in name_leaves.iter().enumerate() {
+            write_entry(
+                &mut rsrc,
+                name_dir_offs[name_i] + dir_size(entry_i),
+                &ResourceId::Id(*language),
+                (data_entries_off + (leaf_i + entry_i) * RESOURCE_DATA_ENTRY_SIZE) as u32,
+            );
+        }
+        leaf_i += name_leaves.len();
+    }
+
+    for (leaf_i, (_, _, _, resource_i)) in leaves.iter().enumerate() {
+        let resource = &table.resources[*resource_i];
+        let entry_off = data_entries_off + leaf_i * RESOURCE_DATA_ENTRY_SIZE;
+        let data_rva = rva + data_offs[*resource_i] as u32;
+
+        rsrc[entry_off..entry_off + 4].copy_from_slice(&data_rva.to_le_bytes());
+        rsrc[entry_off + 4..entry_off + 8].copy_from_slice(&(resource.data.len() as u32).to_le_bytes());
+        rsrc[entry_off + 8..entry_off + 12].copy_from_slice(&resource.codepage.to_le_bytes());
+    }
+
+    let strings_off = data_entries_off + leaves.len() * RESOURCE_DATA_ENTRY_SIZE;
+    rsrc[strings_off..strings_off + strings.len()].copy_from_slice(&strings);
+
+    for (resource, data_off) in table.resources.iter().zip(data_offs.iter()) {
+        rsrc[*data_off..*data_off + resource.data.len()].copy_from_slice(&resource.data);
+    }
+
+    Ok(rsrc)
+}
+
+/// converts resource data into an editable file, returning its extension and contents
+fn resource_to_file(typ: &ResourceId, data: &[u8]) -> Option<(&'static str, Vec<u8>)> {
+    match typ {
+        ResourceId::Id(RT_BITMAP) => {
+            let header_size = read_u32(data, 0).ok()? as usize;
+            let bit_count = read_u16(data, 14).ok()? as usize;
+            let compression = read_u32(data, 16).ok()?;
+            let colors_used = read_u32(data, 32).ok()? as usize;
+
+            let mut palette_size = match colors_used {
+                0 if bit_count <= 8 => 1 << bit_count,
+                colors_used => colors_used,
+            } * 4;
+            // BI_BITFIELDS masks follow a plain BITMAPINFOHEADER
+            if compression == 3 && header_size == 40 {
+                palette_size += 12;
+            }
+
+            let mut file = b"BM".to_vec();
+            file.extend_from_slice(&(14 + data.len() as u32).to_le_bytes());
+            file.extend_from_slice(&0u32.to_le_bytes());
+            file.extend_from_slice(&((14 + header_size + palette_size) as u32).to_le_bytes());
+            file.extend_from_slice(data);
+            Some(("bmp", file))
+        }
+        ResourceId::Id(RT_ICON) | ResourceId::Id(RT_CURSOR) => {
+            let is_cursor = *typ == ResourceId::Id(RT_CURSOR);
+            let image = if is_cursor { data.get(4..)? } else { data };
+
+            let (width, height, bit_count) = if image.starts_with(b"\x89PNG") {
+                let width = u32::from_be_bytes(image.get(16..20)?.try_into().ok()?);
+                let height = u32::from_be_bytes(image.get(20..24)?.try_into().ok()?);
+                (width, height, 32)
+            } else {
+                (
+                    read_u32(image, 4).ok()?,
+                    read_u32(image, 8).ok()? / 2,
+                    read_u16(image, 14).ok()?,
+                )
+            };
+
+            let mut file = Vec::new();
+            file.extend_from_slice(&0u16.to_le_bytes());
+            file.extend_from_slice(&(if is_cursor { 2u16 } else { 1u16 }).to_le_bytes());
+            file.extend_from_slice(&1u16.to_le_bytes());
+            file.push(if width >= 256 { 0 } else { width as u8 });
+            file.push(if height >= 256 { 0 } else { height as u8 });
+            file.push(if bit_count < 8 { 1 << bit_count } else { 0 });
+            file.push(0);
+            if is_cursor {
+                // the hotspot takes the place of planes and bit count
+                file.extend_from_slice(&data[0..4]);
+            } else {
+                file.extend_from_slice(&1u16.to_le_bytes());
+                file.extend_from_slice(&bit_count.to_le_bytes());
+            }
+            file.extend_from_slice(&(image.len() as u32).to_le_bytes());
+            file.extend_from_slice(&22u32.to_le_bytes());
+            file.extend_from_slice(image);
+            Some((if is_cursor { "cur" } else { "ico" }, file))
+        }
+        ResourceId::Id(RT_STRING) => {
+            let mut strings = Vec::new();
+            let mut off = 0;
+            for _ in 0..16 {
+                let len = read_u16(data, off).ok()? as usize;
+                let units = (0..len)
+                    .map(|i| read_u16(data, off + 2 + i * 2))
+                    .collect::<Result<Vec<u16>, String>>()
+                    .ok()?;
+                strings.push(String::from_utf16(&units).ok()?);
+                off += 2 + len * 2;
+            }
+
+            let file = toml::to_string_pretty(&StringTable { strings }).ok()?;
+            Some(("toml", file.into_bytes()))
+        }
+        ResourceId::Id(RT_MENU | RT_DIALOG | RT_ACCELERATOR | RT_VERSION) => {
+            rsrc_text::resource_to_toml(typ, data).map(|text| ("toml", text.into_bytes()))
+        }
+        ResourceId::Id(RT_HTML) => Some(("html", data.to_vec())),
+        ResourceId::Id(RT_MANIFEST) => Some(("manifest", data.to_vec())),
+        _ => None,
+    }
+}
+
+/// converts an extracted resource file back into resource data
+fn resource_from_file(typ: &ResourceId, extension: &str, file: &[u8]) -> Result<Vec<u8>, String> {
+    match (typ, extension) {
+        (ResourceId::Id(RT_BITMAP), "bmp") => file
+            .get(14..)
+            .map(|data| data.to_vec())
+            .ok_or_else(|| "bitmap is missing its file header".to_string()),
+        (ResourceId::Id(RT_ICON), "ico") | (ResourceId::Id(RT_CURSOR), "cur") => {
+            if read_u16(file, 4)? != 1 {
+                return Err("icon and cursor files must hold exactly one image".to_string());
+            }
+
+            let size = read_u32(file, 6 + 8)? as usize;
+            let off = read_u32(file, 6 + 12)? as usize;
+            let image = file
+                .get(off..off + size)
+                .ok_or_else(|| "image data is out of bounds".to_string())?;
+
+            let mut data = Vec::new();
+            if *typ == ResourceId::Id(RT_CURSOR) {
+                data.extend_from_slice(&file[6 + 4..6 + 8]);
+            }
+            data.extend_from_slice(image);
+            Ok(data)
+        }
+        (ResourceId::Id(RT_STRING), "toml") => {
+            let text = std::str::from_utf8(file).map_err(|err| format!("string table is not UTF-8 ({})", err))?;
+            let table: StringTable =
+                toml::from_str(text).map_err(|err| format!("failed to parse string table ({})", err))?;
+            if table.strings.len() != 16 {
+                return Err(format!("string table has {} strings instead of 16", table.strings.len()));
+            }
+
+            let mut data = Vec::new();
+            for string in table.strings.iter() {
+                let units: Vec<u16> = string.encode_utf16().collect();
+                data.extend_from_slice(&(units.len() as u16).to_le_bytes());
+                for unit in units {
+                    data.extend_from_slice(&unit.to_le_bytes());
+                }
+            }
+            Ok(data)
+        }
+        (ResourceId::Id(RT_MENU | RT_DIALOG | RT_ACCELERATOR | RT_VERSION), "toml") => {
+            let text = std::str::from_utf8(file).map_err(|err| format!("resource is not UTF-8 ({})", err))?;
+            rsrc_text::resource_from_toml(typ, text)
+        }
+        _ => Ok(file.to_vec()),
+    }
+}
+
+/// writes every resource to its own file in `dir`, along with a `resources.toml` index
+pub fn extract_resources(table: &ResourceTable, dir: &Path) -> Result<(), String> {
+    let mut index = ResourceIndex {
+        timestamp: table.timestamp,
+        characteristics: table.characteristics,
+        major_version: table.major_version,
+        minor_version: table.minor_version,
+        data_alignment: table.data_alignment,
+        padding: table.padding,
+        resources: Vec::new(),
+    };
+
+    let mut files = HashSet::new();
+    for resource in table.resources.iter() {
+        // anything that would not convert back byte for byte is kept as raw data
+        let (extension, contents) = match resource_to_file(&resource.typ, &resource.data) {
+            Some((extension, contents))
+                if resource_from_file(&resource.typ, extension, &contents).as_deref() == Ok(&resource.data[..]) =>
+            {
+                (extension, contents)
+            }
+            _ => ("bin", resource.data.clone()),
+        };
+
+        // names that only differ in characters that can't be in a file name, or in case on file
+        // systems that ignore it, would share a file, so the later ones are numbered
+        let file = (1..)
+            .map(|n| {
+                let suffix = if n == 1 { String::new() } else { format!("-{}", n) };
+                format!(
+                    "{}/{}{}_{}.{}",
+                    type_dir_name(&resource.typ),
+                    id_file_name(&resource.name),
+                    suffix,
+                    resource.language,
+                    extension
+                )
+            })
+            .find(|file| !files.contains(&file.to_lowercase()))
+            .unwrap();
+        files.insert(file.to_lowercase());
+
+        let file_path = dir.join(&file);
+        fs::create_dir_all(file_path.parent().unwrap())
+            .map_err(|err| format!("failed to create resource directory ({})", err))?;
+        fs::write(&file_path, contents)
+            .map_err(|err| format!("failed to write resource file `{}` ({})", file_path.display(), err))?;
+
+        index.resources.push(ResourceIndexEntry {
+            typ: resource.typ.clone(),
+            name: resource.name.clone(),
+            language: resource.language,
+            codepage: resource.codepage,
+            file,
+        });
+    }
+
+    let index_path = dir.join(INDEX_FILE_NAME);
+    let index_string = toml::to_string_pretty(&index)
+        .map_err(|err| format!("failed to serialize resource index ({})", err))?;
+    fs::write(&index_path, index_string)
+        .map_err(|err| format!("failed to write resource index `{}` ({})", index_path.display(), err))
+}
+
+/// whether `dir` already holds extracted resources
+pub fn has_resources(dir: &Path) -> bool {
+    dir.join(INDEX_FILE_NAME).exists()
+}
+
+/// reads the resources extracted to `dir` back in
+pub fn load_resources(dir: &Path) -> Result<ResourceTable, String> {
+    let index_path = dir.join(INDEX_FILE_NAME);
+    let index_string = fs::read_to_string(&index_path)
+        .map_err(|err| format!("failed to open resource index `{}` ({})", index_path.display(), err))?;
+    let index: ResourceIndex = toml::from_str(&index_string)
+        .map_err(|err| format!("failed to parse resource index `{}` ({})", index_path.display(), err))?;
+
+    if !index.data_alignment.is_power_of_two() {
+        return Err(format!("resource data alignment `{}` is not a power of two", index.data_alignment));
+    }
+
+    let resources = index
+        .resources
+        .into_iter()
+        .map(|entry| {
+            let file_path = dir.join(&entry.file);
+            let file = fs::read(&file_path)
+                .map_err(|err| format!("failed to open resource file `{}` ({})", file_path.display(), err))?;
+            let extension = file_path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+
+            Ok(Resource {
+                data: resource_from_file(&entry.typ, extension, &file)
+                    .map_err(|err| format!("failed to read resource file `{}` ({})", file_path.display(), err))?,
+                typ: entry.typ,
+                name: entry.name,
+                language: entry.language,
+                codepage: entry.codepage,
+            })
+        })
+        .collect::<Result<Vec<Resource>, String>>()?;
+
+    Ok(ResourceTable {
+        timestamp: index.timestamp,
+        characteristics: index.characteristics,
+        major_version: index.major_version,
+        minor_version: index.minor_version,
+        data_alignment: index.data_alignment,
+        padding: index.padding,
+        resources,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use super::{
+        build_resources, extract_resources, load_resources, parse_resources, resource_from_file, resource_to_file,
+        ResourceId, RT_ACCELERATOR, RT_DIALOG, RT_MENU, RT_STRING, RT_VERSION,
+    };
+
+    /// a `.rsrc` section linked from `tests/data/resources.rc`
+    const RSRC: &[u8] = include_bytes!("../tests/data/resources.rsrc");
+    const RSRC_RVA: u32 = 0x3000;
+
+    #[test]
+    fn rebuilds_resources_byte_for_byte() {
+        let table = parse_resources(RSRC, RSRC_RVA).unwrap();
+        assert_eq!(table.resources.len(), 7);
+        assert_eq!(build_resources(&table, RSRC_RVA, RSRC.len()).unwrap(), RSRC);
+    }
+
+    #[test]
+    fn rebuilds_resources_from_extracted_files() {
+        let dir = env::temp_dir().join(format!("pod_rsrc_test_{}", std::process::id()));
+        let table = parse_resources(RSRC, RSRC_RVA).unwrap();
+        extract_resources(&table, &dir).unwrap();
+        let rebuilt = build_resources(&load_resources(&dir).unwrap(), RSRC_RVA, RSRC.len());
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(rebuilt.unwrap(), RSRC);
+    }
+
+    #[test]
+    fn text_forms_convert_back_to_the_same_data() {
+        let table = parse_resources(RSRC, RSRC_RVA).unwrap();
+        for typ in [RT_MENU, RT_DIALOG, RT_STRING, RT_ACCELERATOR, RT_VERSION] {
+            let typ = ResourceId::Id(typ);
+            let resources: Vec<_> = table.resources.iter().filter(|resource| resource.typ == typ).collect();
+            assert!(!resources.is_empty(), "no resource of type {:?}", typ);
+
+            for resource in resources {
+                let (extension, file) = resource_to_file(&typ, &resource.data).unwrap();
+                assert_eq!(extension, "toml");
+                assert_eq!(resource_from_file(&typ, extension, &file).unwrap(), resource.data);
+            }
+        }
+    }
+
+    #[test]
+    fn refuses_resources_it_cant_reproduce() {
+        let mut rsrc = RSRC.to_vec();
+        rsrc.extend_from_slice(b"trailing");
+        assert!(parse_resources(&rsrc, RSRC_RVA).is_err());
+    }
+}
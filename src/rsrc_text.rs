@@ -0,0 +1,993 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::hex,
+    rsrc::{ResourceId, RT_ACCELERATOR, RT_DIALOG, RT_MENU, RT_VERSION},
+};
+
+/// dialog style bit saying a font follows the title
+const DS_SETFONT: u32 = 0x40;
+/// first fields of a `DIALOGEX` template, version 1 and the signature
+const DIALOGEX_HEADER: [u16; 2] = [1, 0xFFFF];
+
+const MF_POPUP: u16 = 0x10;
+const MF_END: u16 = 0x80;
+const MENU_FLAGS: [(&str, u16); 8] = [
+    ("grayed", 0x1),
+    ("inactive", 0x2),
+    ("bitmap", 0x4),
+    ("checked", 0x8),
+    ("menubarbreak", 0x20),
+    ("menubreak", 0x40),
+    ("ownerdraw", 0x100),
+    ("help", 0x4000),
+];
+/// `MENUEX` item flags, the popup flag and the end flag
+const MENUEX_POPUP: u16 = 0x01;
+const MENUEX_END: u16 = 0x80;
+
+/// accelerator flag marking the last entry of a table
+const ACCEL_END: u16 = 0x80;
+const ACCELERATOR_FLAGS: [(&str, u16); 5] = [
+    ("virtkey", 0x1),
+    ("noinvert", 0x2),
+    ("shift", 0x4),
+    ("control", 0x8),
+    ("alt", 0x10),
+];
+
+const VS_FIXEDFILEINFO_SIGNATURE: u32 = 0xFEEF_04BD;
+const VS_FIXEDFILEINFO_VERSION: u32 = 0x0001_0000;
+const VS_FIXEDFILEINFO_SIZE: usize = 52;
+/// `wType` of version info blocks holding text, rather than binary data
+const VERSION_TEXT: u16 = 1;
+
+/// a `DIALOG` or `DIALOGEX` template
+#[derive(Debug, Serialize, Deserialize)]
+struct Dialog {
+    /// a `DIALOGEX` template, which adds help IDs, font weights and 32 bit control IDs
+    #[serde(default, skip_serializing_if = "is_default")]
+    extended: bool,
+    #[serde(with = "hex")]
+    style: u32,
+    #[serde(default, skip_serializing_if = "is_default", with = "hex")]
+    ex_style: u32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    help_id: u32,
+    x: i16,
+    y: i16,
+    width: i16,
+    height: i16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    menu: Option<ResourceId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    class: Option<ResourceId>,
+    #[serde(default)]
+    title: String,
+    /// present exactly when the style has `DS_SETFONT`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    font: Option<DialogFont>,
+    #[serde(default)]
+    controls: Vec<DialogControl>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DialogFont {
+    size: u16,
+    #[serde(default, skip_serializing_if = "is_default")]
+    weight: u16,
+    #[serde(default, skip_serializing_if = "is_default")]
+    italic: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    charset: u8,
+    typeface: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DialogControl {
+    id: u32,
+    /// a window class name, or an atom of a predefined one, 128 being a button, 129 an edit, 130 a
+    /// static, 131 a list box, 132 a scroll bar and 133 a combo box
+    class: ResourceId,
+    /// text, or the ID of an image resource
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<ResourceId>,
+    #[serde(with = "hex")]
+    style: u32,
+    #[serde(default, skip_serializing_if = "is_default", with = "hex")]
+    ex_style: u32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    help_id: u32,
+    x: i16,
+    y: i16,
+    width: i16,
+    height: i16,
+    /// creation data passed to the control
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    data: Vec<u8>,
+}
+
+/// a `MENU` or `MENUEX` template
+#[derive(Debug, Serialize, Deserialize)]
+struct Menu {
+    /// a `MENUEX` template, whose items have a type, state and help ID instead of flags
+    #[serde(default, skip_serializing_if = "is_default")]
+    extended: bool,
+    #[serde(default, skip_serializing_if = "is_default")]
+    help_id: u32,
+    items: Vec<MenuItem>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MenuItem {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    text: String,
+    /// the command ID, which popups of a `MENU` template don't have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    flags: Vec<String>,
+    #[serde(rename = "type", default, skip_serializing_if = "is_default", with = "hex")]
+    typ: u32,
+    #[serde(default, skip_serializing_if = "is_default", with = "hex")]
+    state: u32,
+    #[serde(default, skip_serializing_if = "is_default")]
+    help_id: u32,
+    /// the items of a popup
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    items: Option<Vec<MenuItem>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct AcceleratorTable {
+    accelerators: Vec<Accelerator>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Accelerator {
+    /// a virtual key code with the `virtkey` flag, a character otherwise
+    key: u16,
+    id: u16,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    flags: Vec<String>,
+}
+
+/// a `VERSIONINFO` resource
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionInfo {
+    file_version: String,
+    product_version: String,
+    #[serde(with = "hex")]
+    file_flags_mask: u32,
+    #[serde(with = "hex")]
+    file_flags: u32,
+    #[serde(with = "hex")]
+    file_os: u32,
+    #[serde(with = "hex")]
+    file_type: u32,
+    #[serde(default, skip_serializing_if = "is_default", with = "hex")]
+    file_subtype: u32,
+    #[serde(default, skip_serializing_if = "is_default", with = "hex")]
+    file_date: u64,
+    /// the `StringFileInfo` blocks, one per language and code page
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    string_tables: Vec<VersionStringTable>,
+    /// the `Translation` in `VarFileInfo`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    translations: Vec<VersionTranslation>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionStringTable {
+    /// language and code page as 8 hex digits, such as `040904B0`
+    block: String,
+    strings: Vec<VersionString>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionString {
+    key: String,
+    value: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VersionTranslation {
+    #[serde(with = "hex")]
+    language: u16,
+    codepage: u16,
+}
+
+/// a block of version info, as laid out in the resource
+struct VersionNode {
+    key: String,
+    text: bool,
+    value: Vec<u8>,
+    children: Vec<VersionNode>,
+}
+
+fn is_default<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// reads resource data front to back, with alignment relative to its start
+struct Reader<'a> {
+    data: &'a [u8],
+    off: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Reader<'a> {
+        Reader { data, off: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.off..self.off + len)?;
+        self.off += len;
+        Some(bytes)
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        Some(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        Some(u16::from_le_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        Some(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    /// a NUL terminated UTF-16 string
+    fn sz(&mut self) -> Option<String> {
+        let mut units = Vec::new();
+        loop {
+            match self.u16()? {
+                0 => return String::from_utf16(&units).ok(),
+                unit => units.push(unit),
+            }
+        }
+    }
+
+    /// a string or, after `0xFFFF`, an ordinal, `None` if it is empty
+    fn sz_or_ord(&mut self) -> Option<Option<ResourceId>> {
+        match self.data.get(self.off..self.off + 2)? {
+            [0xFF, 0xFF] => {
+                self.off += 2;
+                Some(Some(ResourceId::Id(self.u16()?)))
+            }
+            _ => {
+                let string = self.sz()?;
+                Some((!string.is_empty()).then_some(ResourceId::Name(string)))
+            }
+        }
+    }
+
+    fn align(&mut self) {
+        self.off = (self.off + 3) & !3;
+    }
+
+    fn is_done(&self) -> bool {
+        self.off >= self.data.len()
+    }
+}
+
+fn push_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn push_sz(out: &mut Vec<u8>, string: &str) {
+    for unit in string.encode_utf16().chain([0]) {
+        push_u16(out, unit);
+    }
+}
+
+fn push_sz_or_ord(out: &mut Vec<u8>, id: &Option<ResourceId>) {
+    match id {
+        Some(ResourceId::Id(id)) => {
+            push_u16(out, 0xFFFF);
+            push_u16(out, *id);
+        }
+        Some(ResourceId::Name(name)) => push_sz(out, name),
+        None => push_u16(out, 0),
+    }
+}
+
+fn pad(out: &mut Vec<u8>) {
+    out.resize((out.len() + 3) & !3, 0);
+}
+
+/// the names of the bits set in `flags`, or `None` if some of them have no name
+fn flag_names(mut flags: u16, names: &[(&str, u16)]) -> Option<Vec<String>> {
+    let mut set = Vec::new();
+    for (name, bit) in names.iter() {
+        if flags & bit != 0 {
+            set.push(name.to_string());
+            flags &= !bit;
+        }
+    }
+    (flags == 0).then_some(set)
+}
+
+fn flag_bits(set: &[String], names: &[(&str, u16)]) -> Result<u16, String> {
+    set.iter().try_fold(0, |flags, name| {
+        names
+            .iter()
+            .find(|(flag_name, _)| flag_name == name)
+            .map(|(_, bit)| flags | bit)
+            .ok_or_else(|| {
+                format!(
+                    "unknown flag `{}`, expected one of {}",
+                    name,
+                    names.iter().map(|(name, _)| format!("`{}`", name)).collect::<Vec<_>>().join(", ")
+                )
+            })
+    })
+}
+
+/// the TOML form of a dialog, menu, accelerator table or version info resource
+pub fn resource_to_toml(typ: &ResourceId, data: &[u8]) -> Option<String> {
+    match typ {
+        ResourceId::Id(RT_DIALOG) => toml::to_string_pretty(&read_dialog(data)?).ok(),
+        ResourceId::Id(RT_MENU) => toml::to_string_pretty(&read_menu(data)?).ok(),
+        ResourceId::Id(RT_ACCELERATOR) => toml::to_string_pretty(&read_accelerators(data)?).ok(),
+        ResourceId::Id(RT_VERSION) => toml::to_string_pretty(&read_version(data)?).ok(),
+        _ => None,
+    }
+}
+
+/// compiles the TOML form of a resource back into resource data
+pub fn resource_from_toml(typ: &ResourceId, text: &str) -> Result<Vec<u8>, String> {
+    let parse_err = |err: toml::de::Error| format!("failed to parse resource ({})", err);
+    match typ {
+        ResourceId::Id(RT_DIALOG) => write_dialog(&toml::from_str(text).map_err(parse_err)?),
+        ResourceId::Id(RT_MENU) => write_menu(&toml::from_str(text).map_err(parse_err)?),
+        ResourceId::Id(RT_ACCELERATOR) => write_accelerators(&toml::from_str(text).map_err(parse_err)?),
+        ResourceId::Id(RT_VERSION) => write_version(&toml::from_str(text).map_err(parse_err)?),
+        typ => Err(format!("resources of type `{:?}` have no TOML form", typ)),
+    }
+}
+
+fn read_dialog(data: &[u8]) -> Option<Dialog> {
+    let mut reader = Reader::new(data);
+    let extended = data.get(..4) == Some(&[1, 0, 0xFF, 0xFF]);
+
+    let (help_id, ex_style, style) = if extended {
+        reader.bytes(4)?;
+        (reader.u32()?, reader.u32()?, reader.u32()?)
+    } else {
+        let style = reader.u32()?;
+        (0, reader.u32()?, style)
+    };
+    let control_count = reader.u16()?;
+    let (x, y, width, height) = (reader.i16()?, reader.i16()?, reader.i16()?, reader.i16()?);
+    let menu = reader.sz_or_ord()?;
+    let class = reader.sz_or_ord()?;
+    let title = reader.sz()?;
+    let font = if style & DS_SETFONT != 0 {
+        let size = reader.u16()?;
+        let (weight, italic, charset) = if extended {
+            (reader.u16()?, reader.u8()? != 0, reader.u8()?)
+        } else {
+            (0, false, 0)
+        };
+        Some(DialogFont {
+            size,
+            weight,
+            italic,
+            charset,
+            typeface: reader.sz()?,
+        })
+    } else {
+        None
+    };
+
+    let mut controls = Vec::new();
+    for _ in 0..control_count {
+        reader.align();
+        let (help_id, ex_style, style) = if extended {
+            (reader.u32()?, reader.u32()?, reader.u32()?)
+        } else {
+            let style = reader.u32()?;
+            (0, reader.u32()?, style)
+        };
+        let (x, y, width, height) = (reader.i16()?, reader.i16()?, reader.i16()?, reader.i16()?);
+        let id = if extended { reader.u32()? } else { reader.u16()? as u32 };
+        let class = reader.sz_or_ord()??;
+        let title = reader.sz_or_ord()?;
+        let data_size = reader.u16()? as usize;
+
+        controls.push(DialogControl {
+            id,
+            class,
+            title,
+            style,
+            ex_style,
+            help_id,
+            x,
+            y,
+            width,
+            height,
+            data: reader.bytes(data_size)?.to_vec(),
+        });
+    }
+
+    Some(Dialog {
+        extended,
+        style,
+        ex_style,
+        help_id,
+        x,
+        y,
+        width,
+        height,
+        menu,
+        class,
+        title,
+        font,
+        controls,
+    })
+}
+
+fn write_dialog(dialog: &Dialog) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    if dialog.extended {
+        DIALOGEX_HEADER.iter().for_each(|&value| push_u16(&mut out, value));
+        push_u32(&mut out, dialog.help_id);
+        push_u32(&mut out, dialog.ex_style);
+        push_u32(&mut out, dialog.style);
+    } else {
+        if dialog.help_id != 0 {
+            return Err("only extended dialogs have a `help_id`".to_string());
+        }
+        push_u32(&mut out, dialog.style);
+        push_u32(&mut out, dialog.ex_style);
+    }
+    let control_count =
+        u16::try_from(dialog.controls.len()).map_err(|_| "dialog has too many controls".to_string())?;
+    push_u16(&mut out, control_count);
+    for value in [dialog.x, dialog.y, dialog.width, dialog.height] {
+        push_u16(&mut out, value as u16);
+    }
+    push_sz_or_ord(&mut out, &dialog.menu);
+    push_sz_or_ord(&mut out, &dialog.class);
+    push_sz(&mut out, &dialog.title);
+
+    match (&dialog.font, dialog.style & DS_SETFONT != 0) {
+        (Some(font), true) => {
+            push_u16(&mut out, font.size);
+            if dialog.extended {
+                push_u16(&mut out, font.weight);
+                out.push(font.italic as u8);
+                out.push(font.charset);
+            } else if font.weight != 0 || font.italic || font.charset != 0 {
+                return Err("only extended dialogs have a font `weight`, `italic` and `charset`".to_string());
+            }
+            push_sz(&mut out, &font.typeface);
+        }
+        (None, false) => (),
+        (Some(_), false) => return Err("dialog has a `font`, but its style lacks `DS_SETFONT` (0x40)".to_string()),
+        (None, true) => return Err("dialog style has `DS_SETFONT` (0x40), but there is no `font`".to_string()),
+    }
+
+    for (control_i, control) in dialog.controls.iter().enumerate() {
+        pad(&mut out);
+        if dialog.extended {
+            push_u32(&mut out, control.help_id);
+            push_u32(&mut out, control.ex_style);
+            push_u32(&mut out, control.style);
+        } else {
+            if control.help_id != 0 {
+                return Err(format!("control `{}` has a `help_id`, which only extended dialogs have", control_i));
+            }
+            push_u32(&mut out, control.style);
+            push_u32(&mut out, control.ex_style);
+        }
+        for value in [control.x, control.y, control.width, control.height] {
+            push_u16(&mut out, value as u16);
+        }
+        if dialog.extended {
+            push_u32(&mut out, control.id);
+        } else {
+            let id = u16::try_from(control.id)
+                .map_err(|_| format!("control `{}` has an `id` too large for a dialog that isn't extended", control_i))?;
+            push_u16(&mut out, id);
+        }
+        push_sz_or_ord(&mut out, &Some(control.class.clone()));
+        push_sz_or_ord(&mut out, &control.title);
+        let data_size = u16::try_from(control.data.len())
+            .map_err(|_| format!("control `{}` has too much creation `data`", control_i))?;
+        push_u16(&mut out, data_size);
+        out.extend_from_slice(&control.data);
+    }
+
+    Ok(out)
+}
+
+fn read_menu(data: &[u8]) -> Option<Menu> {
+    let mut reader = Reader::new(data);
+    match (reader.u16()?, reader.u16()?) {
+        (0, 0) => Some(Menu {
+            extended: false,
+            help_id: 0,
+            items: read_menu_items(&mut reader)?,
+        }),
+        // the offset from itself to the items, past the help ID
+        (1, 4) => Some(Menu {
+            extended: true,
+            help_id: reader.u32()?,
+            items: read_menuex_items(&mut reader)?,
+        }),
+        _ => None,
+    }
+}
+
+fn read_menu_items(reader: &mut Reader) -> Option<Vec<MenuItem>> {
+    let mut items = Vec::new();
+    loop {
+        let flags = reader.u16()?;
+        let popup = flags & MF_POPUP != 0;
+        let id = if popup { None } else { Some(reader.u16()? as u32) };
+        let text = reader.sz()?;
+        let children = if popup { Some(read_menu_items(reader)?) } else { None };
+
+        items.push(MenuItem {
+            text,
+            id,
+            flags: flag_names(flags & !(MF_POPUP | MF_END), &MENU_FLAGS)?,
+            typ: 0,
+            state: 0,
+            help_id: 0,
+            items: children,
+        });
+        if flags & MF_END != 0 {
+            return Some(items);
+        }
+    }
+}
+
+fn read_menuex_items(reader: &mut Reader) -> Option<Vec<MenuItem>> {
+    let mut items = Vec::new();
+    loop {
+        reader.align();
+        let (typ, state, id) = (reader.u32()?, reader.u32()?, reader.u32()?);
+        let flags = reader.u16()?;
+        if flags & !(MENUEX_POPUP | MENUEX_END) != 0 {
+            return None;
+        }
+        let text = reader.sz()?;
+        let (help_id, children) = if flags & MENUEX_POPUP != 0 {
+            reader.align();
+            (reader.u32()?, Some(read_menuex_items(reader)?))
+        } else {
+            (0, None)
+        };
+
+        items.push(MenuItem {
+            text,
+            id: Some(id),
+            flags: Vec::new(),
+            typ,
+            state,
+            help_id,
+            items: children,
+        });
+        if flags & MENUEX_END != 0 {
+            return Some(items);
+        }
+    }
+}
+
+fn write_menu(menu: &Menu) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    if menu.extended {
+        push_u16(&mut out, 1);
+        push_u16(&mut out, 4);
+        push_u32(&mut out, menu.help_id);
+        write_menuex_items(&mut out, &menu.items)?;
+    } else {
+        if menu.help_id != 0 {
+            return Err("only extended menus have a `help_id`".to_string());
+        }
+        push_u16(&mut out, 0);
+        push_u16(&mut out, 0);
+        write_menu_items(&mut out, &menu.items)?;
+    }
+
+    Ok(out)
+}
+
+fn write_menu_items(out: &mut Vec<u8>, items: &[MenuItem]) -> Result<(), String> {
+    if items.is_empty() {
+        return Err("menus and popups need at least one item".to_string());
+    }
+
+    for (item_i, item) in items.iter().enumerate() {
+        if item.typ != 0 || item.state != 0 || item.help_id != 0 {
+            return Err(format!("item `{}` has a `type`, `state` or `help_id`, which only extended menus have", item.text));
+        }
+
+        let mut flags = flag_bits(&item.flags, &MENU_FLAGS)?;
+        if item.items.is_some() {
+            flags |= MF_POPUP;
+        }
+        if item_i == items.len() - 1 {
+            flags |= MF_END;
+        }
+        push_u16(out, flags);
+
+        match &item.items {
+            Some(children) => {
+                if item.id.is_some() {
+                    return Err(format!("popup `{}` has an `id`, which only extended menus give popups", item.text));
+                }
+                push_sz(out, &item.text);
+                write_menu_items(out, children)?;
+            }
+            None => {
+                let id = u16::try_from(item.id.unwrap_or(0))
+                    .map_err(|_| format!("item `{}` has an `id` too large for a menu that isn't extended", item.text))?;
+                push_u16(out, id);
+                push_sz(out, &item.text);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn write_menuex_items(out: &mut Vec<u8>, items: &[MenuItem]) -> Result<(), String> {
+    if items.is_empty() {
+        return Err("menus and popups need at least one item".to_string());
+    }
+
+    for (item_i, item) in items.iter().enumerate() {
+        if !item.flags.is_empty() {
+            return Err(format!("item `{}` has `flags`, extended menus take a `type` and `state` instead", item.text));
+        }
+
+        pad(out);
+        push_u32(out, item.typ);
+        push_u32(out, item.state);
+        push_u32(out, item.id.unwrap_or(0));
+        let mut flags = if item.items.is_some() { MENUEX_POPUP } else { 0 };
+        if item_i == items.len() - 1 {
+            flags |= MENUEX_END;
+        }
+        push_u16(out, flags);
+        push_sz(out, &item.text);
+
+        if let Some(children) = &item.items {
+            pad(out);
+            push_u32(out, item.help_id);
+            write_menuex_items(out, children)?;
+        } else if item.help_id != 0 {
+            return Err(format!("item `{}` has a `help_id`, which only popups have", item.text));
+        }
+    }
+
+    Ok(())
+}
+
+fn read_accelerators(data: &[u8]) -> Option<AcceleratorTable> {
+    let mut reader = Reader::new(data);
+    let mut accelerators = Vec::new();
+    loop {
+        let (flags, key, id, padding) = (reader.u16()?, reader.u16()?, reader.u16()?, reader.u16()?);
+        if padding != 0 {
+            return None;
+        }
+
+        accelerators.push(Accelerator {
+            key,
+            id,
+            flags: flag_names(flags & !ACCEL_END, &ACCELERATOR_FLAGS)?,
+        });
+        if flags & ACCEL_END != 0 {
+            return Some(AcceleratorTable { accelerators });
+        }
+    }
+}
+
+fn write_accelerators(table: &AcceleratorTable) -> Result<Vec<u8>, String> {
+    if table.accelerators.is_empty() {
+        return Err("accelerator tables need at least one accelerator".to_string());
+    }
+
+    let mut out = Vec::new();
+    for (accelerator_i, accelerator) in table.accelerators.iter().enumerate() {
+        let mut flags = flag_bits(&accelerator.flags, &ACCELERATOR_FLAGS)?;
+        if accelerator_i == table.accelerators.len() - 1 {
+            flags |= ACCEL_END;
+        }
+        push_u16(&mut out, flags);
+        push_u16(&mut out, accelerator.key);
+        push_u16(&mut out, accelerator.id);
+        push_u16(&mut out, 0);
+    }
+
+    Ok(out)
+}
+
+/// a block of version info and its children, which end where its length says
+fn read_version_node(reader: &mut Reader) -> Option<VersionNode> {
+    let start = reader.off;
+    let length = reader.u16()? as usize;
+    let value_length = reader.u16()? as usize;
+    let text = match reader.u16()? {
+        0 => false,
+        VERSION_TEXT => true,
+        _ => return None,
+    };
+    let key = reader.sz()?;
+    reader.align();
+    // text values are measured in characters
+    let value = reader.bytes(if text { value_length * 2 } else { value_length })?.to_vec();
+
+    let mut children = Vec::new();
+    loop {
+        reader.align();
+        if reader.off >= start + length {
+            break;
+        }
+        children.push(read_version_node(reader)?);
+    }
+
+    Some(VersionNode {
+        key,
+        text,
+        value,
+        children,
+    })
+}
+
+/// writes a block of version info whose children are written by `children`, the length of a text
+/// value being counted in characters
+fn write_version_node(
+    out: &mut Vec<u8>,
+    node: &VersionNode,
+    children: impl FnOnce(&mut Vec<u8>) -> Result<(), String>,
+) -> Result<(), String> {
+    let start = out.len();
+    push_u16(out, 0);
+    let value_length = if node.text { node.value.len() / 2 } else { node.value.len() };
+    push_u16(out, u16::try_from(value_length).map_err(|_| format!("version info `{}` is too long", node.key))?);
+    push_u16(out, if node.text { VERSION_TEXT } else { 0 });
+    push_sz(out, &node.key);
+    pad(out);
+    out.extend_from_slice(&node.value);
+    children(out)?;
+
+    let length = u16::try_from(out.len() - start).map_err(|_| format!("version info `{}` is too long", node.key))?;
+    out[start..start + 2].copy_from_slice(&length.to_le_bytes());
+    Ok(())
+}
+
+fn text_value(value: &str) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    push_sz(&mut bytes, value);
+    bytes
+}
+
+fn read_version(data: &[u8]) -> Option<VersionInfo> {
+    let root = read_version_node(&mut Reader::new(data))?;
+    if root.key != "VS_VERSION_INFO" || root.text || root.value.len() != VS_FIXEDFILEINFO_SIZE {
+        return None;
+    }
+
+    let mut fixed = Reader::new(&root.value);
+    let fields: Vec<u32> = (0..VS_FIXEDFILEINFO_SIZE / 4).map(|_| fixed.u32()).collect::<Option<_>>()?;
+    if fields[0] != VS_FIXEDFILEINFO_SIGNATURE || fields[1] != VS_FIXEDFILEINFO_VERSION {
+        return None;
+    }
+    let version = |ms: u32, ls: u32| format!("{}.{}.{}.{}", ms >> 16, ms & 0xFFFF, ls >> 16, ls & 0xFFFF);
+
+    let mut info = VersionInfo {
+        file_version: version(fields[2], fields[3]),
+        product_version: version(fields[4], fields[5]),
+        file_flags_mask: fields[6],
+        file_flags: fields[7],
+        file_os: fields[8],
+        file_type: fields[9],
+        file_subtype: fields[10],
+        file_date: (fields[11] as u64) << 32 | fields[12] as u64,
+        string_tables: Vec::new(),
+        translations: Vec::new(),
+    };
+
+    // rc writes the strings before the translations, anything else is kept as it is
+    let mut children = root.children.iter().peekable();
+    if let Some(string_file_info) = children.next_if(|child| child.key == "StringFileInfo") {
+        if !string_file_info.text || !string_file_info.value.is_empty() {
+            return None;
+        }
+        for table in string_file_info.children.iter() {
+            if !table.text || !table.value.is_empty() {
+                return None;
+            }
+            let strings = table
+                .children
+                .iter()
+                .map(|string| {
+                    let mut value = Reader::new(&string.value);
+                    let text = value.sz()?;
+                    (string.text && string.children.is_empty() && value.is_done()).then(|| VersionString {
+                        key: string.key.clone(),
+                        value: text,
+                    })
+                })
+                .collect::<Option<_>>()?;
+            info.string_tables.push(VersionStringTable {
+                block: table.key.clone(),
+                strings,
+            });
+        }
+        if info.string_tables.is_empty() {
+            return None;
+        }
+    }
+    if let Some(var_file_info) = children.next_if(|child| child.key == "VarFileInfo") {
+        let [translation] = &var_file_info.children[..] else {
+            return None;
+        };
+        if !var_file_info.text
+            || !var_file_info.value.is_empty()
+            || translation.key != "Translation"
+            || translation.text
+            || !translation.children.is_empty()
+            || translation.value.is_empty()
+            || translation.value.len() % 4 != 0
+        {
+            return None;
+        }
+        let mut value = Reader::new(&translation.value);
+        while !value.is_done() {
+            info.translations.push(VersionTranslation {
+                language: value.u16()?,
+                codepage: value.u16()?,
+            });
+        }
+    }
+    if children.next().is_some() {
+        return None;
+    }
+
+    Some(info)
+}
+
+fn write_version(info: &VersionInfo) -> Result<Vec<u8>, String> {
+    let version = |version: &str, key: &str| -> Result<(u32, u32), String> {
+        let parts = version
+            .split('.')
+            .map(|part| part.parse::<u16>().map(u32::from))
+            .collect::<Result<Vec<u32>, _>>()
+            .ok()
+            .filter(|parts| parts.len() == 4)
+            .ok_or_else(|| format!("`{}` `{}` is not four numbers separated by dots", key, version))?;
+        Ok((parts[0] << 16 | parts[1], parts[2] << 16 | parts[3]))
+    };
+    let (file_ms, file_ls) = version(&info.file_version, "file_version")?;
+    let (product_ms, product_ls) = version(&info.product_version, "product_version")?;
+
+    let mut fixed = Vec::new();
+    for field in [
+        VS_FIXEDFILEINFO_SIGNATURE,
+        VS_FIXEDFILEINFO_VERSION,
+        file_ms,
+        file_ls,
+        product_ms,
+        product_ls,
+        info.file_flags_mask,
+        info.file_flags,
+        info.file_os,
+        info.file_type,
+        info.file_subtype,
+        (info.file_date >> 32) as u32,
+        info.file_date as u32,
+    ] {
+        push_u32(&mut fixed, field);
+    }
+
+    let node = |key: &str, text: bool, value: Vec<u8>| VersionNode {
+        key: key.to_string(),
+        text,
+        value,
+        children: Vec::new(),
+    };
+
+    let mut out = Vec::new();
+    write_version_node(&mut out, &node("VS_VERSION_INFO", false, fixed), |out| {
+        if !info.string_tables.is_empty() {
+            pad(out);
+            write_version_node(out, &node("StringFileInfo", true, Vec::new()), |out| {
+                for table in info.string_tables.iter() {
+                    pad(out);
+                    write_version_node(out, &node(&table.block, true, Vec::new()), |out| {
+                        for string in table.strings.iter() {
+                            pad(out);
+                            write_version_node(out, &node(&string.key, true, text_value(&string.value)), |_| Ok(()))?;
+                        }
+                        Ok(())
+                    })?;
+                }
+                Ok(())
+            })?;
+        }
+
+        if !info.translations.is_empty() {
+            let mut translations = Vec::new();
+            for translation in info.translations.iter() {
+                push_u16(&mut translations, translation.language);
+                push_u16(&mut translations, translation.codepage);
+            }
+
+            pad(out);
+            write_version_node(out, &node("VarFileInfo", true, Vec::new()), |out| {
+                pad(out);
+                write_version_node(out, &node("Translation", false, translations), |_| Ok(()))
+            })?;
+        }
+        Ok(())
+    })?;
+
+    Ok(out)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::{resource_from_toml, resource_to_toml};
+    use crate::rsrc::{ResourceId, RT_DIALOG, RT_MENU};
+
+    /// `MENUEX` templates, which llvm-rc can't compile, so aren't in `tests/data/resources.rc`
+    const MENUEX: &str = r#"
+extended = true
+help_id = 7
+
+[[items]]
+text = "&Edit"
+id = 0
+help_id = 3
+
+[[items.items]]
+text = "&Copy"
+id = 2001
+
+[[items.items]]
+id = 0
+type = "0x800"
+
+[[items.items]]
+text = "&Paste"
+id = 2002
+state = "0x3"
+"#;
+
+    #[test]
+    fn extended_menus_round_trip() {
+        let typ = ResourceId::Id(RT_MENU);
+        let data = resource_from_toml(&typ, MENUEX).unwrap();
+        let text = resource_to_toml(&typ, &data).unwrap();
+        assert_eq!(resource_from_toml(&typ, &text).unwrap(), data);
+        assert!(text.contains("extended = true"));
+        assert!(text.contains("type = \"0x800\""));
+    }
+
+    #[test]
+    fn reports_what_cant_be_written() {
+        let typ = ResourceId::Id(RT_MENU);
+        let err = resource_from_toml(&typ, "[[items]]\ntext = \"x\"\nid = 1\nstate = \"0x3\"\n").unwrap_err();
+        assert!(err.contains("only extended menus"), "{}", err);
+
+        let err = resource_from_toml(&ResourceId::Id(RT_DIALOG), "style = \"0x40\"\n").unwrap_err();
+        assert!(err.contains("missing field"), "{}", err);
+    }
+}
@@ -0,0 +1,280 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use crate::tool_diagnostic::{self, Severity};
+
+/// what `WINEDEBUG` is set to unless the config says otherwise, so wine's own messages stay out of the
+/// tools' output
+const DEFAULT_WINE_DEBUG: &str = "-all";
+
+/// environment variables MSVC tools search, which hold `;` separated directories
+const SEARCH_PATH_VARS: [&str; 2] = ["INCLUDE", "LIB"];
+
+/// a program of the toolchain, run directly or through a wrapper such as wine
+#[derive(Debug, Clone)]
+pub struct Tool {
+    pub path: String,
+    /// program the tool is run through, which takes Windows paths
+    pub wrapper: Option<String>,
+    /// `WINEPREFIX` for the wrapper, as an absolute path
+    pub wine_prefix: Option<PathBuf>,
+    pub wine_debug: Option<String>,
+}
+
+impl Tool {
+    /// a tool run directly
+    pub fn direct(path: &str) -> Tool {
+        Tool {
+            path: path.to_string(),
+            wrapper: None,
+            wine_prefix: None,
+            wine_debug: None,
+        }
+    }
+
+    /// a command running the tool, with the wrapper's environment set up
+    pub fn command(&self) -> Command {
+        self.command_with_env(|var| env::var(var).ok())
+    }
+
+    /// `command`, with the search paths handed to the wrapper read through `var` rather than from
+    /// pod's own environment
+    fn command_with_env(&self, var: impl Fn(&str) -> Option<String>) -> Command {
+        let Some(wrapper) = &self.wrapper else {
+            return Command::new(&self.path);
+        };
+
+        let mut command = Command::new(wrapper);
+        command
+            .arg(&self.path)
+            .env("WINEDEBUG", self.wine_debug.as_deref().unwrap_or(DEFAULT_WINE_DEBUG));
+        if let Some(wine_prefix) = &self.wine_prefix {
+            command.env("WINEPREFIX", wine_prefix);
+        }
+        for name in SEARCH_PATH_VARS {
+            if let Some(value) = var(name) {
+                command.env(name, self.search_path(&value));
+            }
+        }
+
+        command
+    }
+
+    /// runs `command`, made by `command`, writing everything it printed to `log_path` and printing the
+    /// diagnostics in it, and returns what it printed to stdout
+    pub fn run(&self, mut command: Command, log_path: &Path) -> Result<String, String> {
+        let output = command
+            .output()
+            .map_err(|err| format!("failed to execute `{}` ({})", self.path, err))?;
+        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+
+        if let Some(log_dir) = log_path.parent() {
+            fs::create_dir_all(log_dir).map_err(|err| format!("failed to create log directory ({})", err))?;
+        }
+        fs::write(
+            log_path,
+            format!("{:?}\n{}\n\nstdout:\n{}\nstderr:\n{}", command, output.status, stdout, stderr),
+        )
+        .map_err(|err| format!("failed to write log `{}` ({})", log_path.display(), err))?;
+
+        let mut diagnostics = tool_diagnostic::parse(&format!("{}\n{}", stdout, stderr), &self.path);
+        for diagnostic in diagnostics.iter_mut() {
+            diagnostic.file = diagnostic.file.take().map(|file| self.host_path(&file).display().to_string());
+        }
+        tool_diagnostic::print(&diagnostics, log_path);
+
+        if output.status.success() {
+            return Ok(stdout);
+        }
+
+        let errors = diagnostics.iter().filter(|diagnostic| diagnostic.severity == Severity::Error).count();
+        if errors > 0 {
+            Err(format!(
+                "{} error{}, see `{}`",
+                errors,
+                if errors == 1 { "" } else { "s" },
+                log_path.display()
+            ))
+        } else {
+            // nothing could be made of the output, so it is passed on as is, each part on its own lines
+            let output = [stdout.trim_end(), stderr.trim_end()]
+                .into_iter()
+                .filter(|output| !output.is_empty())
+                .map(|output| format!("{}\n", output))
+                .collect::<String>();
+            Err(format!("{}see `{}`", output, log_path.display()))
+        }
+    }
+
+    /// the command line `command` runs, for build files that run the tool themselves
+    pub fn command_line(&self) -> String {
+        let Some(wrapper) = &self.wrapper else {
+            return self.path.clone();
+        };
+
+        let mut line = format!("env WINEDEBUG={}", self.wine_debug.as_deref().unwrap_or(DEFAULT_WINE_DEBUG));
+        if let Some(wine_prefix) = &self.wine_prefix {
+            line += &format!(" WINEPREFIX={}", wine_prefix.display());
+        }
+        for var in SEARCH_PATH_VARS {
+            if let Ok(value) = env::var(var) {
+                line += &format!(" '{}={}'", var, self.search_path(&value));
+            }
+        }
+
+        format!("{} {} {}", line, wrapper, self.path)
+    }
+
+    /// `path` the way the tool sees it
+    pub fn arg_path(&self, path: &Path) -> String {
+        match &self.wrapper {
+            Some(_) => windows_path(path),
+            None => path.display().to_string(),
+        }
+    }
+
+    /// a path printed by the tool, the way pod sees it
+    pub fn host_path(&self, path: &str) -> PathBuf {
+        if self.wrapper.is_none() {
+            return PathBuf::from(path);
+        }
+
+        let unix_path = |path: &str| path.replace('\\', "/");
+        let host_path = match path.get(..2).map(|drive| drive.to_ascii_uppercase()).as_deref() {
+            Some("Z:") => PathBuf::from(unix_path(&path[2..])),
+            // the system drive of the prefix, where the toolchain is usually installed
+            Some("C:") => match self.wine_prefix.clone().or_else(default_wine_prefix) {
+                Some(wine_prefix) => wine_prefix.join("drive_c").join(unix_path(&path[2..]).trim_start_matches('/')),
+                None => return PathBuf::from(path),
+            },
+            _ => PathBuf::from(unix_path(path)),
+        };
+
+        // wine finds files whatever the case of their path, as Windows does
+        find_ignoring_case(&host_path).unwrap_or(host_path)
+    }
+
+    /// a `;` separated list of directories with the unix ones turned into Windows paths
+    fn search_path(&self, value: &str) -> String {
+        value
+            .split(';')
+            .map(|dir| if dir.starts_with('/') { self.arg_path(Path::new(dir)) } else { dir.to_string() })
+            .collect::<Vec<_>>()
+            .join(";")
+    }
+}
+
+/// `path` as a Windows path, through the `Z:` drive wine maps to `/` if it is absolute
+fn windows_path(path: &Path) -> String {
+    let path = path.display().to_string().replace('/', "\\");
+    if path.starts_with('\\') {
+        format!("Z:{}", path)
+    } else {
+        path
+    }
+}
+
+/// the existing file that is `path` if case is ignored
+fn find_ignoring_case(path: &Path) -> Option<PathBuf> {
+    let mut found = PathBuf::new();
+    for component in path.components() {
+        let exact = found.join(component);
+        if exact.exists() {
+            found = exact;
+            continue;
+        }
+
+        let name = component.as_os_str().to_string_lossy();
+        let dir = if found.as_os_str().is_empty() { Path::new(".") } else { &found };
+        let entry = fs::read_dir(dir)
+            .ok()?
+            .flatten()
+            .find(|entry| entry.file_name().to_string_lossy().eq_ignore_ascii_case(&name))?;
+        found.push(entry.file_name());
+    }
+
+    Some(found)
+}
+
+/// the prefix wine uses when `WINEPREFIX` isn't set
+fn default_wine_prefix() -> Option<PathBuf> {
+    env::var_os("WINEPREFIX")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".wine")))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use std::{env, fs, os::unix::fs::PermissionsExt, path::Path};
+
+    use super::Tool;
+
+    /// a wine stand-in that prints the arguments and environment it was run with
+    const WINE_STAND_IN: &str = "#!/bin/sh
+printf 'arg %s\\n' \"$@\"
+printf 'WINEPREFIX %s\\n' \"$WINEPREFIX\"
+printf 'WINEDEBUG %s\\n' \"$WINEDEBUG\"
+printf 'INCLUDE %s\\n' \"$INCLUDE\"
+";
+
+    #[test]
+    fn wrapped_tool_gets_windows_paths_and_wine_environment() {
+        let dir = env::temp_dir().join(format!("pod_tool_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let wine_path = dir.join("wine");
+        fs::write(&wine_path, WINE_STAND_IN).unwrap();
+        fs::set_permissions(&wine_path, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let wine_prefix = dir.join("prefix");
+        let tool = Tool {
+            path: "C:\\VC98\\BIN\\CL.EXE".to_string(),
+            wrapper: Some(wine_path.display().to_string()),
+            wine_prefix: Some(wine_prefix.clone()),
+            wine_debug: None,
+        };
+
+        let mut command = tool.command_with_env(|var| match var {
+            "INCLUDE" => Some("/opt/vc/include;C:\\VC98\\INCLUDE".to_string()),
+            _ => None,
+        });
+        command
+            .arg(format!("/Fo{}", tool.arg_path(Path::new("/project/build/f.obj"))))
+            .arg(tool.arg_path(Path::new("src/f.c")));
+        let output = command.output().unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8(output.stdout).unwrap().lines().collect::<Vec<_>>(),
+            [
+                "arg C:\\VC98\\BIN\\CL.EXE",
+                "arg /FoZ:\\project\\build\\f.obj",
+                "arg src\\f.c",
+                &format!("WINEPREFIX {}", wine_prefix.display()),
+                "WINEDEBUG -all",
+                "INCLUDE Z:\\opt\\vc\\include;C:\\VC98\\INCLUDE",
+            ]
+        );
+    }
+
+    #[test]
+    fn wrapped_tool_paths_map_back_to_host_paths() {
+        let tool = Tool {
+            path: "cl".to_string(),
+            wrapper: Some("wine".to_string()),
+            wine_prefix: Some("/nonexistent/prefix".into()),
+            wine_debug: None,
+        };
+
+        assert_eq!(tool.host_path("Z:\\project\\src\\f.c"), Path::new("/project/src/f.c"));
+        assert_eq!(
+            tool.host_path("C:\\VC98\\INCLUDE\\stdio.h"),
+            Path::new("/nonexistent/prefix/drive_c/VC98/INCLUDE/stdio.h")
+        );
+        assert_eq!(tool.host_path("inc\\h.h"), Path::new("inc/h.h"));
+    }
+}
@@ -0,0 +1,274 @@
+use std::{fmt, path::Path, sync::OnceLock};
+
+use clap::ValueEnum;
+use serde::Serialize;
+
+static MESSAGE_FORMAT: OnceLock<MessageFormat> = OnceLock::new();
+
+/// how diagnostics from the toolchain are printed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MessageFormat {
+    /// one line each, `file:line:column: severity code: message`
+    Human,
+    /// one JSON object per line, for editors
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+/// a message an assembler, compiler or linker printed about a file
+#[derive(Debug, Clone, Serialize)]
+pub struct ToolDiagnostic {
+    pub file: Option<String>,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub severity: Severity,
+    /// the tool's own code for the message, such as `C2065`
+    pub code: Option<String>,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+struct JsonDiagnostic<'a> {
+    #[serde(flatten)]
+    diagnostic: &'a ToolDiagnostic,
+    /// everything the tool printed, for when the message isn't enough
+    log: String,
+}
+
+/// sets the format diagnostics are printed in, once, before any command runs
+pub fn set_message_format(format: MessageFormat) {
+    MESSAGE_FORMAT.get_or_init(|| format);
+}
+
+pub fn message_format() -> MessageFormat {
+    *MESSAGE_FORMAT.get_or_init(|| MessageFormat::Human)
+}
+
+impl fmt::Display for ToolDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(file) = &self.file {
+            write!(f, "{}:", file)?;
+            if let Some(line) = self.line {
+                write!(f, "{}:", line)?;
+            }
+            if let Some(column) = self.column {
+                write!(f, "{}:", column)?;
+            }
+            write!(f, " ")?;
+        }
+
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        };
+        write!(f, "{}", severity)?;
+        if let Some(code) = &self.code {
+            write!(f, " {}", code)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+/// prints what a command is doing, to stdout, or to stderr in the JSON format so that stdout holds
+/// nothing but diagnostics
+pub fn status(message: impl fmt::Display) {
+    match message_format() {
+        MessageFormat::Human => println!("{}", message),
+        MessageFormat::Json => eprintln!("{}", message),
+    }
+}
+
+/// prints `diagnostics`, which came from the tool run logged to `log_path`, in the message format
+pub fn print(diagnostics: &[ToolDiagnostic], log_path: &Path) {
+    for diagnostic in diagnostics {
+        match message_format() {
+            MessageFormat::Human => println!("{}", diagnostic),
+            MessageFormat::Json => {
+                let json = JsonDiagnostic {
+                    diagnostic,
+                    log: log_path.display().to_string(),
+                };
+                if let Ok(json) = serde_json::to_string(&json) {
+                    println!("{}", json);
+                }
+            }
+        }
+    }
+}
+
+/// finds the diagnostics in what a tool printed, in the formats of MSVC, gcc and clang, and GNU ld,
+/// whose messages start with `program`, the name it was run as
+pub fn parse(output: &str, program: &str) -> Vec<ToolDiagnostic> {
+    output
+        .lines()
+        .filter_map(|line| parse_msvc(line).or_else(|| parse_gcc(line)).or_else(|| parse_ld(line, program)))
+        .collect()
+}
+
+/// `file(line[,column]) : severity code: message` or `tool : severity code: message`
+fn parse_msvc(line: &str) -> Option<ToolDiagnostic> {
+    let (severity, start, end) = find_severity(line, " ")?;
+    let before = line[..start].trim_end();
+    let origin = before.strip_suffix("Command line").unwrap_or(before).trim_end().strip_suffix(':')?.trim_end();
+    let (code, message) = line[end..].split_once(':')?;
+    let code = code.trim();
+    if code.is_empty() || code.contains(' ') {
+        return None;
+    }
+
+    let (file, line_number, column) = match origin.strip_suffix(')').and_then(|origin| origin.rsplit_once('(')) {
+        Some((file, position)) => {
+            let (line_number, column) = match position.split_once(',') {
+                Some((line_number, column)) => (line_number.parse().ok()?, Some(column.parse().ok()?)),
+                None => (position.parse().ok()?, None),
+            };
+            (Some(file.to_string()), Some(line_number), column)
+        }
+        // messages about no file in particular name the tool instead, such as `LINK`
+        None => (None, None, None),
+    };
+
+    Some(ToolDiagnostic {
+        file,
+        line: line_number,
+        column,
+        severity,
+        code: Some(code.to_string()),
+        message: message.trim().to_string(),
+    })
+}
+
+/// `file:line[:column]: severity: message`
+fn parse_gcc(line: &str) -> Option<ToolDiagnostic> {
+    let (severity, start, end) = find_severity(line, ": ")?;
+    let origin = line[..start].trim_end().strip_suffix(':')?;
+    let message = line[end..].trim();
+
+    let (rest, last) = origin.rsplit_once(':')?;
+    let last: usize = last.parse().ok()?;
+    let (file, line_number, column) = match rest.rsplit_once(':') {
+        Some((file, line_number)) if line_number.parse::<usize>().is_ok() => {
+            (file, line_number.parse().ok()?, Some(last))
+        }
+        _ => (rest, last, None),
+    };
+
+    Some(ToolDiagnostic {
+        file: Some(file.to_string()),
+        line: Some(line_number),
+        column,
+        severity,
+        code: None,
+        message: message.to_string(),
+    })
+}
+
+/// `program: [warning: ]message` or `object:(section+offset): message`
+fn parse_ld(line: &str, program: &str) -> Option<ToolDiagnostic> {
+    let program_name = Path::new(program).file_name()?.to_string_lossy().to_string();
+
+    let from_program = line.split_once(": ").filter(|(prefix, _)| {
+        *prefix == program || Path::new(prefix).file_name().is_some_and(|name| *name == *program_name)
+    });
+    let rest = from_program.map_or(line, |(_, rest)| rest);
+
+    let (file, message) = match rest.split_once(":(").and_then(|(file, rest)| Some((file, rest.split_once("): ")?.1))) {
+        Some((file, message)) => (Some(file.to_string()), message),
+        None if from_program.is_some() => (None, rest),
+        None => return None,
+    };
+
+    let (severity, message) = match message.strip_prefix("warning: ") {
+        Some(message) => (Severity::Warning, message),
+        None => (Severity::Error, message),
+    };
+
+    Some(ToolDiagnostic {
+        file,
+        line: None,
+        column: None,
+        severity,
+        code: None,
+        message: message.trim().to_string(),
+    })
+}
+
+/// the first severity in `line` that is followed by `after`, with where it starts and where what
+/// follows starts
+fn find_severity(line: &str, after: &str) -> Option<(Severity, usize, usize)> {
+    [
+        ("fatal error", Severity::Error),
+        ("error", Severity::Error),
+        ("warning", Severity::Warning),
+        ("note", Severity::Note),
+    ]
+    .iter()
+    .filter_map(|(word, severity)| {
+        let start = line.find(&format!(" {}{}", word, after))? + 1;
+        Some((*severity, start, start + word.len() + after.len()))
+    })
+    .min_by_key(|(_, start, _)| *start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse, Severity};
+
+    #[test]
+    fn parses_msvc_diagnostics() {
+        let output = "f.c\nsrc\\f.c(12) : error C2065: 'x' : undeclared identifier\n\
+                      LINK : fatal error LNK1181: cannot open input file 'a.obj'\n";
+        let diagnostics = parse(output, "cl");
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].file.as_deref(), Some("src\\f.c"));
+        assert_eq!(diagnostics[0].line, Some(12));
+        assert_eq!(diagnostics[0].column, None);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].code.as_deref(), Some("C2065"));
+        assert_eq!(diagnostics[0].message, "'x' : undeclared identifier");
+
+        assert_eq!(diagnostics[1].file, None);
+        assert_eq!(diagnostics[1].code.as_deref(), Some("LNK1181"));
+        assert_eq!(diagnostics[1].message, "cannot open input file 'a.obj'");
+    }
+
+    #[test]
+    fn parses_gcc_diagnostics() {
+        let output = "src/f.c: In function 'f':\n\
+                      src/f.c:3:5: warning: unused variable 'y' [-Wunused-variable]\n    3 |     int y;\n";
+        let diagnostics = parse(output, "gcc");
+        assert_eq!(diagnostics.len(), 1);
+
+        assert_eq!(diagnostics[0].file.as_deref(), Some("src/f.c"));
+        assert_eq!(diagnostics[0].line, Some(3));
+        assert_eq!(diagnostics[0].column, Some(5));
+        assert_eq!(diagnostics[0].severity, Severity::Warning);
+        assert_eq!(diagnostics[0].code, None);
+        assert_eq!(diagnostics[0].message, "unused variable 'y' [-Wunused-variable]");
+    }
+
+    #[test]
+    fn parses_ld_diagnostics() {
+        let output = "/usr/bin/ld: cannot find -lfoo: No such file or directory\n\
+                      build/a.obj:(.text+0x1): undefined reference to `_y'\n";
+        let diagnostics = parse(output, "ld");
+        assert_eq!(diagnostics.len(), 2);
+
+        assert_eq!(diagnostics[0].file, None);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].message, "cannot find -lfoo: No such file or directory");
+
+        assert_eq!(diagnostics[1].file.as_deref(), Some("build/a.obj"));
+        assert_eq!(diagnostics[1].severity, Severity::Error);
+        assert_eq!(diagnostics[1].message, "undefined reference to `_y'");
+    }
+}
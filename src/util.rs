@@ -1,9 +1,212 @@
-use std::fs;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
 
-use crate::config::Config;
+use goblin::pe::PE;
+
+use crate::{
+    config::{Config, Include, Section, Symbol},
+    config_edit,
+    diagnostic::Source,
+};
+
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+static BUILD_DIR_OVERRIDE: OnceLock<Option<PathBuf>> = OnceLock::new();
+static BUILD_DIR: OnceLock<PathBuf> = OnceLock::new();
+static VERSION: OnceLock<Option<String>> = OnceLock::new();
+
+/// moves into the project root, which is the directory of the config, so every path in the config
+/// resolves relative to it
+///
+/// `directory` is moved into first, like `make -C`, then the config is `config` if given, otherwise
+/// it is searched for in the current directory and its parents when `discover` is set, or else
+/// `pod.toml` in the current directory, `version` selects one of the versions the config describes
+pub fn enter_project(
+    directory: Option<&Path>,
+    config: Option<&Path>,
+    build_dir: Option<&Path>,
+    version: Option<&str>,
+    discover: bool,
+) -> Result<(), String> {
+    if let Some(directory) = directory {
+        env::set_current_dir(directory)
+            .map_err(|err| format!("failed to change to directory `{}` ({})", directory.display(), err))?;
+    }
+
+    let cwd = env::current_dir().map_err(|err| format!("failed to get current directory ({})", err))?;
+
+    let config_path = match config {
+        Some(config) => cwd.join(config),
+        None if discover => cwd
+            .ancestors()
+            .map(|dir| dir.join("pod.toml"))
+            .find(|config_path| config_path.is_file())
+            .ok_or_else(|| format!("could not find `pod.toml` in `{}` or any parent directory", cwd.display()))?,
+        None => cwd.join("pod.toml"),
+    };
+
+    let root = config_path.parent().unwrap_or(Path::new("/"));
+    env::set_current_dir(root).map_err(|err| format!("failed to change to project root `{}` ({})", root.display(), err))?;
+
+    // these are set only once, before any command runs
+    CONFIG_PATH.get_or_init(|| PathBuf::from(config_path.file_name().unwrap_or("pod.toml".as_ref())));
+    BUILD_DIR_OVERRIDE.get_or_init(|| build_dir.map(|build_dir| cwd.join(build_dir)));
+    VERSION.get_or_init(|| version.map(str::to_string));
+
+    Ok(())
+}
+
+/// the config file, relative to the project root
+pub fn config_path() -> &'static Path {
+    CONFIG_PATH.get_or_init(|| PathBuf::from("pod.toml"))
+}
+
+/// the build directory given on the command line, as an absolute path
+pub fn build_dir_override() -> Option<&'static Path> {
+    BUILD_DIR_OVERRIDE.get_or_init(|| None).as_deref()
+}
+
+/// the version selected on the command line
+pub fn version() -> Option<&'static str> {
+    VERSION.get_or_init(|| None).as_deref()
+}
+
+/// the directory build output goes into, relative to the project root, each version has its own
+pub fn build_dir() -> &'static Path {
+    BUILD_DIR.get_or_init(|| {
+        let build_dir = build_dir_override().map_or_else(|| PathBuf::from("build"), Path::to_path_buf);
+        match version() {
+            Some(version) => build_dir.join(version),
+            None => build_dir,
+        }
+    })
+}
+
+/// where everything a tool printed while building `name` is kept, in the build directory
+pub fn log_path(name: &str) -> PathBuf {
+    build_dir().join("logs").join(format!("{}.log", name))
+}
+
+/// `path` relative to the project root, which is how paths are written in the config, or as given
+/// if it is outside the project
+pub fn relative_to_root(path: &str) -> String {
+    env::current_dir()
+        .ok()
+        .and_then(|root| Some(Path::new(path).strip_prefix(root).ok()?.display().to_string()))
+        .unwrap_or_else(|| path.to_string())
+}
 
 pub fn get_config() -> Result<Config, String> {
-    let toml_string = fs::read_to_string("pod.toml").map_err(|err| format!("failed to open pod.toml ({})", err.to_string()))?;
+    Config::load(config_path(), version())
+}
+
+/// writes `config` back to pod.toml and the files it includes, keeping their comments and formatting
+pub fn write_config(config: &Config) -> Result<(), String> {
+    let main_source = Source {
+        path: config_path().display().to_string(),
+        text: None,
+    };
+    let sources = if config.sources.is_empty() { std::slice::from_ref(&main_source) } else { &config.sources[..] };
+
+    for (source_i, source) in sources.iter().enumerate() {
+        let sections: Vec<Section> = config
+            .sections
+            .iter()
+            .filter(|sec| sec.origin.source == source_i)
+            .cloned()
+            .collect();
+        let symbols: Vec<Symbol> = config
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.origin == source_i)
+            .cloned()
+            .collect();
+
+        let existing = fs::read_to_string(&source.path)
+            .map_err(|err| format!("failed to open `{}` ({})", source.path, err))?;
+        let toml_string = if source_i == 0 {
+            // with versions, the executable only exists in the selected version
+            let (executable, hash, output) = match config.version {
+                Some(_) => (String::new(), String::new(), None),
+                None => (config.executable.clone(), config.hash.clone(), config.output.clone()),
+            };
+
+            config_edit::merge_config(
+                &existing,
+                &Config {
+                    executable,
+                    hash,
+                    output,
+                    sections,
+                    symbols,
+                    ..config.clone()
+                },
+            )?
+        } else {
+            config_edit::merge_config(&existing, &Include { sections, symbols })?
+        };
+
+        fs::write(&source.path, toml_string).map_err(|err| format!("failed to write `{}` ({})", source.path, err))?;
+    }
+
+    Ok(())
+}
+
+/// parses an integer written either in decimal or as `0x` prefixed hex
+pub fn parse_int(value: &str) -> Result<u64, String> {
+    match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|err| err.to_string())
+}
+
+/// converts an RVA into an offset into the executable file, if it is backed by raw section data
+pub fn rva_to_file_offset(pe: &PE, rva: usize) -> Option<usize> {
+    pe.sections.iter().find_map(|sec| {
+        let sec_start = sec.virtual_address as usize;
+        if rva >= sec_start && rva < sec_start + sec.size_of_raw_data as usize {
+            Some(sec.pointer_to_raw_data as usize + rva - sec_start)
+        } else {
+            None
+        }
+    })
+}
+
+/// a MASM file which reproduces `data` byte for byte in the `POD` segment
+pub fn data_asm(data: &[u8]) -> String {
+    let mut asm = String::new();
+    asm += ".386\n.MODEL flat\nPOD SEGMENT BYTE\n";
+
+    // 49 is the max bytes MASM supports in one DB call for some reason
+    for chunk in data.chunks(49) {
+        asm += "DB ";
+
+        for byte in chunk.iter() {
+            asm += &byte.to_string();
+            asm += ", "
+        }
+
+        asm.pop();
+        asm.pop();
+        asm += "\n"
+    }
+
+    asm += "POD ENDS\nEND\n";
+
+    asm
+}
 
-    toml::from_str(&toml_string).map_err(|err| format!("failed to parse pod.toml ({})", err.to_string()))
-}
\ No newline at end of file
+/// converts an offset into the executable file into an RVA, if it is inside raw section data
+pub fn file_offset_to_rva(pe: &PE, offset: usize) -> Option<usize> {
+    pe.sections.iter().find_map(|sec| {
+        let data_start = sec.pointer_to_raw_data as usize;
+        if offset >= data_start && offset < data_start + sec.size_of_raw_data as usize {
+            Some(sec.virtual_address as usize + offset - data_start)
+        } else {
+            None
+        }
+    })
+}